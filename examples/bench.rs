@@ -0,0 +1,153 @@
+// Load generator for the server, to compare performance as features land.
+//
+// Boots the server in-process on an ephemeral port and hammers it from many
+// concurrent keep-alive connections, alternating between /echo/ and a file
+// GET, then reports the throughput and latency percentiles.
+//
+// Usage:
+//
+//  cargo run --release --example bench -- [--connections N] [--seconds N] > /dev/null
+//
+// The server logs every request to stdout, the report is written to stderr.
+
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use http_server_starter_rust::{Config, Server};
+
+const FILE_SIZE: usize = 64 * 1024;
+
+fn main() {
+    let mut connections = 32;
+    let mut seconds = 5;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().and_then(|v| v.parse().ok());
+        match (arg.as_str(), value) {
+            ("--connections", Some(n)) => connections = n,
+            ("--seconds", Some(n)) => seconds = n as u64,
+            _ => {
+                eprintln!("Usage: bench [--connections N] [--seconds N]");
+                process::exit(1);
+            }
+        }
+    }
+
+    let dir = env::temp_dir().join(format!("http-server-bench-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("file.bin"), vec![b'x'; FILE_SIZE]).unwrap();
+
+    let config = Config {
+        directory: Some(dir.to_string_lossy().into_owned()),
+        ..Config::default()
+    };
+    let server = Server::bind("127.0.0.1:0", config).expect("bind server");
+    let addr = server.local_addr().unwrap();
+    let shutdown = server.shutdown_handle().unwrap();
+    let server_thread = thread::spawn(move || server.run());
+
+    let deadline = Instant::now() + Duration::from_secs(seconds);
+    let clients: Vec<_> = (0..connections)
+        .map(|_| thread::spawn(move || client(addr, deadline)))
+        .collect();
+
+    let mut latencies: Vec<Duration> = clients
+        .into_iter()
+        .flat_map(|client| client.join().unwrap())
+        .collect();
+
+    shutdown.shutdown();
+    server_thread.join().unwrap();
+    let _ = fs::remove_dir_all(&dir);
+
+    latencies.sort();
+    let percentile = |p: usize| {
+        latencies
+            .get((latencies.len() * p / 100).min(latencies.len().saturating_sub(1)))
+            .copied()
+            .unwrap_or_default()
+    };
+
+    eprintln!("connections: {}", connections);
+    eprintln!("requests:    {}", latencies.len());
+    eprintln!(
+        "throughput:  {:.0} req/s",
+        latencies.len() as f64 / seconds as f64
+    );
+    eprintln!("latency p50: {:?}", percentile(50));
+    eprintln!("latency p90: {:?}", percentile(90));
+    eprintln!("latency p99: {:?}", percentile(99));
+}
+
+// Sends requests over a keep-alive connection until the deadline, returning
+// the latency of each one. A new connection is opened whenever the server
+// closes the current one.
+fn client(addr: std::net::SocketAddr, deadline: Instant) -> Vec<Duration> {
+    let connect = || {
+        let stream = TcpStream::connect(addr).expect("connect to server");
+        stream.set_nodelay(true).unwrap();
+        let reader = BufReader::new(stream.try_clone().unwrap());
+        (stream, reader)
+    };
+    let (mut stream, mut reader) = connect();
+    let mut latencies = vec![];
+
+    for i in 0.. {
+        if Instant::now() >= deadline {
+            break;
+        }
+
+        let path = if i % 2 == 0 {
+            "/echo/benchmark"
+        } else {
+            "/files/file.bin"
+        };
+
+        let start = Instant::now();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let closed = read_response(&mut reader);
+        latencies.push(start.elapsed());
+
+        if closed {
+            (stream, reader) = connect();
+        }
+    }
+
+    latencies
+}
+
+// Reads one response, using its Content-Length to find where it ends.
+// Returns whether the server is closing the connection.
+fn read_response(reader: &mut BufReader<TcpStream>) -> bool {
+    let mut content_length = 0;
+    let mut close = false;
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap() == 0 {
+            panic!("server closed the connection");
+        }
+        if line == "\r\n" {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            if key.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse().unwrap();
+            }
+            if key.eq_ignore_ascii_case("Connection") {
+                close = value.trim().eq_ignore_ascii_case("close");
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+
+    close
+}
@@ -0,0 +1,52 @@
+// Access log written to a file, one line per request in the Common Log
+// Format:
+//
+//  127.0.0.1 - - [06/Nov/1994:08:49:37 +0000] "GET /echo/abc HTTP/1.1" 200 3
+//
+// Reference: https://httpd.apache.org/docs/current/logs.html#common
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::httpdate;
+
+pub struct AccessLog {
+    // Connections log from their own threads, the lock keeps their lines
+    // from interleaving.
+    file: Mutex<File>,
+}
+
+impl AccessLog {
+    // Lines are appended, so the log survives restarts.
+    pub fn open(path: &Path) -> io::Result<AccessLog> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(AccessLog {
+            file: Mutex::new(file),
+        })
+    }
+
+    // `bytes` is the size of the response body, None when it isn't known
+    // up front.
+    pub fn log(&self, client: IpAddr, request_line: &str, status: u16, bytes: Option<u64>) {
+        let line = format!(
+            "{} - - [{}] \"{}\" {} {}\n",
+            client,
+            httpdate::format_common_log(SystemTime::now()),
+            request_line.escape_default(),
+            status,
+            bytes.map_or("-".to_string(), |bytes| bytes.to_string())
+        );
+
+        // The file isn't buffered, every line is written as a whole right
+        // away. Logging never fails a request.
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = file.write_all(line.as_bytes()) {
+            eprintln!("Error: writing access log: {}", e);
+        }
+    }
+}
@@ -0,0 +1,887 @@
+// Brotli compressed data format, used for the "br" content-coding.
+//
+// The encoder emits one compressed meta-block per 16MiB of input, each with a
+// single prefix code per alphabet (no block switches, no context modeling),
+// built from LZ77 matches found through the same hash chains as the DEFLATE
+// encoder. It doesn't get close to the reference encoder, but it beats gzip
+// on text thanks to the per-response prefix codes. The decoder handles what
+// the encoder produces plus a bit more (uncompressed and metadata
+// meta-blocks, simple prefix codes, the distance cache), it rejects block
+// switches, context maps and static dictionary references.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7932
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io;
+
+use crate::deflate::{
+    insert_hash, longest_match, BitReader, BitWriter, Huffman, HASH_BITS, MIN_MATCH, WINDOW_SIZE,
+};
+
+const MAX_META_BLOCK: usize = 1 << 24;
+const MAX_CHAIN: usize = 128;
+
+const LITERAL_ALPHABET: usize = 256;
+const COMMAND_ALPHABET: usize = 704;
+// 16 + NDIRECT + (48 << NPOSTFIX), with NPOSTFIX = NDIRECT = 0.
+const DISTANCE_ALPHABET: usize = 64;
+
+// Base lengths of the insert length codes and their extra bits.
+const INSERT_BASE: [u32; 24] = [
+    0, 1, 2, 3, 4, 5, 6, 8, 10, 14, 18, 26, 34, 50, 66, 98, 130, 194, 322, 578, 1090, 2114, 6210,
+    22594,
+];
+const INSERT_EXTRA: [u32; 24] = [
+    0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 7, 8, 9, 10, 12, 14, 24,
+];
+
+// Base lengths of the copy length codes and their extra bits.
+const COPY_BASE: [u32; 24] = [
+    2, 3, 4, 5, 6, 7, 8, 9, 10, 12, 14, 18, 22, 30, 38, 54, 70, 102, 134, 198, 326, 582, 1094, 2118,
+];
+const COPY_EXTRA: [u32; 24] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 7, 8, 9, 10, 24,
+];
+
+// Each block of 64 insert-and-copy codes covers 8 insert length codes by 8
+// copy length codes, starting at these. The first two blocks reuse the last
+// distance instead of coding one.
+const COMMAND_BLOCKS: [(usize, usize); 11] = [
+    (0, 0),
+    (0, 8),
+    (0, 0),
+    (0, 8),
+    (8, 0),
+    (8, 8),
+    (0, 16),
+    (16, 0),
+    (8, 16),
+    (16, 8),
+    (16, 16),
+];
+const IMPLICIT_DISTANCE_BLOCKS: usize = 2;
+
+// Order in which the code length code lengths are stored.
+const CODE_LENGTH_ORDER: [usize; 18] =
+    [1, 2, 3, 4, 0, 5, 17, 6, 16, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+// Code length symbol repeating a zero length 3 to 10 times.
+const REPEAT_ZERO: usize = 17;
+const REPEAT_PREVIOUS: usize = 16;
+
+// Inserts `literals`, then copies `copy_len` bytes from `distance` back. The
+// last command of a meta-block may only insert (a `copy_len` of 0).
+struct Command {
+    literals: std::ops::Range<usize>,
+    copy_len: usize,
+    distance: usize,
+}
+
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = BitWriter::new();
+
+    // WBITS = 16, a single 0 bit. That's a window of 2^16 - 16 bytes, enough
+    // for the distances of the matches found.
+    out.write_bits(0, 1);
+
+    for chunk in data.chunks(MAX_META_BLOCK) {
+        write_meta_block(&mut out, chunk);
+    }
+
+    // ISLAST and ISLASTEMPTY.
+    out.write_bits(1, 1);
+    out.write_bits(1, 1);
+    out.finish()
+}
+
+fn write_meta_block(out: &mut BitWriter, data: &[u8]) {
+    let commands = find_commands(data);
+
+    let mut literal_freqs = vec![0u32; LITERAL_ALPHABET];
+    let mut command_freqs = vec![0u32; COMMAND_ALPHABET];
+    let mut distance_freqs = vec![0u32; DISTANCE_ALPHABET];
+    for command in commands.iter() {
+        for &byte in &data[command.literals.clone()] {
+            literal_freqs[byte as usize] += 1;
+        }
+        command_freqs[command_symbol(command)] += 1;
+        if command.copy_len > 0 {
+            distance_freqs[distance_code(command.distance).0] += 1;
+        }
+    }
+
+    // ISLAST, MNIBBLES and MLEN - 1, ISUNCOMPRESSED.
+    let nibbles = match data.len() - 1 {
+        len if len < 1 << 16 => 4,
+        len if len < 1 << 20 => 5,
+        _ => 6,
+    };
+    out.write_bits(0, 1);
+    out.write_bits(nibbles - 4, 2);
+    out.write_bits((data.len() - 1) as u32, nibbles * 4);
+    out.write_bits(0, 1);
+
+    // A single block type for literals, commands and distances.
+    out.write_bits(0, 3);
+    // NPOSTFIX and NDIRECT.
+    out.write_bits(0, 2);
+    out.write_bits(0, 4);
+    // Context mode of the literal block type, unused with a single tree.
+    out.write_bits(0, 2);
+    // A single literal and distance prefix code, so no context maps.
+    out.write_bits(0, 1);
+    out.write_bits(0, 1);
+
+    let literal_codes = write_prefix_code(out, &literal_freqs, 8);
+    let command_codes = write_prefix_code(out, &command_freqs, 10);
+    let distance_codes = write_prefix_code(out, &distance_freqs, 6);
+
+    for command in commands.iter() {
+        let (code, len) = command_codes[command_symbol(command)];
+        out.write_code(code, len);
+
+        let insert_len = command.literals.len() as u32;
+        let insert = length_code(&INSERT_BASE, insert_len);
+        out.write_bits(insert_len - INSERT_BASE[insert], INSERT_EXTRA[insert]);
+        let copy_len = command.copy_len.max(2) as u32;
+        let copy = length_code(&COPY_BASE, copy_len);
+        out.write_bits(copy_len - COPY_BASE[copy], COPY_EXTRA[copy]);
+
+        for &byte in &data[command.literals.clone()] {
+            let (code, len) = literal_codes[byte as usize];
+            out.write_code(code, len);
+        }
+
+        if command.copy_len > 0 {
+            let (symbol, extra, extra_bits) = distance_code(command.distance);
+            let (code, len) = distance_codes[symbol];
+            out.write_code(code, len);
+            out.write_bits(extra, extra_bits);
+        }
+    }
+}
+
+fn find_commands(data: &[u8]) -> Vec<Command> {
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; WINDOW_SIZE];
+    let mut commands = vec![];
+    let mut literals = 0;
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let (len, distance) = longest_match(data, pos, &head, &prev, MAX_CHAIN);
+
+        if len >= MIN_MATCH {
+            commands.push(Command {
+                literals: literals..pos,
+                copy_len: len,
+                distance,
+            });
+            for p in pos..pos + len {
+                insert_hash(data, p, &mut head, &mut prev);
+            }
+            pos += len;
+            literals = pos;
+        } else {
+            insert_hash(data, pos, &mut head, &mut prev);
+            pos += 1;
+        }
+    }
+
+    if literals < data.len() {
+        commands.push(Command {
+            literals: literals..data.len(),
+            copy_len: 0,
+            distance: 0,
+        });
+    }
+
+    commands
+}
+
+fn length_code(base: &[u32; 24], len: u32) -> usize {
+    base.iter().rposition(|&b| b <= len).unwrap()
+}
+
+// Insert-and-copy symbol of the command, always with an explicit distance.
+fn command_symbol(command: &Command) -> usize {
+    let insert = length_code(&INSERT_BASE, command.literals.len() as u32);
+    let copy = length_code(&COPY_BASE, command.copy_len.max(2) as u32);
+
+    let block = COMMAND_BLOCKS
+        .iter()
+        .skip(IMPLICIT_DISTANCE_BLOCKS)
+        .position(|&(i, c)| i == insert & !7 && c == copy & !7)
+        .unwrap()
+        + IMPLICIT_DISTANCE_BLOCKS;
+
+    block * 64 + ((insert & 7) << 3) + (copy & 7)
+}
+
+// Distance symbol (with NPOSTFIX = NDIRECT = 0), its extra bits value and
+// their count.
+fn distance_code(distance: usize) -> (usize, u32, u32) {
+    let value = distance + 3;
+    let extra_bits = value.ilog2() - 1;
+    let high = (value >> extra_bits) & 1;
+    let symbol = 16 + 2 * (extra_bits as usize - 1) + high;
+    let extra = value - ((2 + high) << extra_bits);
+
+    (symbol, extra as u32, extra_bits)
+}
+
+// Writes the prefix code for the symbol frequencies, returning the code and
+// length of each symbol.
+fn write_prefix_code(out: &mut BitWriter, freqs: &[u32], symbol_bits: u32) -> Vec<(u32, u32)> {
+    let used: Vec<usize> = (0..freqs.len()).filter(|&s| freqs[s] > 0).collect();
+
+    // A simple prefix code with a single symbol, which takes no bits.
+    if used.len() < 2 {
+        out.write_bits(1, 2);
+        out.write_bits(0, 2);
+        out.write_bits(
+            used.first().copied().unwrap_or_default() as u32,
+            symbol_bits,
+        );
+        return vec![(0, 0); freqs.len()];
+    }
+
+    let lengths = code_lengths(freqs, 15);
+    let last = lengths.iter().rposition(|&len| len > 0).unwrap();
+
+    // The code lengths, with runs of zeros shortened.
+    let mut tokens: Vec<(usize, u32)> = vec![];
+    let mut i = 0;
+    while i <= last {
+        if lengths[i] != 0 {
+            tokens.push((lengths[i] as usize, 0));
+            i += 1;
+            continue;
+        }
+
+        let run = lengths[i..].iter().take_while(|&&len| len == 0).count();
+        push_zeros(&mut tokens, run);
+        i += run;
+    }
+
+    let mut code_length_freqs = [0u32; 18];
+    for &(symbol, _) in tokens.iter() {
+        code_length_freqs[symbol] += 1;
+    }
+    // A code with a single symbol is a special case, give it a companion so
+    // the code is a regular one.
+    if code_length_freqs.iter().filter(|&&freq| freq > 0).count() < 2 {
+        let unused = code_length_freqs
+            .iter()
+            .position(|&freq| freq == 0)
+            .unwrap();
+        code_length_freqs[unused] = 1;
+    }
+    let code_length_lengths = code_lengths(&code_length_freqs, 5);
+
+    // HSKIP = 0, then the code length code lengths until the code is
+    // complete.
+    out.write_bits(0, 2);
+    let mut space = 32;
+    for &symbol in CODE_LENGTH_ORDER.iter() {
+        let len = code_length_lengths[symbol];
+        let (bits, count) = match len {
+            0 => (0, 2),
+            1 => (7, 4),
+            2 => (3, 3),
+            3 => (2, 2),
+            4 => (1, 2),
+            _ => (15, 4),
+        };
+        out.write_bits(bits, count);
+
+        if len > 0 {
+            space -= 32 >> len;
+            if space == 0 {
+                break;
+            }
+        }
+    }
+
+    let code_length_codes = canonical_codes(&code_length_lengths);
+    for (symbol, extra) in tokens {
+        out.write_code(
+            code_length_codes[symbol],
+            code_length_lengths[symbol] as u32,
+        );
+        if symbol == REPEAT_ZERO {
+            out.write_bits(extra, 3);
+        }
+    }
+
+    canonical_codes(&lengths)
+        .into_iter()
+        .zip(lengths)
+        .map(|(code, len)| (code, len as u32))
+        .collect()
+}
+
+// Consecutive repeat codes build on each other: after a first run of
+// 3 + extra, each following one turns the run into (run - 2) * 8 + 3 + extra.
+fn push_zeros(tokens: &mut Vec<(usize, u32)>, mut run: usize) {
+    if run == 11 {
+        tokens.push((0, 0));
+        run -= 1;
+    }
+
+    if run < 3 {
+        tokens.extend(std::iter::repeat_n((0, 0), run));
+        return;
+    }
+
+    let start = tokens.len();
+    run -= 3;
+    loop {
+        tokens.push((REPEAT_ZERO, (run & 7) as u32));
+        run >>= 3;
+        if run == 0 {
+            break;
+        }
+        run -= 1;
+    }
+    tokens[start..].reverse();
+}
+
+// Huffman code lengths for the frequencies, at most `max_len` bits long.
+// Frequencies are flattened until the tree is shallow enough.
+fn code_lengths(freqs: &[u32], max_len: u8) -> Vec<u8> {
+    let mut freqs = freqs.to_vec();
+
+    loop {
+        let lengths = huffman_lengths(&freqs);
+        if lengths.iter().all(|&len| len <= max_len) {
+            return lengths;
+        }
+
+        for freq in freqs.iter_mut().filter(|freq| **freq > 0) {
+            *freq = freq.div_ceil(2);
+        }
+    }
+}
+
+fn huffman_lengths(freqs: &[u32]) -> Vec<u8> {
+    let mut heap = BinaryHeap::new();
+    let mut parents: Vec<usize> = vec![];
+    let mut leaves = vec![];
+
+    for (symbol, &freq) in freqs.iter().enumerate() {
+        if freq > 0 {
+            heap.push(Reverse((freq as u64, parents.len())));
+            leaves.push((symbol, parents.len()));
+            parents.push(usize::MAX);
+        }
+    }
+
+    while heap.len() > 1 {
+        let Reverse((a_freq, a)) = heap.pop().unwrap();
+        let Reverse((b_freq, b)) = heap.pop().unwrap();
+        let node = parents.len();
+        parents.push(usize::MAX);
+        parents[a] = node;
+        parents[b] = node;
+        heap.push(Reverse((a_freq + b_freq, node)));
+    }
+
+    let mut lengths = vec![0u8; freqs.len()];
+    for (symbol, mut node) in leaves {
+        while parents[node] != usize::MAX {
+            lengths[symbol] += 1;
+            node = parents[node];
+        }
+    }
+    lengths
+}
+
+// Codes are assigned in order of length, then of symbol, as in DEFLATE.
+fn canonical_codes(lengths: &[u8]) -> Vec<u32> {
+    let mut counts = [0u32; 16];
+    for &len in lengths {
+        counts[len as usize] += 1;
+    }
+    counts[0] = 0;
+
+    let mut next = [0u32; 16];
+    let mut code = 0;
+    for len in 1..16 {
+        code = (code + counts[len - 1]) << 1;
+        next[len] = code;
+    }
+
+    lengths
+        .iter()
+        .map(|&len| {
+            if len == 0 {
+                return 0;
+            }
+            let code = next[len as usize];
+            next[len as usize] += 1;
+            code
+        })
+        .collect()
+}
+
+// Decompresses a Brotli stream, see the top of this module for what is
+// supported.
+pub fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut input = BitReader::new(data);
+    let mut out: Vec<u8> = vec![];
+    // Most recent first.
+    let mut distances = [4, 11, 15, 16];
+
+    let window = (1 << read_window_bits(&mut input)?) - 16;
+
+    loop {
+        let last = input.read_bits(1)? == 1;
+        if last && input.read_bits(1)? == 1 {
+            break;
+        }
+
+        let nibbles = match input.read_bits(2)? {
+            3 => {
+                skip_metadata(&mut input)?;
+                continue;
+            }
+            n => n + 4,
+        };
+        let len = input.read_bits(nibbles * 4)? as usize + 1;
+
+        if !last && input.read_bits(1)? == 1 {
+            input.align();
+            out.extend_from_slice(input.read_bytes(len)?);
+            continue;
+        }
+
+        decompress_meta_block(&mut input, &mut out, len, window, &mut distances)?;
+
+        if last {
+            break;
+        }
+    }
+
+    Ok(out)
+}
+
+fn read_window_bits(input: &mut BitReader) -> io::Result<u32> {
+    if input.read_bits(1)? == 0 {
+        return Ok(16);
+    }
+
+    match input.read_bits(3)? {
+        0 => match input.read_bits(3)? {
+            0 => Ok(17),
+            1 => Err(invalid("large windows are not supported")),
+            n => Ok(8 + n),
+        },
+        n => Ok(17 + n),
+    }
+}
+
+fn skip_metadata(input: &mut BitReader) -> io::Result<()> {
+    if input.read_bits(1)? != 0 {
+        return Err(invalid("reserved bit set"));
+    }
+
+    let bytes = input.read_bits(2)?;
+    let len = match bytes {
+        0 => 0,
+        n => input.read_bits(n * 8)? as usize + 1,
+    };
+    input.align();
+    input.read_bytes(len)?;
+    Ok(())
+}
+
+fn decompress_meta_block(
+    input: &mut BitReader,
+    out: &mut Vec<u8>,
+    len: usize,
+    window: usize,
+    distances: &mut [usize; 4],
+) -> io::Result<()> {
+    for _ in 0..3 {
+        if read_count(input)? != 1 {
+            return Err(invalid("block switches are not supported"));
+        }
+    }
+
+    let postfix = input.read_bits(2)?;
+    let direct = input.read_bits(4)? << postfix;
+    // Context mode, irrelevant with a single literal prefix code.
+    input.read_bits(2)?;
+    if read_count(input)? != 1 || read_count(input)? != 1 {
+        return Err(invalid("context maps are not supported"));
+    }
+
+    let literals = read_prefix_code(input, LITERAL_ALPHABET)?;
+    let commands = read_prefix_code(input, COMMAND_ALPHABET)?;
+    let distance_alphabet = 16 + direct as usize + (48 << postfix);
+    let distance_codes = read_prefix_code(input, distance_alphabet)?;
+
+    let end = out.len() + len;
+    while out.len() < end {
+        let symbol = commands.decode(input)? as usize;
+        let (insert_block, copy_block) = COMMAND_BLOCKS[symbol >> 6];
+        let insert = insert_block + ((symbol >> 3) & 7);
+        let copy = copy_block + (symbol & 7);
+
+        let insert_len = (INSERT_BASE[insert] + input.read_bits(INSERT_EXTRA[insert])?) as usize;
+        let copy_len = (COPY_BASE[copy] + input.read_bits(COPY_EXTRA[copy])?) as usize;
+
+        if out.len() + insert_len > end {
+            return Err(invalid("insert past the end of the meta-block"));
+        }
+        for _ in 0..insert_len {
+            out.push(literals.decode(input)? as u8);
+        }
+        if out.len() == end {
+            break;
+        }
+
+        let distance = if symbol >> 6 < IMPLICIT_DISTANCE_BLOCKS {
+            distances[0]
+        } else {
+            let code = distance_codes.decode(input)? as u32;
+            let distance = read_distance(input, code, postfix, direct, distances)?;
+            if code != 0 {
+                distances.rotate_right(1);
+                distances[0] = distance;
+            }
+            distance
+        };
+
+        if distance > out.len().min(window) {
+            return Err(invalid("static dictionary references are not supported"));
+        }
+        if out.len() + copy_len > end {
+            return Err(invalid("copy past the end of the meta-block"));
+        }
+
+        // Copy byte by byte since the match may overlap itself.
+        let start = out.len() - distance;
+        for i in 0..copy_len {
+            out.push(out[start + i]);
+        }
+    }
+
+    Ok(())
+}
+
+// Values from 1 to 256, e.g. NBLTYPES.
+fn read_count(input: &mut BitReader) -> io::Result<u32> {
+    if input.read_bits(1)? == 0 {
+        return Ok(1);
+    }
+
+    match input.read_bits(3)? {
+        0 => Ok(2),
+        n => Ok((1 << n) + 1 + input.read_bits(n)?),
+    }
+}
+
+fn read_distance(
+    input: &mut BitReader,
+    code: u32,
+    postfix: u32,
+    direct: u32,
+    distances: &[usize; 4],
+) -> io::Result<usize> {
+    let distance = match code {
+        0..=3 => distances[code as usize] as i64,
+        4..=9 => {
+            let delta = [-1, 1, -2, 2, -3, 3][code as usize - 4];
+            distances[0] as i64 + delta
+        }
+        10..=15 => {
+            let delta = [-1, 1, -2, 2, -3, 3][code as usize - 10];
+            distances[1] as i64 + delta
+        }
+        _ if code < 16 + direct => (code - 15) as i64,
+        _ => {
+            let code = code - direct - 16;
+            let extra_bits = 1 + (code >> (postfix + 1));
+            let extra = input.read_bits(extra_bits)? as i64;
+            let high = (code >> postfix) & 1;
+            let low = code & ((1 << postfix) - 1);
+            let offset = ((2 + high as i64) << extra_bits) - 4;
+            ((offset + extra) << postfix) + low as i64 + direct as i64 + 1
+        }
+    };
+
+    if distance <= 0 {
+        return Err(invalid("invalid distance"));
+    }
+    Ok(distance as usize)
+}
+
+enum PrefixCode {
+    // Decoded without reading any bit.
+    Single(u16),
+    Huffman(Huffman),
+}
+
+impl PrefixCode {
+    fn decode(&self, input: &mut BitReader) -> io::Result<u16> {
+        match self {
+            PrefixCode::Single(symbol) => Ok(*symbol),
+            PrefixCode::Huffman(huffman) => huffman.decode(input),
+        }
+    }
+}
+
+fn read_prefix_code(input: &mut BitReader, alphabet: usize) -> io::Result<PrefixCode> {
+    let hskip = input.read_bits(2)?;
+    if hskip == 1 {
+        return read_simple_prefix_code(input, alphabet);
+    }
+
+    // The code length code lengths, each with a fixed variable length code.
+    let mut code_length_lengths = [0u8; 18];
+    let mut space = 32;
+    let mut used = 0;
+    for &symbol in CODE_LENGTH_ORDER.iter().skip(hskip as usize) {
+        let len = match input.read_bits(2)? {
+            0 => 0,
+            1 => 4,
+            2 => 3,
+            _ => match input.read_bits(1)? {
+                0 => 2,
+                _ => match input.read_bits(1)? {
+                    0 => 1,
+                    _ => 5,
+                },
+            },
+        };
+        code_length_lengths[symbol] = len;
+
+        if len > 0 {
+            used += 1;
+            space -= 32 >> len;
+            if space <= 0 {
+                break;
+            }
+        }
+    }
+    if used != 1 && space != 0 {
+        return Err(invalid("invalid code length code"));
+    }
+    let code_length_code = match used {
+        1 => {
+            PrefixCode::Single(code_length_lengths.iter().position(|&len| len > 0).unwrap() as u16)
+        }
+        _ => PrefixCode::Huffman(Huffman::new(&code_length_lengths)),
+    };
+
+    let mut lengths = vec![0u8; alphabet];
+    let mut space = 32768;
+    let mut previous = 8;
+    let mut repeat = 0;
+    let mut repeat_len = 0;
+    let mut symbol = 0;
+
+    while symbol < alphabet && space > 0 {
+        let code = code_length_code.decode(input)? as usize;
+
+        if code < REPEAT_PREVIOUS {
+            repeat = 0;
+            lengths[symbol] = code as u8;
+            symbol += 1;
+            if code != 0 {
+                previous = code as u8;
+                space -= 32768 >> code;
+            }
+            continue;
+        }
+
+        let (extra_bits, len) = match code {
+            REPEAT_PREVIOUS => (2, previous),
+            _ => (3, 0),
+        };
+        if repeat_len != len {
+            repeat = 0;
+            repeat_len = len;
+        }
+        let old_repeat = repeat;
+        if repeat > 0 {
+            repeat = (repeat - 2) << extra_bits;
+        }
+        repeat += input.read_bits(extra_bits)? as usize + 3;
+
+        let count = repeat - old_repeat;
+        if symbol + count > alphabet {
+            return Err(invalid("code lengths past the end of the alphabet"));
+        }
+        lengths[symbol..symbol + count].fill(len);
+        symbol += count;
+        if len != 0 {
+            space -= count as i32 * (32768 >> len);
+        }
+    }
+
+    if space != 0 {
+        return Err(invalid("incomplete prefix code"));
+    }
+    Ok(PrefixCode::Huffman(Huffman::new(&lengths)))
+}
+
+fn read_simple_prefix_code(input: &mut BitReader, alphabet: usize) -> io::Result<PrefixCode> {
+    let count = input.read_bits(2)? as usize + 1;
+    let symbol_bits = (alphabet - 1).ilog2() + 1;
+
+    let mut symbols = vec![];
+    for _ in 0..count {
+        let symbol = input.read_bits(symbol_bits)? as usize;
+        if symbol >= alphabet || symbols.contains(&symbol) {
+            return Err(invalid("invalid simple prefix code"));
+        }
+        symbols.push(symbol);
+    }
+
+    let code_lengths: &[u8] = match count {
+        1 => return Ok(PrefixCode::Single(symbols[0] as u16)),
+        2 => &[1, 1],
+        3 => &[1, 2, 2],
+        _ if input.read_bits(1)? == 0 => &[2, 2, 2, 2],
+        _ => &[1, 2, 3, 3],
+    };
+
+    let mut lengths = vec![0u8; alphabet];
+    for (&symbol, &len) in symbols.iter().zip(code_lengths) {
+        lengths[symbol] = len;
+    }
+    Ok(PrefixCode::Huffman(Huffman::new(&lengths)))
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<u8> {
+        let mut data = vec![];
+        for i in 0..2000 {
+            data.extend_from_slice(format!("line {} of some repetitive text\n", i % 37).as_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn round_trips() {
+        let data = sample();
+        assert_eq!(decompress(&compress(&data)).unwrap(), data);
+    }
+
+    #[test]
+    fn round_trips_empty_and_tiny_inputs() {
+        for data in [
+            &b""[..],
+            b"a",
+            b"ab",
+            b"abc",
+            b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+        ] {
+            assert_eq!(decompress(&compress(data)).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn round_trips_binary_data() {
+        // Every byte value, with little repetition, for deep prefix codes.
+        let data: Vec<u8> = (0..100_000u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect();
+        assert_eq!(decompress(&compress(&data)).unwrap(), data);
+    }
+
+    #[test]
+    fn compresses_better_than_gzip() {
+        let data = sample();
+        let compressed = compress(&data);
+
+        assert!(compressed.len() < data.len() / 4);
+        assert!(compressed.len() < crate::deflate::compress(&data, 6).len());
+    }
+
+    #[test]
+    fn zero_runs() {
+        for run in 0..200 {
+            let mut tokens = vec![];
+            push_zeros(&mut tokens, run);
+
+            let mut repeat = 0;
+            let mut zeros = 0;
+            for (symbol, extra) in tokens {
+                if symbol == 0 {
+                    repeat = 0;
+                    zeros += 1;
+                    continue;
+                }
+                let old = repeat;
+                if repeat > 0 {
+                    repeat = (repeat - 2) << 3;
+                }
+                repeat += extra as usize + 3;
+                zeros += repeat - old;
+            }
+            assert_eq!(zeros, run);
+        }
+    }
+
+    #[test]
+    fn code_lengths_are_limited() {
+        // Fibonacci frequencies give the deepest possible Huffman tree.
+        let mut freqs = vec![1u32, 1];
+        while freqs.len() < 30 {
+            freqs.push(freqs[freqs.len() - 1] + freqs[freqs.len() - 2]);
+        }
+
+        let lengths = code_lengths(&freqs, 15);
+        assert!(lengths.iter().all(|&len| (1..=15).contains(&len)));
+        let kraft: f64 = lengths.iter().map(|&len| 0.5f64.powi(len as i32)).sum();
+        assert_eq!(kraft, 1.0);
+    }
+
+    #[test]
+    fn decompresses_uncompressed_meta_block() {
+        // "hello" compressed by the reference encoder at quality 11, which
+        // stored it as is.
+        let compressed = [0x0b, 0x02, 0x80, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x03];
+        assert_eq!(decompress(&compressed).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn decompresses_reference_stream() {
+        // Compressed by the reference encoder at quality 1.
+        let compressed = [
+            0x8b, 0x57, 0x00, 0x00, 0x80, 0xaa, 0xaa, 0xaa, 0xea, 0xff, 0x6e, 0x97, 0x13, 0xdb,
+            0xe1, 0x62, 0xa7, 0xb3, 0x1d, 0xec, 0x70, 0xd1, 0x45, 0x74, 0xd9, 0xc4, 0x4c, 0x4d,
+            0x17, 0x33, 0x85, 0x03, 0xb8, 0xf8, 0x95, 0x26, 0x52, 0x54, 0x62, 0x83, 0x31, 0xcf,
+            0x08, 0x6a, 0x2f, 0x47, 0x78, 0xba, 0x57, 0x11, 0xb2, 0xf2, 0x2c, 0x30, 0xfc, 0x21,
+            0xf4, 0x7c, 0xbf, 0xe0, 0x41, 0x15, 0xcd, 0x11, 0x92, 0x58, 0x3f, 0x34, 0xdb, 0x03,
+            0xc3, 0x52, 0x77, 0x0a, 0x15, 0x91, 0x7f, 0x48, 0xfe, 0x30, 0x7d, 0x73, 0x30, 0x7e,
+            0x10, 0x34, 0x2f, 0x2a, 0x48, 0xfe, 0xe9, 0x5c, 0x11, 0xba, 0x7d, 0xf7, 0x6d,
+        ];
+        let expected = [
+            "The quick brown fox jumps over the lazy dog. "
+                .repeat(3)
+                .as_str(),
+            "Pack my box with five dozen liquor jugs!\n",
+        ]
+        .concat();
+
+        assert_eq!(decompress(&compressed).unwrap(), expected.as_bytes());
+    }
+
+    #[test]
+    fn rejects_truncated_stream() {
+        let compressed = compress(&sample());
+        assert!(decompress(&compressed[..compressed.len() / 2]).is_err());
+    }
+}
@@ -0,0 +1,327 @@
+// Chunked transfer coding, which sends a message-body of unknown length as
+// a series of chunks, each prefixed with its size in hex:
+//
+//  5\r\n
+//  hello\r\n
+//  0\r\n
+//  \r\n
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7230#section-4.1
+
+use std::cell::RefCell;
+use std::io::{self, BufRead, Read, Write};
+use std::rc::Rc;
+
+// Longest chunk-size (or trailer) line accepted, extensions included.
+const MAX_LINE: u64 = 4096;
+
+// Trailer fields, as (name, value) pairs. Shared, since they only arrive
+// once the reader has reached the end of the body.
+pub type TrailerFields = Rc<RefCell<Vec<(String, String)>>>;
+
+// Reads the decoded message-body out of a chunked stream. The reader stops
+// right after the last chunk and the trailer section, so whatever follows
+// (e.g. a pipelined request) is left in the inner reader.
+pub struct ChunkedReader<R> {
+    inner: R,
+    // Bytes left in the current chunk.
+    remaining: u64,
+    done: bool,
+    // The first error met: past it the framing is lost, so every later read
+    // fails the same way instead of parsing data as a chunk line.
+    failed: Option<(io::ErrorKind, String)>,
+    trailers: TrailerFields,
+}
+
+impl<R: BufRead> ChunkedReader<R> {
+    pub fn new(inner: R) -> ChunkedReader<R> {
+        ChunkedReader {
+            inner,
+            remaining: 0,
+            done: false,
+            failed: None,
+            trailers: TrailerFields::default(),
+        }
+    }
+
+    // The trailer fields received after the last chunk, filled in when the
+    // body has been read to the end.
+    pub fn trailers(&self) -> TrailerFields {
+        Rc::clone(&self.trailers)
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = vec![];
+        (&mut self.inner)
+            .take(MAX_LINE)
+            .read_until(b'\n', &mut line)?;
+
+        let line = line
+            .strip_suffix(b"\r\n")
+            .ok_or_else(|| invalid("chunk line is not terminated by CRLF"))?;
+
+        String::from_utf8(line.to_vec()).map_err(|_| invalid("chunk line is not UTF-8"))
+    }
+
+    // chunk-size [ chunk-ext ] CRLF, e.g. "1a;name=value". The size is
+    // made of hex digits only: from_str_radix alone would also take a sign
+    // ("+5"), which stricter parsers in front of this server would not,
+    // and the two would disagree on where the body ends.
+    fn read_chunk_size(&mut self) -> io::Result<u64> {
+        let line = self.read_line()?;
+        // Whitespace is only allowed before the chunk extensions.
+        let size = line
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches([' ', '\t']);
+
+        if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid("invalid chunk size"));
+        }
+        u64::from_str_radix(size, 16).map_err(|_| invalid("invalid chunk size"))
+    }
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some((kind, message)) = &self.failed {
+            return Err(io::Error::new(*kind, message.clone()));
+        }
+
+        let read = self.read_chunked(buf);
+        if let Err(e) = &read {
+            if e.kind() != io::ErrorKind::Interrupted {
+                self.failed = Some((e.kind(), e.to_string()));
+            }
+        }
+        read
+    }
+}
+
+impl<R: BufRead> ChunkedReader<R> {
+    fn read_chunked(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            self.remaining = self.read_chunk_size()?;
+
+            if self.remaining == 0 {
+                // The last chunk is followed by optional trailer fields and
+                // an empty line.
+                loop {
+                    let line = self.read_line()?;
+                    if line.is_empty() {
+                        break;
+                    }
+                    let (name, value) = line
+                        .split_once(':')
+                        .filter(|(name, _)| !name.is_empty() && !name.contains(' '))
+                        .ok_or_else(|| invalid("invalid trailer field"))?;
+                    self.trailers
+                        .borrow_mut()
+                        .push((name.to_string(), value.trim().to_string()));
+                }
+                self.done = true;
+                return Ok(0);
+            }
+        }
+
+        let max = buf
+            .len()
+            .min(self.remaining.try_into().unwrap_or(usize::MAX));
+        let n = self.inner.read(&mut buf[..max])?;
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "chunked message-body ended early",
+            ));
+        }
+
+        self.remaining -= n as u64;
+        if self.remaining == 0 {
+            let mut crlf = [0u8; 2];
+            self.inner.read_exact(&mut crlf)?;
+            if &crlf != b"\r\n" {
+                return Err(invalid("chunk data is not terminated by CRLF"));
+            }
+        }
+
+        Ok(n)
+    }
+}
+
+// Writes everything read from `reader` as a chunked message-body, one chunk
+// per read, followed by the last chunk and the trailer fields. These are only
+// asked for once the whole body is read, so they can depend on it.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7230#section-4.1.2
+pub fn write_chunked<R, W, F>(reader: &mut R, mut writer: W, trailers: F) -> io::Result<()>
+where
+    R: Read + ?Sized,
+    W: Write,
+    F: FnOnce() -> Vec<(String, String)>,
+{
+    let mut buf = [0u8; 8192];
+
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        write!(writer, "{:x}\r\n", n)?;
+        writer.write_all(&buf[..n])?;
+        writer.write_all(b"\r\n")?;
+    }
+
+    writer.write_all(b"0\r\n")?;
+    for (key, val) in trailers() {
+        write!(writer, "{}: {}\r\n", key, val)?;
+    }
+    writer.write_all(b"\r\n")
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(raw: &[u8]) -> io::Result<(Vec<u8>, Vec<u8>)> {
+        let mut inner = raw;
+        let mut body = vec![];
+        ChunkedReader::new(&mut inner).read_to_end(&mut body)?;
+        Ok((body, inner.to_vec()))
+    }
+
+    #[test]
+    fn decodes_chunks() {
+        let (body, rest) =
+            decode(b"5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\nX-Trailer: a\r\n\r\nNEXT").unwrap();
+
+        assert_eq!(body, b"hello, world");
+        // Whatever follows the chunked body is left unread.
+        assert_eq!(rest, b"NEXT");
+    }
+
+    #[test]
+    fn errors_are_sticky() {
+        let mut inner = &b"zz\r\n5\r\nhello\r\n0\r\n\r\n"[..];
+        let mut reader = ChunkedReader::new(&mut inner);
+        let mut buf = [0u8; 16];
+
+        for _ in 0..2 {
+            let err = reader.read(&mut buf).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(err.to_string(), "invalid chunk size");
+        }
+    }
+
+    #[test]
+    fn collects_trailer_fields() {
+        let mut inner = &b"5\r\nhello\r\n0\r\nX-Checksum: abc\r\nX-Other:  1 \r\n\r\n"[..];
+        let mut reader = ChunkedReader::new(&mut inner);
+        let trailers = reader.trailers();
+        let mut body = vec![];
+        reader.read_to_end(&mut body).unwrap();
+
+        assert_eq!(body, b"hello");
+        assert_eq!(
+            *trailers.borrow(),
+            vec![
+                ("X-Checksum".to_string(), "abc".to_string()),
+                ("X-Other".to_string(), "1".to_string())
+            ]
+        );
+
+        for raw in [
+            &b"0\r\nno colon\r\n\r\n"[..],
+            b"0\r\n: empty\r\n\r\n",
+            b"0\r\nX a: b\r\n\r\n",
+        ] {
+            assert!(decode(raw).is_err(), "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn decodes_empty_body() {
+        let (body, rest) = decode(b"0\r\n\r\n").unwrap();
+
+        assert!(body.is_empty());
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn encoded_chunks_decode_back() {
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+
+        let mut encoded = vec![];
+        write_chunked(&mut &data[..], &mut encoded, Vec::new).unwrap();
+        assert!(encoded.ends_with(b"\r\n0\r\n\r\n"));
+
+        let (body, rest) = decode(&encoded).unwrap();
+        assert_eq!(body, data);
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn each_read_is_written_as_its_own_chunk() {
+        // Reads are not coalesced: whatever the reader has produced so far
+        // goes out as soon as it is read.
+        let pieces = [&b"first"[..], b"second", b"third"];
+        let mut reader = io::Cursor::new(pieces[0])
+            .chain(io::Cursor::new(pieces[1]))
+            .chain(io::Cursor::new(pieces[2]));
+
+        let mut encoded = vec![];
+        write_chunked(&mut reader, &mut encoded, Vec::new).unwrap();
+        assert_eq!(
+            encoded,
+            b"5\r\nfirst\r\n6\r\nsecond\r\n5\r\nthird\r\n0\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn encoded_trailers_decode_back() {
+        let mut encoded = vec![];
+        let trailers = || vec![("X-Checksum".to_string(), "abc".to_string())];
+        write_chunked(&mut &b"hello"[..], &mut encoded, trailers).unwrap();
+        assert!(encoded.ends_with(b"\r\n0\r\nX-Checksum: abc\r\n\r\n"));
+
+        let (body, rest) = decode(&[&encoded[..], b"NEXT"].concat()).unwrap();
+        assert_eq!(body, b"hello");
+        assert_eq!(rest, b"NEXT");
+    }
+
+    #[test]
+    fn rejects_malformed_chunks() {
+        for raw in [
+            &b"z\r\nhello\r\n0\r\n\r\n"[..],
+            b"5\r\nhelloXX0\r\n\r\n",
+            b"5\nhello\r\n0\r\n\r\n",
+            b"5\r\nhel",
+            b"5\r\nhello\r\n",
+            b"+5\r\nhello\r\n0\r\n\r\n",
+            b"-0\r\n\r\n",
+            b" 5\r\nhello\r\n0\r\n\r\n",
+            b"0x5\r\nhello\r\n0\r\n\r\n",
+            b";ext\r\nhello\r\n0\r\n\r\n",
+        ] {
+            assert!(decode(raw).is_err(), "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn allows_whitespace_before_chunk_extensions() {
+        let (body, rest) = decode(b"5 ;a=b\r\nhello\r\n0\t;c\r\n\r\n").unwrap();
+        assert_eq!(body, b"hello");
+        assert!(rest.is_empty());
+    }
+}
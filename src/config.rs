@@ -0,0 +1,1006 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::encoding;
+use crate::{Error, FileSystem, OsFileSystem, Request};
+
+// How a request path ending with "/" (other than the root) is routed.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum TrailingSlash {
+    // Paths are matched as sent, so "/user-agent/" is not "/user-agent".
+    Strict,
+    // "/x" and "/x/" are equivalent, the trailing slash is dropped before routing.
+    #[default]
+    Ignore,
+    // "/x/" gets a 301 redirect to the canonical "/x".
+    Redirect,
+}
+
+// Which cross-origin requests get the CORS response headers.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum Cors {
+    #[default]
+    Disabled,
+    // Any origin is allowed (`Access-Control-Allow-Origin: *`).
+    AllowAll,
+    // Only the listed origins are allowed.
+    Origins(Vec<String>),
+}
+
+// Server configuration shared (read-only) by every connection.
+#[derive(Debug, Clone)]
+pub struct Config {
+    // Root directory used by the /files/ endpoints.
+    pub directory: Option<String>,
+    pub trailing_slash: TrailingSlash,
+    pub cors: Cors,
+    // Enables the GET /stats endpoint.
+    pub stats: bool,
+    // Maximum number of headers accepted in a single request.
+    pub max_headers: usize,
+    // Maximum length of a single header line, without its CRLF.
+    pub max_header_line_length: usize,
+    // Maximum size of all the header lines of a request together.
+    pub max_header_size: usize,
+    // Maximum length of the request-target, e.g. "/echo/abc?x=1".
+    pub max_uri_length: usize,
+    // Response bodies smaller than this (in bytes) are never compressed.
+    pub compression_min_size: usize,
+    // From 0 (no compression, fastest) to 9 (best compression, slowest).
+    pub gzip_level: u32,
+    // Content-codings responses may be compressed with, by preference when
+    // the client accepts several equally. Empty to never compress.
+    pub encodings: Vec<String>,
+    // Custom HTML pages served as the body of error responses, by status code.
+    pub error_pages: Vec<(u16, PathBuf)>,
+    // Directories served for specific hosts (virtual hosts), by host name.
+    // Requests for any other host are served from `directory`.
+    pub vhosts: Vec<(String, String)>,
+    // Maximum number of requests per second accepted from a single client IP.
+    pub rate_limit: Option<f64>,
+    // Files served, in order of preference, when a request targets a
+    // directory. The directory is listed when none of them exists.
+    pub index_files: Vec<String>,
+    // Rejects every request which would write to the directory.
+    pub read_only: bool,
+    // Headers added to every response, e.g. security headers.
+    pub headers: Vec<(String, String)>,
+    // Disables Nagle's algorithm on accepted connections, so small writes
+    // (like a response head) are sent right away instead of being delayed.
+    pub tcp_nodelay: bool,
+    // Enables POST /admin/shutdown for requests carrying this token.
+    pub admin_token: Option<String>,
+    // Takes the client address from X-Forwarded-For, for servers running
+    // behind a reverse proxy.
+    pub trust_proxy: bool,
+    // Development mode: more helpful (and verbose) error responses.
+    pub dev: bool,
+    // Link headers sent in a 103 Early Hints response ahead of the final
+    // response, by request path. Routes without an entry get no hints.
+    pub early_hints: Vec<(String, String)>,
+    // Time budget for receiving a request head, from its first byte to the
+    // end of the headers. Bounds slow clients which dribble the headers
+    // byte by byte to hold connections open.
+    pub request_timeout: Option<Duration>,
+    // Path prefix the server is mounted under (e.g. "/api" behind a proxy).
+    // It is stripped from request paths before routing, requests outside of
+    // it are not found.
+    pub base_path: Option<String>,
+    // Requests served on a single keep-alive connection before it is
+    // closed, so a client reconnects instead of holding a worker forever.
+    pub keepalive_max_requests: Option<usize>,
+    // Content types of the files served, by (lowercase) file extension.
+    // Files with any other extension are served as application/octet-stream.
+    pub mime_types: Vec<(String, String)>,
+    // Turns off the informational output, errors are still reported.
+    pub quiet: bool,
+    // Largest message-body (in bytes) accepted in a request.
+    pub max_body_size: Option<u64>,
+    // File every request is logged to, besides the standard output.
+    pub access_log: Option<PathBuf>,
+    // How long (in seconds) clients may cache the files served. 0 means
+    // they must be revalidated on every use.
+    pub file_cache_max_age: u64,
+    // Where the /files/ endpoints read and write files, the actual
+    // filesystem unless replaced (e.g. in tests).
+    pub filesystem: Arc<dyn FileSystem>,
+    // Guesses the content type of files without an extension from their
+    // first bytes.
+    pub sniff_content_type: bool,
+    // Adds X-Echo-Method and X-Echo-Path headers to every response, with
+    // the method and path of the request it answers.
+    pub echo_request: bool,
+    // File extensions (lowercase, without the dot) which may be served.
+    // When set, files with any other extension (or none) are forbidden.
+    pub allowed_extensions: Option<Vec<String>>,
+    // File extensions which are never served, even when allowed.
+    pub denied_extensions: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            directory: None,
+            trailing_slash: TrailingSlash::default(),
+            cors: Cors::default(),
+            stats: false,
+            max_headers: 100,
+            max_header_line_length: 8 * 1024,
+            max_header_size: 64 * 1024,
+            max_uri_length: 8 * 1024,
+            compression_min_size: 1024,
+            // Balanced default between compression ratio and speed.
+            gzip_level: 6,
+            encodings: vec!["br".to_string(), "gzip".to_string(), "deflate".to_string()],
+            error_pages: vec![],
+            vhosts: vec![],
+            rate_limit: None,
+            index_files: vec!["index.html".to_string()],
+            read_only: false,
+            headers: vec![],
+            tcp_nodelay: true,
+            admin_token: None,
+            trust_proxy: false,
+            dev: false,
+            early_hints: vec![],
+            request_timeout: Some(Duration::from_secs(30)),
+            base_path: None,
+            keepalive_max_requests: Some(100),
+            mime_types: vec![],
+            quiet: false,
+            max_body_size: None,
+            access_log: None,
+            file_cache_max_age: 0,
+            filesystem: Arc::new(OsFileSystem),
+            sniff_content_type: false,
+            echo_request: false,
+            allowed_extensions: None,
+            denied_extensions: vec![],
+        }
+    }
+}
+
+impl Config {
+    // Parse CLI args
+    //  * --directory {string}
+    //  * --trailing-slash {strict|ignore|redirect}
+    //  * --cors-allow-all
+    //  * --cors-origin {origin} (repeatable)
+    //  * --stats
+    //  * --max-headers {number}
+    //  * --max-header-line-length {bytes}
+    //  * --max-header-size {bytes}
+    //  * --compression-min-size {bytes}
+    //  * --gzip-level {0-9}
+    //  * --encodings {coding},{coding},... (br, gzip or deflate; empty to disable)
+    //  * --error-page {status}={path} (repeatable)
+    //  * --vhost {host}={directory} (repeatable)
+    //  * --rate-limit {requests-per-second}
+    //  * --index-files {name},{name},... (empty to always list directories)
+    //  * --read-only
+    //  * --header "{name}: {value}" (repeatable)
+    //  * --tcp-nodelay {on|off}
+    //  * --admin-token {token}
+    //  * --trust-proxy
+    //  * --dev
+    //  * --early-hint {path}={link} (repeatable)
+    //  * --request-timeout {seconds} (0 to disable)
+    //  * --base-path {path}
+    //  * --keepalive-max-requests {number} (0 for no limit)
+    //  * --mime {extension}={type} (repeatable)
+    //  * --quiet
+    //  * --max-body-size {bytes}
+    //  * --access-log {path}
+    //  * --file-cache-max-age {seconds}
+    //  * --sniff-content-type
+    //  * --max-uri-length {bytes}
+    //  * --echo-request
+    //  * --allow-ext {extension},{extension},... (repeatable)
+    //  * --deny-ext {extension},{extension},... (repeatable)
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Config, Error> {
+        let mut config = Config::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--directory" => {
+                    config.directory = Some(next_value(&mut args, &arg)?);
+                }
+                "--trailing-slash" => {
+                    let policy = next_value(&mut args, &arg)?;
+                    config.trailing_slash = match policy.as_str() {
+                        "strict" => TrailingSlash::Strict,
+                        "ignore" => TrailingSlash::Ignore,
+                        "redirect" => TrailingSlash::Redirect,
+                        _ => return Err(invalid_value(&arg, &policy)),
+                    };
+                }
+                "--cors-allow-all" => {
+                    if let Cors::Origins(_) = config.cors {
+                        return Err(cors_conflict());
+                    }
+                    config.cors = Cors::AllowAll;
+                }
+                "--cors-origin" => {
+                    let origin = next_value(&mut args, &arg)?;
+                    match &mut config.cors {
+                        Cors::Disabled => config.cors = Cors::Origins(vec![origin]),
+                        Cors::Origins(origins) => origins.push(origin),
+                        Cors::AllowAll => return Err(cors_conflict()),
+                    }
+                }
+                "--stats" => config.stats = true,
+                "--read-only" => config.read_only = true,
+                "--trust-proxy" => config.trust_proxy = true,
+                "--dev" => config.dev = true,
+                "--quiet" => config.quiet = true,
+                "--sniff-content-type" => config.sniff_content_type = true,
+                "--echo-request" => config.echo_request = true,
+                "--max-headers" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.max_headers = value.parse().map_err(|_| invalid_value(&arg, &value))?;
+                }
+                "--max-header-line-length" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.max_header_line_length =
+                        value.parse().map_err(|_| invalid_value(&arg, &value))?;
+                }
+                "--max-header-size" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.max_header_size =
+                        value.parse().map_err(|_| invalid_value(&arg, &value))?;
+                }
+                "--max-uri-length" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.max_uri_length =
+                        value.parse().map_err(|_| invalid_value(&arg, &value))?;
+                }
+                "--compression-min-size" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.compression_min_size =
+                        value.parse().map_err(|_| invalid_value(&arg, &value))?;
+                }
+                "--gzip-level" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.gzip_level = value
+                        .parse()
+                        .ok()
+                        .filter(|level| *level <= 9)
+                        .ok_or_else(|| invalid_value(&arg, &value))?;
+                }
+                "--encodings" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let mut encodings = vec![];
+                    for name in value.split(',').map(|name| name.trim()) {
+                        if name.is_empty() {
+                            continue;
+                        }
+                        let name = name.to_ascii_lowercase();
+                        if encoding::encoder(&name).is_none() {
+                            return Err(invalid_value(&arg, &value));
+                        }
+                        if !encodings.contains(&name) {
+                            encodings.push(name);
+                        }
+                    }
+                    config.encodings = encodings;
+                }
+                "--header" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let header = parse_header(&value).ok_or_else(|| invalid_value(&arg, &value))?;
+                    config.headers.push(header);
+                }
+                "--tcp-nodelay" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.tcp_nodelay = match value.as_str() {
+                        "on" => true,
+                        "off" => false,
+                        _ => return Err(invalid_value(&arg, &value)),
+                    };
+                }
+                "--admin-token" => {
+                    let token = next_value(&mut args, &arg)?;
+                    if token.is_empty() {
+                        return Err(invalid_value(&arg, &token));
+                    }
+                    config.admin_token = Some(token);
+                }
+                "--early-hint" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let (path, link) = value
+                        .split_once('=')
+                        .filter(|(path, link)| path.starts_with('/') && !link.is_empty())
+                        .filter(|(_, link)| !link.contains(['\r', '\n']))
+                        .ok_or_else(|| invalid_value(&arg, &value))?;
+                    config
+                        .early_hints
+                        .push((path.to_string(), link.to_string()));
+                }
+                "--request-timeout" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let seconds: f64 = value
+                        .parse()
+                        .ok()
+                        .filter(|s: &f64| s.is_finite() && *s >= 0.0)
+                        .ok_or_else(|| invalid_value(&arg, &value))?;
+                    config.request_timeout =
+                        Some(Duration::from_secs_f64(seconds)).filter(|d| !d.is_zero());
+                }
+                "--base-path" => {
+                    let value = next_value(&mut args, &arg)?;
+                    if !value.starts_with('/') || value.contains(['?', '#']) {
+                        return Err(invalid_value(&arg, &value));
+                    }
+                    // "/api/" is the same as "/api", and "/" is no prefix at all.
+                    let path = value.trim_end_matches('/');
+                    config.base_path = Some(path.to_string()).filter(|p| !p.is_empty());
+                }
+                "--keepalive-max-requests" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let max: usize = value.parse().map_err(|_| invalid_value(&arg, &value))?;
+                    config.keepalive_max_requests = Some(max).filter(|max| *max > 0);
+                }
+                "--file-cache-max-age" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.file_cache_max_age =
+                        value.parse().map_err(|_| invalid_value(&arg, &value))?;
+                }
+                "--access-log" => {
+                    config.access_log = Some(PathBuf::from(next_value(&mut args, &arg)?));
+                }
+                "--max-body-size" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.max_body_size =
+                        Some(value.parse().map_err(|_| invalid_value(&arg, &value))?);
+                }
+                "--mime" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let (ext, mime) = value
+                        .split_once('=')
+                        .map(|(ext, mime)| (ext.trim_start_matches('.'), mime))
+                        .filter(|(ext, _)| !ext.is_empty() && !ext.contains('.'))
+                        .filter(|(_, mime)| is_media_type(mime))
+                        .ok_or_else(|| invalid_value(&arg, &value))?;
+                    // A later mapping for the same extension wins.
+                    let ext = ext.to_ascii_lowercase();
+                    config.mime_types.retain(|(e, _)| *e != ext);
+                    config.mime_types.push((ext, mime.to_string()));
+                }
+                "--error-page" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let (status, path) = value
+                        .split_once('=')
+                        .ok_or_else(|| invalid_value(&arg, &value))?;
+                    let status: u16 = status
+                        .parse()
+                        .ok()
+                        .filter(|s| (400..600).contains(s))
+                        .ok_or_else(|| invalid_value(&arg, &value))?;
+                    config.error_pages.push((status, PathBuf::from(path)));
+                }
+                "--vhost" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let (host, dir) = value
+                        .split_once('=')
+                        .filter(|(host, dir)| !host.is_empty() && !dir.is_empty())
+                        .ok_or_else(|| invalid_value(&arg, &value))?;
+                    config.vhosts.push((host.to_string(), dir.to_string()));
+                }
+                "--rate-limit" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let rate: f64 = value
+                        .parse()
+                        .ok()
+                        .filter(|r: &f64| r.is_finite() && *r > 0.0)
+                        .ok_or_else(|| invalid_value(&arg, &value))?;
+                    config.rate_limit = Some(rate);
+                }
+                "--index-files" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.index_files = value
+                        .split(',')
+                        .map(|name| name.trim())
+                        .filter(|name| !name.is_empty())
+                        .map(|name| name.to_string())
+                        .collect();
+                }
+                "--allow-ext" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config
+                        .allowed_extensions
+                        .get_or_insert_with(Vec::new)
+                        .extend(extension_list(&value));
+                }
+                "--deny-ext" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.denied_extensions.extend(extension_list(&value));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(config)
+    }
+
+    // Checks every directory this config serves, see `check_directory`.
+    pub fn check_directories(&self) -> Result<(), Error> {
+        let dirs = self
+            .directory
+            .iter()
+            .chain(self.vhosts.iter().map(|(_, dir)| dir));
+
+        for dir in dirs {
+            check_directory(Path::new(dir), !self.read_only)?;
+        }
+
+        Ok(())
+    }
+
+    // Directory served for the request, picked by its Host header. The port
+    // is ignored unless the vhost was configured with one.
+    pub fn directory_for(&self, req: &Request) -> Option<&String> {
+        if let (Some(host), Some(name)) = (req.host(), req.host_name()) {
+            let host = host.trim();
+            for (vhost, dir) in self.vhosts.iter() {
+                if vhost.eq_ignore_ascii_case(host) || vhost.eq_ignore_ascii_case(&name) {
+                    return Some(dir);
+                }
+            }
+        }
+
+        self.directory.as_ref()
+    }
+
+    // Content type of a served file, based on its extension.
+    pub fn content_type_for(&self, path: &Path) -> String {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+
+        ext.and_then(|ext| self.mime_types.iter().find(|(e, _)| *e == ext))
+            .map(|(_, mime)| mime.clone())
+            .unwrap_or_else(|| "application/octet-stream".to_string())
+    }
+
+    // Whether --allow-ext and --deny-ext let the file be served. The
+    // extension of a dotfile is its whole name, e.g. "env" for ".env", so
+    // those can be denied too.
+    pub fn serves_extension(&self, path: &Path) -> bool {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        let ext = match name.rsplit_once('.') {
+            Some((_, ext)) => ext,
+            None => "",
+        };
+
+        if self.denied_extensions.iter().any(|e| e == ext) {
+            return false;
+        }
+
+        match &self.allowed_extensions {
+            Some(allowed) => allowed.iter().any(|e| e == ext),
+            None => true,
+        }
+    }
+}
+
+// Parses a comma-separated list of file extensions, e.g. "html, .CSS,js",
+// into lowercase extensions without their dot.
+fn extension_list(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(',')
+        .map(|ext| ext.trim().trim_start_matches('.'))
+        .filter(|ext| !ext.is_empty())
+        .map(|ext| ext.to_ascii_lowercase())
+}
+
+// Checks that a served directory exists and can be listed, and, unless
+// `writable` is false, that files can be created in it. Catches typos in the
+// flags at startup rather than on every request.
+pub fn check_directory(path: &Path, writable: bool) -> Result<(), Error> {
+    let metadata = fs::metadata(path)
+        .map_err(|e| Error::Config(format!("cannot access directory {:?}: {}", path, e)))?;
+
+    if !metadata.is_dir() {
+        return Err(Error::Config(format!("{:?} is not a directory", path)));
+    }
+
+    fs::read_dir(path)
+        .map_err(|e| Error::Config(format!("cannot read directory {:?}: {}", path, e)))?;
+
+    if writable && metadata.permissions().readonly() {
+        return Err(Error::Config(format!(
+            "directory {:?} is not writable",
+            path
+        )));
+    }
+
+    Ok(())
+}
+
+// Parses a "Name: Value" header. The name must be a token and the value
+// can't contain line breaks, which would let it inject further headers.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7230#section-3.2
+fn parse_header(header: &str) -> Option<(String, String)> {
+    let (name, value) = header.split_once(':')?;
+    let value = value.trim();
+
+    let is_tchar = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+    if name.is_empty() || !name.chars().all(is_tchar) {
+        return None;
+    }
+    if value.chars().any(|c| c.is_ascii_control() && c != '\t') {
+        return None;
+    }
+
+    Some((name.to_string(), value.to_string()))
+}
+
+// Checks a "type/subtype" media type, optionally with parameters, e.g.
+// "text/plain; charset=utf-8". Line breaks would let it inject headers.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7231#section-3.1.1.1
+fn is_media_type(value: &str) -> bool {
+    let is_tchar = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+    let essence = value.split(';').next().unwrap_or_default();
+
+    match essence.split_once('/') {
+        Some((kind, subtype)) => {
+            !kind.is_empty()
+                && !subtype.is_empty()
+                && kind.chars().all(is_tchar)
+                && subtype.chars().all(is_tchar)
+                && !value.chars().any(|c| c.is_ascii_control())
+        }
+        None => false,
+    }
+}
+
+fn next_value<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<String, Error> {
+    args.next()
+        .ok_or(Error::Config(format!("missing value for {}", flag)))
+}
+
+fn invalid_value(flag: &str, value: &str) -> Error {
+    Error::Config(format!("invalid {} value {:?}", flag, value))
+}
+
+fn cors_conflict() -> Error {
+    Error::Config("--cors-allow-all cannot be combined with --cors-origin".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_cors_origins() {
+        let config = Config::from_args(args(&[
+            "server",
+            "--cors-origin",
+            "http://a.test",
+            "--cors-origin",
+            "http://b.test",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            config.cors,
+            Cors::Origins(vec![
+                "http://a.test".to_string(),
+                "http://b.test".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn parses_error_pages() {
+        let config = Config::from_args(args(&[
+            "server",
+            "--error-page",
+            "404=/tmp/404.html",
+            "--error-page",
+            "500=/tmp/500.html",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            config.error_pages,
+            vec![
+                (404, PathBuf::from("/tmp/404.html")),
+                (500, PathBuf::from("/tmp/500.html"))
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_error_pages() {
+        for value in ["404", "abc=/tmp/x.html", "200=/tmp/x.html"] {
+            let res = Config::from_args(args(&["server", "--error-page", value]));
+            assert!(matches!(res, Err(Error::Config(_))), "{}", value);
+        }
+    }
+
+    #[test]
+    fn rejects_cors_allow_all_with_origins() {
+        let res = Config::from_args(args(&[
+            "server",
+            "--cors-origin",
+            "http://a.test",
+            "--cors-allow-all",
+        ]));
+        assert!(matches!(res, Err(Error::Config(_))));
+
+        let res = Config::from_args(args(&[
+            "server",
+            "--cors-allow-all",
+            "--cors-origin",
+            "http://a.test",
+        ]));
+        assert!(matches!(res, Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn rejects_invalid_rate_limits() {
+        for value in ["0", "-1", "abc", "inf"] {
+            let res = Config::from_args(args(&["server", "--rate-limit", value]));
+            assert!(matches!(res, Err(Error::Config(_))), "{}", value);
+        }
+
+        let config = Config::from_args(args(&["server", "--rate-limit", "2.5"])).unwrap();
+        assert_eq!(config.rate_limit, Some(2.5));
+    }
+
+    #[test]
+    fn parses_index_files() {
+        assert_eq!(Config::default().index_files, vec!["index.html"]);
+
+        let config = Config::from_args(args(&[
+            "server",
+            "--index-files",
+            "index.html, index.htm,,default.html",
+        ]))
+        .unwrap();
+        assert_eq!(
+            config.index_files,
+            vec!["index.html", "index.htm", "default.html"]
+        );
+
+        let config = Config::from_args(args(&["server", "--index-files", ""])).unwrap();
+        assert!(config.index_files.is_empty());
+    }
+
+    #[test]
+    fn parses_extension_lists() {
+        let config = Config::default();
+        assert_eq!(config.allowed_extensions, None);
+        assert!(config.denied_extensions.is_empty());
+
+        let config = Config::from_args(args(&[
+            "server",
+            "--allow-ext",
+            "html, .CSS,,js",
+            "--allow-ext",
+            "png",
+            "--deny-ext",
+            ".env,key",
+        ]))
+        .unwrap();
+        assert_eq!(
+            config.allowed_extensions,
+            Some(vec![
+                "html".to_string(),
+                "css".to_string(),
+                "js".to_string(),
+                "png".to_string()
+            ])
+        );
+        assert_eq!(config.denied_extensions, vec!["env", "key"]);
+    }
+
+    #[test]
+    fn serves_extension() {
+        let config = Config {
+            denied_extensions: vec!["env".to_string(), "key".to_string()],
+            ..Config::default()
+        };
+        assert!(config.serves_extension(Path::new("/srv/index.html")));
+        assert!(config.serves_extension(Path::new("/srv/Makefile")));
+        assert!(!config.serves_extension(Path::new("/srv/.env")));
+        assert!(!config.serves_extension(Path::new("/srv/server.KEY")));
+
+        let config = Config {
+            allowed_extensions: Some(vec!["html".to_string()]),
+            denied_extensions: vec!["html".to_string()],
+            ..Config::default()
+        };
+        assert!(!config.serves_extension(Path::new("/srv/index.html")));
+
+        let config = Config {
+            allowed_extensions: Some(vec!["html".to_string()]),
+            ..Config::default()
+        };
+        assert!(config.serves_extension(Path::new("/srv/index.HTML")));
+        assert!(!config.serves_extension(Path::new("/srv/style.css")));
+        assert!(!config.serves_extension(Path::new("/srv/Makefile")));
+    }
+
+    #[test]
+    fn rejects_invalid_gzip_levels() {
+        for value in ["10", "-1", "fast"] {
+            let res = Config::from_args(args(&["server", "--gzip-level", value]));
+            assert!(matches!(res, Err(Error::Config(_))), "{}", value);
+        }
+
+        let config = Config::from_args(args(&["server", "--gzip-level", "0"])).unwrap();
+        assert_eq!(config.gzip_level, 0);
+    }
+
+    #[test]
+    fn parses_encodings() {
+        assert_eq!(Config::default().encodings, vec!["br", "gzip", "deflate"]);
+
+        let config =
+            Config::from_args(args(&["server", "--encodings", "GZIP, deflate,,gzip"])).unwrap();
+        assert_eq!(config.encodings, vec!["gzip", "deflate"]);
+
+        let config = Config::from_args(args(&["server", "--encodings", ""])).unwrap();
+        assert!(config.encodings.is_empty());
+
+        for value in ["zstd", "gzip,compress", "identity"] {
+            let res = Config::from_args(args(&["server", "--encodings", value]));
+            assert!(matches!(res, Err(Error::Config(_))), "{}", value);
+        }
+    }
+
+    #[test]
+    fn checks_directories() {
+        let base = std::env::temp_dir().join(format!("config-check-{}", std::process::id()));
+        fs::create_dir_all(&base).unwrap();
+        let file = base.join("file.txt");
+        fs::write(&file, "not a directory").unwrap();
+
+        assert!(check_directory(&base, true).is_ok());
+        assert!(matches!(
+            check_directory(&base.join("missing"), false),
+            Err(Error::Config(_))
+        ));
+        assert!(matches!(
+            check_directory(&file, false),
+            Err(Error::Config(_))
+        ));
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn parses_headers() {
+        let config = Config::from_args(args(&[
+            "server",
+            "--header",
+            "X-Content-Type-Options: nosniff",
+            "--header",
+            "Strict-Transport-Security:max-age=63072000",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            config.headers,
+            vec![
+                ("X-Content-Type-Options".to_string(), "nosniff".to_string()),
+                (
+                    "Strict-Transport-Security".to_string(),
+                    "max-age=63072000".to_string()
+                )
+            ]
+        );
+
+        for value in ["no-colon", ": value", "Bad Name: value", "X-A: a\r\nX-B: b"] {
+            let res = Config::from_args(args(&["server", "--header", value]));
+            assert!(matches!(res, Err(Error::Config(_))), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn parses_early_hints() {
+        let config = Config::from_args(args(&[
+            "server",
+            "--early-hint",
+            "/files/index.html=</files/style.css>; rel=preload; as=style",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            config.early_hints,
+            vec![(
+                "/files/index.html".to_string(),
+                "</files/style.css>; rel=preload; as=style".to_string()
+            )]
+        );
+
+        for value in ["</a.css>", "files=</a.css>", "/files="] {
+            let res = Config::from_args(args(&["server", "--early-hint", value]));
+            assert!(matches!(res, Err(Error::Config(_))), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn parses_request_timeout() {
+        let config = Config::from_args(args(&["server", "--request-timeout", "1.5"])).unwrap();
+        assert_eq!(config.request_timeout, Some(Duration::from_millis(1500)));
+
+        let config = Config::from_args(args(&["server", "--request-timeout", "0"])).unwrap();
+        assert_eq!(config.request_timeout, None);
+
+        let res = Config::from_args(args(&["server", "--request-timeout", "-1"]));
+        assert!(matches!(res, Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn parses_base_path() {
+        for (value, expected) in [
+            ("/api", Some("/api")),
+            ("/api/v1/", Some("/api/v1")),
+            ("/", None),
+        ] {
+            let config = Config::from_args(args(&["server", "--base-path", value])).unwrap();
+            assert_eq!(config.base_path.as_deref(), expected, "{}", value);
+        }
+
+        for value in ["api", "/api?x=1"] {
+            let res = Config::from_args(args(&["server", "--base-path", value]));
+            assert!(matches!(res, Err(Error::Config(_))), "{}", value);
+        }
+    }
+
+    #[test]
+    fn parses_keepalive_max_requests() {
+        let config = Config::from_args(args(&["server", "--keepalive-max-requests", "5"])).unwrap();
+        assert_eq!(config.keepalive_max_requests, Some(5));
+
+        let config = Config::from_args(args(&["server", "--keepalive-max-requests", "0"])).unwrap();
+        assert_eq!(config.keepalive_max_requests, None);
+
+        let res = Config::from_args(args(&["server", "--keepalive-max-requests", "many"]));
+        assert!(matches!(res, Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn parses_mime_types() {
+        let config = Config::from_args(args(&[
+            "server",
+            "--mime",
+            "wasm=application/wasm",
+            "--mime",
+            ".TXT=text/plain",
+            "--mime",
+            "wasm=application/x-wasm",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            config.mime_types,
+            vec![
+                ("txt".to_string(), "text/plain".to_string()),
+                ("wasm".to_string(), "application/x-wasm".to_string()),
+            ]
+        );
+        assert_eq!(
+            config.content_type_for(Path::new("a/notes.Txt")),
+            "text/plain"
+        );
+        assert_eq!(
+            config.content_type_for(Path::new("a/image.png")),
+            "application/octet-stream"
+        );
+
+        for value in [
+            "wasm",
+            "=text/plain",
+            "wasm=application",
+            "wasm=text/\r\nX: y",
+            "tar.gz=a/b",
+        ] {
+            let res = Config::from_args(args(&["server", "--mime", value]));
+            assert!(matches!(res, Err(Error::Config(_))), "{}", value);
+        }
+    }
+
+    #[test]
+    fn parses_quiet() {
+        assert!(!Config::from_args(args(&["server"])).unwrap().quiet);
+        assert!(
+            Config::from_args(args(&["server", "--quiet"]))
+                .unwrap()
+                .quiet
+        );
+    }
+
+    #[test]
+    fn parses_sniff_content_type() {
+        assert!(
+            !Config::from_args(args(&["server"]))
+                .unwrap()
+                .sniff_content_type
+        );
+        assert!(
+            Config::from_args(args(&["server", "--sniff-content-type"]))
+                .unwrap()
+                .sniff_content_type
+        );
+    }
+
+    #[test]
+    fn parses_max_body_size() {
+        let config = Config::from_args(args(&["server", "--max-body-size", "1024"])).unwrap();
+        assert_eq!(config.max_body_size, Some(1024));
+
+        let res = Config::from_args(args(&["server", "--max-body-size", "1k"]));
+        assert!(matches!(res, Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn parses_echo_request() {
+        assert!(!Config::from_args(args(&["server"])).unwrap().echo_request);
+        assert!(
+            Config::from_args(args(&["server", "--echo-request"]))
+                .unwrap()
+                .echo_request
+        );
+    }
+
+    #[test]
+    fn parses_header_size_limits() {
+        let config = Config::from_args(args(&[
+            "server",
+            "--max-header-line-length",
+            "256",
+            "--max-header-size",
+            "4096",
+        ]))
+        .unwrap();
+        assert_eq!(config.max_header_line_length, 256);
+        assert_eq!(config.max_header_size, 4096);
+
+        let res = Config::from_args(args(&["server", "--max-header-size", "big"]));
+        assert!(matches!(res, Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn parses_max_uri_length() {
+        let config = Config::from_args(args(&["server", "--max-uri-length", "512"])).unwrap();
+        assert_eq!(config.max_uri_length, 512);
+
+        let res = Config::from_args(args(&["server", "--max-uri-length", "-1"]));
+        assert!(matches!(res, Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn parses_access_log() {
+        let config =
+            Config::from_args(args(&["server", "--access-log", "/var/log/access.log"])).unwrap();
+        assert_eq!(
+            config.access_log,
+            Some(PathBuf::from("/var/log/access.log"))
+        );
+    }
+
+    #[test]
+    fn parses_file_cache_max_age() {
+        let config = Config::from_args(args(&["server", "--file-cache-max-age", "3600"])).unwrap();
+        assert_eq!(config.file_cache_max_age, 3600);
+
+        let res = Config::from_args(args(&["server", "--file-cache-max-age", "-1"]));
+        assert!(matches!(res, Err(Error::Config(_))));
+    }
+}
@@ -0,0 +1,66 @@
+// Cross-Origin Resource Sharing.
+//
+// Reference: https://fetch.spec.whatwg.org/#http-cors-protocol
+
+use crate::{Cors, Request, Response, Status};
+
+// Returns the value for `Access-Control-Allow-Origin` when the request's
+// origin is allowed by the configuration.
+fn allowed_origin(req: &Request, cors: &Cors) -> Option<String> {
+    match cors {
+        Cors::Disabled => None,
+        Cors::AllowAll => Some("*".to_string()),
+        Cors::Origins(origins) => {
+            let origin = req.get_header("Origin")?;
+            if origins.contains(&origin) {
+                Some(origin)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+// A preflight is an OPTIONS request carrying an `Access-Control-Request-Method`
+// header, sent by browsers before the actual cross-origin request.
+//
+// `allowed_methods` are the methods the requested resource supports, as in
+// the Allow header. A resource without any is not answered here.
+pub fn handle_preflight(
+    req: &Request,
+    cors: &Cors,
+    allowed_methods: Option<String>,
+) -> Option<Response> {
+    if req.method != "OPTIONS" || *cors == Cors::Disabled {
+        return None;
+    }
+    req.get_header("Access-Control-Request-Method")?;
+    let allowed_methods = allowed_methods?;
+
+    let mut res = Response::new(Status::NoContent);
+    res.headers
+        .push(("Access-Control-Allow-Methods".to_string(), allowed_methods));
+
+    // Permissively allow whatever headers the browser asked for.
+    if let Some(headers) = req.get_header("Access-Control-Request-Headers") {
+        res.headers
+            .push(("Access-Control-Allow-Headers".to_string(), headers));
+    }
+
+    res.headers
+        .push(("Access-Control-Max-Age".to_string(), "86400".to_string()));
+
+    Some(res)
+}
+
+// Adds the CORS headers to any response of an allowed origin.
+pub fn apply_headers(req: &Request, res: &mut Response, cors: &Cors) {
+    if let Some(origin) = allowed_origin(req, cors) {
+        if origin != "*" {
+            // The response varies by origin, so caches must not share it.
+            res.headers.push(("Vary".to_string(), "Origin".to_string()));
+        }
+        res.headers
+            .push(("Access-Control-Allow-Origin".to_string(), origin));
+    }
+}
@@ -0,0 +1,626 @@
+// DEFLATE compressed data format.
+//
+// The encoder emits either stored blocks (level 0) or a single block using the
+// fixed Huffman codes, with LZ77 matches found through hash chains. It doesn't
+// compress as well as zlib's dynamic Huffman blocks, but it is small and good
+// enough for text responses. The decoder supports all block types.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc1951
+
+use std::io;
+
+pub(crate) const WINDOW_SIZE: usize = 32 * 1024;
+pub(crate) const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_STORED_BLOCK: usize = 65535;
+pub(crate) const HASH_BITS: u32 = 15;
+
+// Base lengths for the length codes 257..285 and their extra bits.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+// Base distances for the distance codes 0..29 and their extra bits.
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+// Order in which the code length code lengths are stored in a dynamic block.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+// Compresses the data with the given level (0-9). Level 0 only stores the
+// data, higher levels search harder for matches.
+pub fn compress(data: &[u8], level: u32) -> Vec<u8> {
+    let mut out = BitWriter::new();
+
+    if level == 0 {
+        write_stored(&mut out, data);
+        return out.finish();
+    }
+
+    // BFINAL = 1, BTYPE = 01 (fixed Huffman codes).
+    out.write_bits(1, 1);
+    out.write_bits(1, 2);
+
+    let max_chain = match level {
+        1 => 4,
+        2 => 8,
+        3 => 16,
+        4 => 32,
+        5 => 64,
+        6 => 128,
+        7 => 256,
+        8 => 1024,
+        _ => 4096,
+    };
+
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; WINDOW_SIZE];
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let (len, dist) = longest_match(data, pos, &head, &prev, max_chain);
+
+        if len >= MIN_MATCH {
+            write_length(&mut out, len);
+            write_distance(&mut out, dist);
+            for p in pos..pos + len {
+                insert_hash(data, p, &mut head, &mut prev);
+            }
+            pos += len;
+        } else {
+            write_literal(&mut out, data[pos] as u16);
+            insert_hash(data, pos, &mut head, &mut prev);
+            pos += 1;
+        }
+    }
+
+    // End of block.
+    write_literal(&mut out, 256);
+    out.finish()
+}
+
+fn write_stored(out: &mut BitWriter, data: &[u8]) {
+    let mut chunks = data.chunks(MAX_STORED_BLOCK).peekable();
+
+    // An empty input still needs one (empty) final block.
+    if chunks.peek().is_none() {
+        out.write_bits(1, 1);
+        out.write_bits(0, 2);
+        out.align();
+        out.bytes.extend_from_slice(&[0, 0, 0xff, 0xff]);
+        return;
+    }
+
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        out.write_bits(last as u32, 1);
+        out.write_bits(0, 2);
+        out.align();
+
+        let len = chunk.len() as u16;
+        out.bytes.extend_from_slice(&len.to_le_bytes());
+        out.bytes.extend_from_slice(&(!len).to_le_bytes());
+        out.bytes.extend_from_slice(chunk);
+    }
+}
+
+fn hash(data: &[u8], pos: usize) -> usize {
+    let v = (data[pos] as u32) << 16 | (data[pos + 1] as u32) << 8 | data[pos + 2] as u32;
+    (v.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+}
+
+pub(crate) fn insert_hash(data: &[u8], pos: usize, head: &mut [usize], prev: &mut [usize]) {
+    if pos + MIN_MATCH > data.len() {
+        return;
+    }
+
+    let h = hash(data, pos);
+    prev[pos % WINDOW_SIZE] = head[h];
+    head[h] = pos;
+}
+
+pub(crate) fn longest_match(
+    data: &[u8],
+    pos: usize,
+    head: &[usize],
+    prev: &[usize],
+    max_chain: usize,
+) -> (usize, usize) {
+    if pos + MIN_MATCH > data.len() {
+        return (0, 0);
+    }
+
+    let max_len = MAX_MATCH.min(data.len() - pos);
+    let mut best = (0, 0);
+    let mut candidate = head[hash(data, pos)];
+    let mut chain = 0;
+
+    while candidate != usize::MAX && chain < max_chain {
+        if pos - candidate > WINDOW_SIZE - 1 {
+            break;
+        }
+
+        let len = data[candidate..]
+            .iter()
+            .zip(&data[pos..pos + max_len])
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        if len > best.0 {
+            best = (len, pos - candidate);
+            if len == max_len {
+                break;
+            }
+        }
+
+        let next = prev[candidate % WINDOW_SIZE];
+        // Entries of the chain are only valid while they point backwards.
+        if next == usize::MAX || next >= candidate {
+            break;
+        }
+        candidate = next;
+        chain += 1;
+    }
+
+    best
+}
+
+// Writes a literal/length symbol using the fixed Huffman code.
+fn write_literal(out: &mut BitWriter, symbol: u16) {
+    let (code, len) = match symbol {
+        0..=143 => (0x30 + symbol as u32, 8),
+        144..=255 => (0x190 + (symbol as u32 - 144), 9),
+        256..=279 => (symbol as u32 - 256, 7),
+        _ => (0xc0 + (symbol as u32 - 280), 8),
+    };
+    out.write_code(code, len);
+}
+
+fn write_length(out: &mut BitWriter, len: usize) {
+    let idx = LENGTH_BASE
+        .iter()
+        .rposition(|&b| b as usize <= len)
+        .unwrap();
+    write_literal(out, 257 + idx as u16);
+    out.write_bits(
+        (len - LENGTH_BASE[idx] as usize) as u32,
+        LENGTH_EXTRA[idx] as u32,
+    );
+}
+
+fn write_distance(out: &mut BitWriter, dist: usize) {
+    let idx = DIST_BASE.iter().rposition(|&b| b as usize <= dist).unwrap();
+    // Distance codes are fixed 5-bit codes.
+    out.write_code(idx as u32, 5);
+    out.write_bits(
+        (dist - DIST_BASE[idx] as usize) as u32,
+        DIST_EXTRA[idx] as u32,
+    );
+}
+
+pub(crate) struct BitWriter {
+    pub(crate) bytes: Vec<u8>,
+    bit_buf: u32,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    pub(crate) fn new() -> BitWriter {
+        BitWriter {
+            bytes: vec![],
+            bit_buf: 0,
+            bit_count: 0,
+        }
+    }
+
+    // Data elements are packed starting with the least significant bit.
+    pub(crate) fn write_bits(&mut self, value: u32, count: u32) {
+        for i in 0..count {
+            self.bit_buf |= ((value >> i) & 1) << self.bit_count;
+            self.bit_count += 1;
+            if self.bit_count == 8 {
+                self.bytes.push(self.bit_buf as u8);
+                self.bit_buf = 0;
+                self.bit_count = 0;
+            }
+        }
+    }
+
+    // Huffman codes are packed starting with the most significant bit.
+    pub(crate) fn write_code(&mut self, code: u32, len: u32) {
+        for i in (0..len).rev() {
+            self.write_bits((code >> i) & 1, 1);
+        }
+    }
+
+    pub(crate) fn align(&mut self) {
+        if self.bit_count > 0 {
+            self.bytes.push(self.bit_buf as u8);
+            self.bit_buf = 0;
+            self.bit_count = 0;
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
+
+// Decompresses a raw DEFLATE stream. Returns the data and how many input bytes
+// were consumed, since the stream may be followed by a container trailer.
+pub fn inflate(data: &[u8]) -> io::Result<(Vec<u8>, usize)> {
+    inflate_limited(data, usize::MAX)
+}
+
+// Same as `inflate`, but fails with `FileTooLarge` as soon as the data would
+// grow past `max_len` bytes. A few bytes of DEFLATE can stand for gigabytes
+// of output, so untrusted streams must be bounded while decompressing.
+pub fn inflate_limited(data: &[u8], max_len: usize) -> io::Result<(Vec<u8>, usize)> {
+    let mut input = BitReader::new(data);
+    let mut out = Output {
+        data: vec![],
+        max_len,
+    };
+
+    loop {
+        let last = input.read_bits(1)? == 1;
+
+        match input.read_bits(2)? {
+            0 => {
+                input.align();
+                let len = input.read_u16()?;
+                let nlen = input.read_u16()?;
+                if len != !nlen {
+                    return Err(invalid("stored block length mismatch"));
+                }
+                out.reserve(len as usize)?;
+                out.data.extend_from_slice(input.read_bytes(len as usize)?);
+            }
+            1 => {
+                let (lit, dist) = fixed_tables();
+                inflate_block(&mut input, &mut out, &lit, &dist)?;
+            }
+            2 => {
+                let (lit, dist) = dynamic_tables(&mut input)?;
+                inflate_block(&mut input, &mut out, &lit, &dist)?;
+            }
+            _ => return Err(invalid("invalid block type")),
+        }
+
+        if last {
+            break;
+        }
+    }
+
+    input.align();
+    Ok((out.data, input.pos))
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+// Decompressed data, bounded to `max_len` bytes.
+struct Output {
+    data: Vec<u8>,
+    max_len: usize,
+}
+
+impl Output {
+    // Checks there is room for `len` more bytes.
+    fn reserve(&self, len: usize) -> io::Result<()> {
+        if self.data.len().saturating_add(len) > self.max_len {
+            return Err(io::Error::new(
+                io::ErrorKind::FileTooLarge,
+                format!("inflated data is over {} bytes", self.max_len),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn inflate_block(
+    input: &mut BitReader,
+    out: &mut Output,
+    lit: &Huffman,
+    dist: &Huffman,
+) -> io::Result<()> {
+    loop {
+        let symbol = lit.decode(input)?;
+
+        match symbol {
+            0..=255 => {
+                out.reserve(1)?;
+                out.data.push(symbol as u8);
+            }
+            256 => return Ok(()),
+            257..=285 => {
+                let idx = (symbol - 257) as usize;
+                let len =
+                    LENGTH_BASE[idx] as usize + input.read_bits(LENGTH_EXTRA[idx] as u32)? as usize;
+
+                let idx = dist.decode(input)? as usize;
+                if idx >= DIST_BASE.len() {
+                    return Err(invalid("invalid distance code"));
+                }
+                let distance =
+                    DIST_BASE[idx] as usize + input.read_bits(DIST_EXTRA[idx] as u32)? as usize;
+                if distance > out.data.len() {
+                    return Err(invalid("distance too far back"));
+                }
+                out.reserve(len)?;
+
+                // Copy byte by byte since the match may overlap itself.
+                let start = out.data.len() - distance;
+                for i in 0..len {
+                    out.data.push(out.data[start + i]);
+                }
+            }
+            _ => return Err(invalid("invalid literal/length code")),
+        }
+    }
+}
+
+fn fixed_tables() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+fn dynamic_tables(input: &mut BitReader) -> io::Result<(Huffman, Huffman)> {
+    let hlit = input.read_bits(5)? as usize + 257;
+    let hdist = input.read_bits(5)? as usize + 1;
+    let hclen = input.read_bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &idx in CODE_LENGTH_ORDER.iter().take(hclen) {
+        code_lengths[idx] = input.read_bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    let mut lengths: Vec<u8> = Vec::with_capacity(hlit + hdist);
+    while lengths.len() < hlit + hdist {
+        let symbol = code_lengths.decode(input)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let prev = *lengths
+                    .last()
+                    .ok_or_else(|| invalid("repeat without previous length"))?;
+                (prev, 3 + input.read_bits(2)?)
+            }
+            17 => (0, 3 + input.read_bits(3)?),
+            18 => (0, 11 + input.read_bits(7)?),
+            _ => return Err(invalid("invalid code length code")),
+        };
+        lengths.resize(lengths.len() + repeat as usize, value);
+    }
+
+    if lengths.len() > hlit + hdist {
+        return Err(invalid("too many code lengths"));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..hlit]),
+        Huffman::new(&lengths[hlit..]),
+    ))
+}
+
+// Canonical Huffman decoding table, as described in RFC 1951 section 3.2.2.
+pub(crate) struct Huffman {
+    // Number of codes of each bit length.
+    counts: [u16; 16],
+    // Symbols ordered by their code.
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    pub(crate) fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0u16; 16];
+        for len in 1..16 {
+            offsets[len] = offsets[len - 1] + counts[len - 1];
+        }
+
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+
+        Huffman { counts, symbols }
+    }
+
+    pub(crate) fn decode(&self, input: &mut BitReader) -> io::Result<u16> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+
+        for len in 1..16 {
+            code |= input.read_bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + (code - first)) as usize]);
+            }
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+
+        Err(invalid("invalid Huffman code"))
+    }
+}
+
+pub(crate) struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit_buf: u32,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> BitReader<'a> {
+        BitReader {
+            data,
+            pos: 0,
+            bit_buf: 0,
+            bit_count: 0,
+        }
+    }
+
+    pub(crate) fn read_bits(&mut self, count: u32) -> io::Result<u32> {
+        while self.bit_count < count {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+            self.pos += 1;
+            self.bit_buf |= (byte as u32) << self.bit_count;
+            self.bit_count += 8;
+        }
+
+        let value = self.bit_buf & ((1u64 << count) - 1) as u32;
+        self.bit_buf >>= count;
+        self.bit_count -= count;
+        Ok(value)
+    }
+
+    // Discards the remaining bits of the current byte.
+    pub(crate) fn align(&mut self) {
+        self.bit_buf = 0;
+        self.bit_count = 0;
+    }
+
+    fn read_u16(&mut self) -> io::Result<u16> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub(crate) fn read_bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let end = self.pos + len;
+        if end > self.data.len() {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<u8> {
+        let mut data = vec![];
+        for i in 0..2000 {
+            data.extend_from_slice(format!("line {} of some repetitive text\n", i % 37).as_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn round_trips_every_level() {
+        let data = sample();
+        for level in 0..=9 {
+            let compressed = compress(&data, level);
+            let (decompressed, consumed) = inflate(&compressed).unwrap();
+
+            assert_eq!(decompressed, data, "level {}", level);
+            assert_eq!(consumed, compressed.len(), "level {}", level);
+        }
+    }
+
+    #[test]
+    fn round_trips_empty_and_tiny_inputs() {
+        for data in [
+            &b""[..],
+            b"a",
+            b"ab",
+            b"abc",
+            b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+        ] {
+            for level in [0, 6] {
+                let (decompressed, _) = inflate(&compress(data, level)).unwrap();
+                assert_eq!(decompressed, data);
+            }
+        }
+    }
+
+    #[test]
+    fn compresses_repetitive_data() {
+        let data = sample();
+        assert!(compress(&data, 6).len() < data.len() / 4);
+    }
+
+    #[test]
+    fn inflates_dynamic_block() {
+        // Compressed by zlib, which picked a dynamic Huffman block.
+        let compressed = [
+            0xb5, 0xcb, 0xc1, 0x01, 0x40, 0x30, 0x10, 0x05, 0xd1, 0xbb, 0x2a, 0xbe, 0x06, 0xd4,
+            0xe2, 0x90, 0x06, 0x82, 0x4d, 0x2c, 0x61, 0x89, 0x04, 0x49, 0xf5, 0xb6, 0x09, 0xe7,
+            0x79, 0x63, 0x66, 0xc2, 0x99, 0x79, 0x5c, 0x31, 0x44, 0x79, 0x76, 0x38, 0x79, 0xb1,
+            0xe4, 0xed, 0xb8, 0x20, 0x37, 0x45, 0x24, 0xcd, 0xc1, 0xd6, 0x82, 0x49, 0x7c, 0x07,
+            0xf3, 0x1b, 0xee, 0xad, 0xba, 0xad, 0x60, 0x50, 0xf4, 0x70, 0x9a, 0xe1, 0xf8, 0x26,
+            0x4d, 0x95, 0x76, 0x04, 0x3e, 0xb3, 0x44, 0x7d, 0xfd, 0xd5, 0x36, 0x1f,
+        ];
+        let expected = [
+            "The quick brown fox jumps over the lazy dog. "
+                .repeat(3)
+                .as_str(),
+            "Pack my box with five dozen liquor jugs!\n",
+        ]
+        .concat();
+
+        let (data, consumed) = inflate(&compressed).unwrap();
+        assert_eq!(data, expected.as_bytes());
+        assert_eq!(consumed, compressed.len());
+    }
+
+    #[test]
+    fn rejects_truncated_stream() {
+        let compressed = compress(&sample(), 6);
+        assert!(inflate(&compressed[..compressed.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn limits_the_inflated_size() {
+        let data = vec![0u8; 100_000];
+
+        for level in [0, 6] {
+            let compressed = compress(&data, level);
+
+            let (inflated, _) = inflate_limited(&compressed, data.len()).unwrap();
+            assert_eq!(inflated.len(), data.len());
+
+            let err = inflate_limited(&compressed, data.len() - 1).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::FileTooLarge);
+        }
+    }
+}
@@ -0,0 +1,414 @@
+// Content-coding negotiation.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7231#section-5.3.4
+
+use crate::brotli;
+use crate::gzip;
+use crate::zlib;
+use crate::{Body, Config, Request, Response, Status};
+
+// A content-coding responses can be compressed with.
+pub trait Encoding: Sync {
+    // The name it is negotiated with, in Accept-Encoding and
+    // Content-Encoding.
+    fn name(&self) -> &'static str;
+
+    fn encode(&self, data: &[u8], config: &Config) -> Vec<u8>;
+}
+
+struct Brotli;
+
+impl Encoding for Brotli {
+    fn name(&self) -> &'static str {
+        "br"
+    }
+
+    fn encode(&self, data: &[u8], _config: &Config) -> Vec<u8> {
+        brotli::compress(data)
+    }
+}
+
+struct Gzip;
+
+impl Encoding for Gzip {
+    fn name(&self) -> &'static str {
+        "gzip"
+    }
+
+    fn encode(&self, data: &[u8], config: &Config) -> Vec<u8> {
+        gzip::encode(data, config.gzip_level)
+    }
+}
+
+struct Deflate;
+
+impl Encoding for Deflate {
+    fn name(&self) -> &'static str {
+        "deflate"
+    }
+
+    fn encode(&self, data: &[u8], config: &Config) -> Vec<u8> {
+        zlib::encode(data, config.gzip_level)
+    }
+}
+
+// Every content-coding this server can compress with. Which ones are
+// actually used, and in which order of preference, is up to the config.
+const ENCODINGS: [&dyn Encoding; 3] = [&Brotli, &Gzip, &Deflate];
+
+// Looks up a content-coding by (lowercase) name.
+pub fn encoder(name: &str) -> Option<&'static dyn Encoding> {
+    ENCODINGS
+        .into_iter()
+        .find(|encoding| encoding.name() == name)
+}
+
+// Whether the request's Accept-Encoding allows the given content-coding,
+// either by name or through the "*" wildcard, with a non-zero quality.
+//
+// Example: "gzip, deflate;q=0.5, br;q=0".
+pub fn accepts(req: &Request, coding: &str) -> bool {
+    quality(req, coding) > 0.0
+}
+
+// The entity tag of a content-coded representation, derived from the tag of
+// the unencoded one: the coding is appended to the opaque tag, e.g.
+// "\"abc\"" becomes "\"abc-gzip\"". Each representation needs its own tag,
+// a cache couldn't tell them apart otherwise.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc9110#section-8.8.3.3
+pub(crate) fn coded_etag(etag: &str, coding: &str) -> String {
+    match etag.strip_suffix('"') {
+        Some(opaque) => format!("{}-{}\"", opaque, coding),
+        None => etag.to_string(),
+    }
+}
+
+// The entity tags a client may hold for a resource whose unencoded
+// representation is tagged `etag`: that one, and one per content-coding
+// the server may have compressed it with.
+pub(crate) fn representation_etags(etag: &str) -> Vec<String> {
+    let coded = ENCODINGS
+        .into_iter()
+        .map(|encoding| coded_etag(etag, encoding.name()));
+    std::iter::once(etag.to_string()).chain(coded).collect()
+}
+
+// Splits an Accept-Encoding value into its content-codings and their
+// quality, e.g. "gzip;q=0.5, br" gives [("gzip", 0.5), ("br", 1.0)]. Items
+// with an invalid qvalue are left out, as if they weren't listed.
+fn parse_accept_encoding(value: &str) -> Vec<(String, f32)> {
+    value
+        .split(',')
+        .filter_map(|item| {
+            let mut params = item.split(';');
+            let name = params.next().unwrap_or_default().trim();
+            if name.is_empty() {
+                return None;
+            }
+
+            let mut q = 1.0;
+            for param in params {
+                let (key, val) = param.split_once('=').unwrap_or((param, ""));
+                if key.trim().eq_ignore_ascii_case("q") {
+                    q = parse_qvalue(val.trim())?;
+                }
+            }
+
+            Some((name.to_ascii_lowercase(), q))
+        })
+        .collect()
+}
+
+// qvalue = ( "0" [ "." 0*3DIGIT ] ) / ( "1" [ "." 0*3("0") ] )
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7231#section-5.3.1
+fn parse_qvalue(value: &str) -> Option<f32> {
+    let (int, frac) = value.split_once('.').unwrap_or((value, ""));
+    let valid = match int {
+        "0" => frac.len() <= 3 && frac.bytes().all(|b| b.is_ascii_digit()),
+        "1" => frac.len() <= 3 && frac.bytes().all(|b| b == b'0'),
+        _ => false,
+    };
+
+    if valid {
+        value.parse().ok()
+    } else {
+        None
+    }
+}
+
+// The quality the request's Accept-Encoding gives to the content-coding. A
+// coding listed by name takes precedence over the "*" wildcard, and one which
+// isn't listed at all is not acceptable, except for "identity" (no coding)
+// which is acceptable unless explicitly excluded.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7231#section-5.3.4
+fn quality(req: &Request, coding: &str) -> f32 {
+    let unlisted = if coding == "identity" { 1.0 } else { 0.0 };
+    let accept = match req.get_header("Accept-Encoding") {
+        Some(accept) => parse_accept_encoding(&accept),
+        None => return unlisted,
+    };
+
+    let mut wildcard = None;
+
+    for (name, q) in accept {
+        if name == coding {
+            return q;
+        }
+        if name == "*" {
+            wildcard.get_or_insert(q);
+        }
+    }
+
+    wildcard.unwrap_or(unlisted)
+}
+
+// The content-coding to compress the response with: the one the client
+// prefers among the configured ones, if it accepts any.
+fn preferred_coding(req: &Request, config: &Config) -> Option<&'static dyn Encoding> {
+    let mut best = None;
+    let mut best_quality = 0.0;
+
+    for encoding in config.encodings.iter().filter_map(|name| encoder(name)) {
+        let q = quality(req, encoding.name());
+        if q > best_quality {
+            best = Some(encoding);
+            best_quality = q;
+        }
+    }
+
+    best
+}
+
+// Whether a body of the given media type is worth compressing. Images,
+// audio, video and archives are already compressed and would only get
+// bigger; anything else, including unknown types, might be text.
+fn compressible(content_type: &str) -> bool {
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    if media_type == "image/svg+xml" {
+        return true;
+    }
+
+    let (kind, subtype) = media_type.split_once('/').unwrap_or((&media_type, ""));
+    !matches!(kind, "image" | "audio" | "video")
+        && !matches!(
+            subtype,
+            "zip" | "gzip" | "x-gzip" | "x-bzip2" | "x-xz" | "zstd" | "x-7z-compressed" | "pdf"
+        )
+}
+
+// Compresses the response body on the fly with the content-coding the client
+// prefers.
+//
+// Tiny bodies are left alone: compressing them wastes CPU and the format's
+// own framing (18 bytes for gzip) can make them bigger.
+//
+// A client which refuses the uncompressed body (e.g. "identity;q=0") gets it
+// compressed regardless of its size or type, or, if none of the codings it
+// accepts is available, a 406 Not Acceptable.
+pub fn compress_response(req: &Request, res: &mut Response, config: &Config) {
+    if res.status != Status::OK || res.get_header("Content-Encoding").is_some() {
+        return;
+    }
+
+    let body = match &res.body {
+        // Streamed bodies are sent as they are read, they are not compressed.
+        Some(Body::Bytes(body)) => body,
+        _ => return,
+    };
+
+    let identity = quality(req, "identity") > 0.0;
+    let worthwhile = !config.encodings.is_empty()
+        && body.len() >= config.compression_min_size
+        && res.content_type.as_deref().is_none_or(compressible);
+    if identity && !worthwhile {
+        return;
+    }
+
+    // Whether or not it gets compressed, the representation now depends on
+    // the request's Accept-Encoding.
+    if res.get_header("Vary").is_none() {
+        res.headers
+            .push(("Vary".to_string(), "Accept-Encoding".to_string()));
+    }
+
+    let encoding = match preferred_coding(req, config) {
+        Some(encoding) => encoding,
+        None if identity => return,
+        None => {
+            *res = not_acceptable();
+            return;
+        }
+    };
+
+    res.body = Some(Body::Bytes(encoding.encode(body, config)));
+    res.headers
+        .push(("Content-Encoding".to_string(), encoding.name().to_string()));
+
+    // The compressed body is a representation of its own: it needs its own
+    // entity tag, and byte ranges, resolved against the unencoded body, don't
+    // apply to it.
+    for (name, value) in res.headers.iter_mut() {
+        if name.eq_ignore_ascii_case("ETag") {
+            *value = coded_etag(value, encoding.name());
+        } else if name.eq_ignore_ascii_case("Accept-Ranges") {
+            *value = "none".to_string();
+        }
+    }
+}
+
+fn not_acceptable() -> Response {
+    Response {
+        status: Status::NotAcceptable,
+        body: Some(Body::Bytes(
+            b"None of the accepted content-codings is available".to_vec(),
+        )),
+        content_type: Some("text/plain".to_string()),
+        headers: vec![("Vary".to_string(), "Accept-Encoding".to_string())],
+        trailers: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accepting(accept_encoding: &str) -> Request {
+        Request {
+            method: "GET".to_string(),
+            path: "/".to_string(),
+            target: "/".to_string(),
+            http_info: "HTTP/1.1".to_string(),
+            headers: vec![("Accept-Encoding".to_string(), accept_encoding.to_string())],
+            body: vec![],
+        }
+    }
+
+    #[test]
+    fn etags_of_coded_representations() {
+        assert_eq!(coded_etag("\"abc\"", "gzip"), "\"abc-gzip\"");
+        assert_eq!(coded_etag("W/\"abc\"", "br"), "W/\"abc-br\"");
+        assert_eq!(
+            representation_etags("\"abc\""),
+            vec!["\"abc\"", "\"abc-br\"", "\"abc-gzip\"", "\"abc-deflate\""]
+        );
+    }
+
+    #[test]
+    fn parses_qvalues() {
+        assert_eq!(
+            parse_accept_encoding("gzip;q=0.5, br;q=1.0, identity;q=0"),
+            vec![
+                ("gzip".to_string(), 0.5),
+                ("br".to_string(), 1.0),
+                ("identity".to_string(), 0.0)
+            ]
+        );
+        assert_eq!(
+            parse_accept_encoding(" GZIP ; Q=0.25 ,, deflate;level=1"),
+            vec![("gzip".to_string(), 0.25), ("deflate".to_string(), 1.0)]
+        );
+        assert_eq!(parse_accept_encoding(""), vec![]);
+    }
+
+    #[test]
+    fn invalid_qvalues_are_ignored() {
+        for q in ["", "2", "1.5", "1.001", "0.1234", "-0", ".5", "abc", "0.5x"] {
+            assert_eq!(parse_qvalue(q), None, "{:?}", q);
+        }
+        for (q, expected) in [("0", 0.0), ("0.", 0.0), ("0.125", 0.125), ("1.000", 1.0)] {
+            assert_eq!(parse_qvalue(q), Some(expected), "{:?}", q);
+        }
+
+        assert_eq!(
+            parse_accept_encoding("gzip;q=2, br"),
+            vec![("br".to_string(), 1.0)]
+        );
+    }
+
+    #[test]
+    fn quality_of_codings() {
+        let req = accepting("gzip;q=0.5, br;q=1.0, *;q=0.1");
+        assert_eq!(quality(&req, "gzip"), 0.5);
+        assert_eq!(quality(&req, "br"), 1.0);
+        assert_eq!(quality(&req, "deflate"), 0.1);
+        assert_eq!(quality(&req, "identity"), 0.1);
+
+        let req = accepting("gzip");
+        assert_eq!(quality(&req, "deflate"), 0.0);
+        assert_eq!(quality(&req, "identity"), 1.0);
+    }
+
+    #[test]
+    fn identity_can_be_excluded() {
+        for accept_encoding in ["identity;q=0", "*;q=0", "gzip, *;q=0"] {
+            assert!(
+                !accepts(&accepting(accept_encoding), "identity"),
+                "{}",
+                accept_encoding
+            );
+        }
+        for accept_encoding in ["", "gzip;q=0", "identity;q=0.1, *;q=0"] {
+            assert!(
+                accepts(&accepting(accept_encoding), "identity"),
+                "{}",
+                accept_encoding
+            );
+        }
+    }
+
+    #[test]
+    fn prefers_the_highest_quality() {
+        let config = Config::default();
+        let preferred = |accept_encoding| {
+            preferred_coding(&accepting(accept_encoding), &config).map(|encoding| encoding.name())
+        };
+
+        assert_eq!(preferred("gzip;q=0.5, br;q=1.0, identity;q=0"), Some("br"));
+        assert_eq!(preferred("gzip;q=0.5, br;q=0.4"), Some("gzip"));
+        // Ties go to the configured order.
+        assert_eq!(preferred("deflate, gzip"), Some("gzip"));
+        assert_eq!(preferred("br;q=0, gzip;q=0"), None);
+        assert_eq!(preferred("compress"), None);
+    }
+
+    #[test]
+    fn encoders_by_name() {
+        for name in ["br", "gzip", "deflate"] {
+            assert_eq!(encoder(name).map(|encoding| encoding.name()), Some(name));
+        }
+        assert!(encoder("zstd").is_none());
+    }
+
+    #[test]
+    fn compressible_media_types() {
+        for content_type in [
+            "text/plain",
+            "text/html; charset=utf-8",
+            "application/json",
+            "application/javascript",
+            "image/svg+xml",
+            "application/ld+json",
+            "application/octet-stream",
+        ] {
+            assert!(compressible(content_type), "{}", content_type);
+        }
+
+        for content_type in [
+            "image/png",
+            "video/mp4",
+            "application/zip",
+            "application/gzip",
+        ] {
+            assert!(!compressible(content_type), "{}", content_type);
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use std::io;
+
+use crate::Status;
+
+#[derive(Debug)]
+pub enum Error {
+    Config(String),
+    Request(String),
+    Response(String),
+    // The request was rejected and the client should be answered with the
+    // given status before the connection is closed.
+    Status(Status, String),
+    // The client closed the connection while the response was being written.
+    // Nothing is wrong with the server, there is just nobody left to answer.
+    Disconnected(String),
+}
+
+// Whether the IO error means the client closed (or reset) the connection.
+pub(crate) fn is_disconnect(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+    )
+}
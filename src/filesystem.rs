@@ -0,0 +1,269 @@
+// Access to the files served by the /files/ endpoints. Handlers go through
+// this trait (see `Config::filesystem`) instead of calling `std::fs`, so
+// their logic can be tested against an in-memory filesystem.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+pub trait FileSystem: fmt::Debug + Send + Sync {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+    // Creates (or truncates) a file, returning a writer for its contents.
+    fn write(&self, path: &Path) -> io::Result<Box<dyn Write>>;
+    fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>>;
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    pub is_dir: bool,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirEntry {
+    // Non UTF-8 names are converted lossily.
+    pub name: String,
+    pub metadata: Metadata,
+    // Whether the entry itself is a symbolic link. Its metadata is the one
+    // of its target, when that exists.
+    pub is_symlink: bool,
+}
+
+// The actual filesystem.
+#[derive(Debug, Default)]
+pub struct OsFileSystem;
+
+impl FileSystem for OsFileSystem {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn write(&self, path: &Path) -> io::Result<Box<dyn Write>> {
+        Ok(Box::new(fs::File::create(path)?))
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        fs::metadata(path).map(|metadata| Metadata {
+            is_dir: metadata.is_dir(),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        let mut entries = vec![];
+
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let is_symlink = entry.file_type()?.is_symlink();
+            let metadata = match self.metadata(&entry.path()) {
+                // A dangling link is described by itself.
+                Err(e) if is_symlink && e.kind() == io::ErrorKind::NotFound => {
+                    let metadata = fs::symlink_metadata(entry.path())?;
+                    Metadata {
+                        is_dir: false,
+                        len: metadata.len(),
+                        modified: metadata.modified().ok(),
+                    }
+                }
+                metadata => metadata?,
+            };
+            entries.push(DirEntry {
+                name: entry.file_name().to_string_lossy().to_string(),
+                metadata,
+                is_symlink,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+}
+
+#[cfg(test)]
+pub use memory::MemoryFileSystem;
+
+#[cfg(test)]
+mod memory {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    // Files kept in memory, by path. Directories are implied by the paths of
+    // the files in them. Every operation on a path marked as failing returns
+    // that error instead.
+    #[derive(Debug, Default)]
+    pub struct MemoryFileSystem {
+        files: Arc<Mutex<BTreeMap<PathBuf, Vec<u8>>>>,
+        failing: Vec<(PathBuf, io::ErrorKind)>,
+    }
+
+    impl MemoryFileSystem {
+        pub fn new() -> MemoryFileSystem {
+            MemoryFileSystem::default()
+        }
+
+        pub fn with_file(self, path: &str, contents: &[u8]) -> MemoryFileSystem {
+            self.files
+                .lock()
+                .unwrap()
+                .insert(PathBuf::from(path), contents.to_vec());
+            self
+        }
+
+        pub fn with_error(mut self, path: &str, kind: io::ErrorKind) -> MemoryFileSystem {
+            self.failing.push((PathBuf::from(path), kind));
+            self
+        }
+
+        pub fn file(&self, path: &str) -> Option<Vec<u8>> {
+            self.files.lock().unwrap().get(Path::new(path)).cloned()
+        }
+
+        fn check(&self, path: &Path) -> io::Result<()> {
+            match self.failing.iter().find(|(p, _)| p == path) {
+                Some((_, kind)) => Err(io::Error::from(*kind)),
+                None => Ok(()),
+            }
+        }
+
+        fn is_dir(&self, path: &Path) -> bool {
+            let files = self.files.lock().unwrap();
+            files
+                .keys()
+                .any(|file| file.starts_with(path) && file != path)
+        }
+    }
+
+    // Writer which stores the file once dropped.
+    struct MemoryFile {
+        path: PathBuf,
+        contents: Vec<u8>,
+        files: Arc<Mutex<BTreeMap<PathBuf, Vec<u8>>>>,
+    }
+
+    impl Write for MemoryFile {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.contents.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Drop for MemoryFile {
+        fn drop(&mut self) {
+            let contents = std::mem::take(&mut self.contents);
+            self.files
+                .lock()
+                .unwrap()
+                .insert(self.path.clone(), contents);
+        }
+    }
+
+    impl FileSystem for MemoryFileSystem {
+        fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+            self.check(path)?;
+            self.file(&path.to_string_lossy())
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        }
+
+        fn write(&self, path: &Path) -> io::Result<Box<dyn Write>> {
+            self.check(path)?;
+            self.files
+                .lock()
+                .unwrap()
+                .insert(path.to_path_buf(), vec![]);
+
+            Ok(Box::new(MemoryFile {
+                path: path.to_path_buf(),
+                contents: vec![],
+                files: Arc::clone(&self.files),
+            }))
+        }
+
+        fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+            self.check(path)?;
+            if self.is_dir(path) {
+                return Ok(Metadata {
+                    is_dir: true,
+                    len: 0,
+                    modified: None,
+                });
+            }
+
+            let contents = self.read(path)?;
+            Ok(Metadata {
+                is_dir: false,
+                len: contents.len() as u64,
+                modified: Some(SystemTime::UNIX_EPOCH),
+            })
+        }
+
+        fn read_dir(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+            self.check(path)?;
+            if !self.is_dir(path) {
+                return Err(io::Error::from(io::ErrorKind::NotFound));
+            }
+
+            let mut names: Vec<String> = self
+                .files
+                .lock()
+                .unwrap()
+                .keys()
+                .filter_map(|file| file.strip_prefix(path).ok()?.iter().next())
+                .map(|name| name.to_string_lossy().to_string())
+                .collect();
+            names.dedup();
+
+            names
+                .into_iter()
+                .map(|name| {
+                    let metadata = self.metadata(&path.join(&name))?;
+                    Ok(DirEntry {
+                        name,
+                        metadata,
+                        is_symlink: false,
+                    })
+                })
+                .collect()
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+            self.check(from)?;
+            self.check(to)?;
+            let mut files = self.files.lock().unwrap();
+            let contents = files
+                .remove(from)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+            files.insert(to.to_path_buf(), contents);
+            Ok(())
+        }
+
+        fn remove_file(&self, path: &Path) -> io::Result<()> {
+            self.check(path)?;
+            self.files
+                .lock()
+                .unwrap()
+                .remove(path)
+                .map(|_| ())
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        }
+    }
+}
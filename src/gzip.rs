@@ -0,0 +1,144 @@
+// GZIP file format, a DEFLATE stream wrapped with a header and a CRC-32
+// trailer. Used for the "gzip" content-coding.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc1952
+
+use std::io;
+
+use crate::deflate;
+
+const ID1: u8 = 0x1f;
+const ID2: u8 = 0x8b;
+const CM_DEFLATE: u8 = 8;
+
+// Header flags.
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+
+// Operating system that produced the stream: 255 = unknown.
+const OS_UNKNOWN: u8 = 255;
+
+pub fn encode(data: &[u8], level: u32) -> Vec<u8> {
+    // ID1 ID2 CM FLG MTIME(4) XFL OS
+    let mut out = vec![ID1, ID2, CM_DEFLATE, 0, 0, 0, 0, 0, 0, OS_UNKNOWN];
+    out.extend_from_slice(&deflate::compress(data, level));
+    out.extend_from_slice(&crc32(data).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+pub fn decode(data: &[u8]) -> io::Result<Vec<u8>> {
+    decode_limited(data, usize::MAX)
+}
+
+// Same as `decode`, but fails with `FileTooLarge` as soon as the decoded
+// data would grow past `max_len` bytes, see `deflate::inflate_limited`.
+pub fn decode_limited(data: &[u8], max_len: usize) -> io::Result<Vec<u8>> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+    if data.len() < 18 || data[0] != ID1 || data[1] != ID2 {
+        return Err(invalid("not a gzip stream"));
+    }
+    if data[2] != CM_DEFLATE {
+        return Err(invalid("unsupported gzip compression method"));
+    }
+
+    let flags = data[3];
+    let mut pos = 10;
+
+    if flags & FEXTRA != 0 {
+        let len = *data
+            .get(pos)
+            .ok_or_else(|| invalid("truncated gzip header"))? as usize
+            | (*data
+                .get(pos + 1)
+                .ok_or_else(|| invalid("truncated gzip header"))? as usize)
+                << 8;
+        pos += 2 + len;
+    }
+    // File name and comment are zero-terminated strings.
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let end = data
+                .get(pos..)
+                .and_then(|rest| rest.iter().position(|&b| b == 0))
+                .ok_or_else(|| invalid("truncated gzip header"))?;
+            pos += end + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        pos += 2;
+    }
+
+    let stream = data
+        .get(pos..)
+        .ok_or_else(|| invalid("truncated gzip header"))?;
+    let (out, consumed) = deflate::inflate_limited(stream, max_len)?;
+
+    let trailer = stream
+        .get(consumed..consumed + 8)
+        .ok_or_else(|| invalid("truncated gzip trailer"))?;
+    let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+
+    if crc != crc32(&out) || size != out.len() as u32 {
+        return Err(invalid("gzip checksum mismatch"));
+    }
+
+    Ok(out)
+}
+
+// CRC-32 as used by gzip (polynomial 0xedb88320, reflected).
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xedb88320 & mask);
+        }
+    }
+
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn round_trips() {
+        let data = "hello gzip ".repeat(100);
+        let encoded = encode(data.as_bytes(), 6);
+
+        assert_eq!(&encoded[..2], &[ID1, ID2]);
+        assert_eq!(decode(&encoded).unwrap(), data.as_bytes());
+    }
+
+    #[test]
+    fn decodes_stream_with_file_name() {
+        // `printf hello | gzip -n` with the FNAME flag set by hand.
+        let mut encoded = encode(b"hello", 6);
+        encoded[3] = FNAME;
+        encoded.splice(10..10, b"hello.txt\0".iter().copied());
+
+        assert_eq!(decode(&encoded).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn rejects_corrupted_checksum() {
+        let mut encoded = encode(b"hello", 6);
+        let len = encoded.len();
+        encoded[len - 8] ^= 0xff;
+
+        assert!(decode(&encoded).is_err());
+    }
+}
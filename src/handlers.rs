@@ -0,0 +1,1147 @@
+use std::ffi::OsString;
+use std::io;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::base64;
+use crate::encoding;
+use crate::httpdate;
+use crate::json::Value;
+use crate::md5::Md5;
+use crate::range::{self, ByteRange};
+use crate::request::percent_encode_path;
+use crate::sniff;
+use crate::stats::StatsCache;
+use crate::{Body, Config, Error, FileSystem, Metadata, Request, Response, ShutdownHandle, Status};
+
+pub fn handle_get_root(_req: &Request) -> Result<Response, Error> {
+    Ok(Response {
+        status: Status::OK,
+        body: None,
+        content_type: None,
+        headers: vec![],
+        trailers: None,
+    })
+}
+
+pub fn handle_get_echo(req: &Request) -> Result<Response, Error> {
+    let parts: Vec<&str> = req.path.split('/').skip(2).collect();
+    let param = parts.join("/");
+
+    Ok(Response {
+        status: Status::OK,
+        body: Some(Body::Bytes(param.to_string().into_bytes())),
+        content_type: Some("text/plain".to_string()),
+        // Echoes are generated on every request, ranges of them aren't served.
+        headers: vec![("Accept-Ranges".to_string(), "none".to_string())],
+        trailers: None,
+    })
+}
+
+pub fn handle_get_user_agent(req: &Request) -> Result<Response, Error> {
+    Ok(Response {
+        status: Status::OK,
+        body: Some(Body::Bytes(
+            req.get_header("User-Agent")
+                .unwrap_or("".to_string())
+                .into_bytes(),
+        )),
+        content_type: Some("text/plain".to_string()),
+        headers: vec![],
+        trailers: None,
+    })
+}
+
+pub fn handle_get_file(req: &Request, config: &Config) -> Result<Response, Error> {
+    let path = &req.path;
+    // "?download=1" asks for the file to be saved rather than displayed.
+    let download = matches!(req.query("download").as_deref(), Some("1" | "true"));
+
+    // "/files/" (or "/files") targets the directory itself.
+    let segments = match file_segments(path) {
+        Some(segments) => segments,
+        None => return Ok(Response::new(Status::NotFound)),
+    };
+    info!(config, "Segments {:?}", segments);
+
+    let dirpath = config
+        .directory_for(req)
+        .ok_or(Error::Response("error getting directory path".to_string()))?;
+
+    let mut filepath = PathBuf::from(dirpath);
+    filepath.extend(&segments);
+    info!(config, "File path {:?}", filepath);
+
+    let fs = config.filesystem.as_ref();
+
+    if fs.metadata(&filepath).is_ok_and(|m| m.is_dir) {
+        // Serve the first index file found, otherwise list the directory.
+        match config
+            .index_files
+            .iter()
+            .map(|name| filepath.join(name))
+            .find(|index| fs.metadata(index).is_ok_and(|m| !m.is_dir))
+        {
+            Some(index) => filepath = index,
+            None => return handle_list_directory(fs, &filepath),
+        }
+    }
+
+    if !config.serves_extension(&filepath) {
+        info!(
+            config,
+            "Not serving {:?}, its extension is not allowed", filepath
+        );
+        return Ok(Response::new(Status::Forbidden));
+    }
+
+    // A pre-compressed "<filename>.gz" sidecar is another representation of
+    // the file, only served while the file itself exists. It is preferred
+    // when the client accepts gzip, so the file doesn't need to be
+    // compressed on every request. Its validators are its own, since its
+    // bytes differ from the file's.
+    let mut sidecar = filepath.clone().into_os_string();
+    sidecar.push(".gz");
+    let sidecar = PathBuf::from(sidecar);
+    let has_sidecar = fs.metadata(&sidecar).is_ok_and(|m| !m.is_dir);
+
+    let gzipped = has_sidecar && encoding::accepts(req, "gzip");
+    let file = fs.metadata(&filepath).and_then(|metadata| {
+        if gzipped {
+            let sidecar_metadata = fs.metadata(&sidecar)?;
+            return Ok((fs.read(&sidecar)?, sidecar_metadata));
+        }
+        let binary = fs.read(&filepath)?;
+        Ok((binary, metadata))
+    });
+
+    match file {
+        Ok((binary, metadata)) => {
+            let sniffed = match filepath.extension() {
+                None if config.sniff_content_type && !gzipped => sniff::content_type(&binary),
+                _ => None,
+            };
+            let content_type = sniffed
+                .map(str::to_string)
+                .unwrap_or_else(|| config.content_type_for(&filepath));
+            let mut etag = file_etag(&metadata);
+            if gzipped {
+                etag = encoding::coded_etag(&etag, "gzip");
+            }
+            let mut res = file_response(req, binary, &metadata, etag, content_type);
+            if gzipped {
+                info!(config, "Serving pre-compressed file {:?}", sidecar);
+                res.headers
+                    .push(("Content-Encoding".to_string(), "gzip".to_string()));
+            }
+            // Either representation may be chosen by Accept-Encoding.
+            if has_sidecar {
+                res.headers
+                    .push(("Vary".to_string(), "Accept-Encoding".to_string()));
+            }
+            res.headers.push(content_disposition(&filepath, download));
+            Ok(res)
+        }
+        Err(ref e) => {
+            let status = if e.kind() == io::ErrorKind::NotFound {
+                Status::NotFound
+            } else {
+                eprintln!(
+                    "Error: Unexpected error reading file: {:?}, err {}",
+                    filepath, e
+                );
+                Status::InternalServerError
+            };
+            Ok(Response::new(status))
+        }
+    }
+}
+
+// Segments of the path a /files/ request targets, relative to the served
+// directory: "/files/docs/a.txt" is ["docs", "a.txt"], and "/files/" (or
+// "/files") is the directory itself. Request paths are normalized before
+// routing so no "." or ".." segments are left, any found anyway is refused
+// (None) so the path never points outside of the directory.
+fn file_segments(path: &str) -> Option<Vec<&str>> {
+    let segments: Vec<&str> = path
+        .split('/')
+        .skip(2)
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    if segments
+        .iter()
+        .any(|segment| *segment == "." || *segment == "..")
+    {
+        return None;
+    }
+    Some(segments)
+}
+
+// Builds the response for a file, with its validators (ETag and
+// Last-Modified) and honoring the Range header.
+fn file_response(
+    req: &Request,
+    binary: Vec<u8>,
+    metadata: &Metadata,
+    etag: String,
+    content_type: String,
+) -> Response {
+    let len = binary.len() as u64;
+    let last_modified = metadata.modified.map(httpdate::format);
+
+    // Advertise that this resource supports range requests.
+    //
+    // Reference: https://datatracker.ietf.org/doc/html/rfc7233#section-2.3
+    let mut headers = vec![
+        ("Accept-Ranges".to_string(), "bytes".to_string()),
+        ("ETag".to_string(), etag.clone()),
+    ];
+    if let Some(last_modified) = &last_modified {
+        headers.push(("Last-Modified".to_string(), last_modified.clone()));
+    }
+
+    // The client's cached copy is still current, it doesn't need the file
+    // again. If-Modified-Since is only looked at without If-None-Match, the
+    // entity tag being the more accurate validator. The copy may be a
+    // compressed one, the 304 then carries the tag of that representation.
+    let validated = match req.get_header("If-None-Match") {
+        Some(tags) => encoding::representation_etags(&etag)
+            .into_iter()
+            .find(|tag| etag_matches(&tags, tag)),
+        None => req
+            .get_header("If-Modified-Since")
+            .filter(|since| unmodified_since(since, last_modified.as_deref()))
+            .map(|_| etag.clone()),
+    };
+    if let Some(validated) = validated {
+        for (name, value) in headers.iter_mut() {
+            if name == "ETag" {
+                *value = validated.clone();
+            }
+        }
+        return Response {
+            status: Status::NotModified,
+            body: None,
+            content_type: None,
+            headers,
+            trailers: None,
+        };
+    }
+
+    let range = match req.get_header("Range") {
+        Some(range) if if_range_matches(req, &etag, last_modified.as_deref()) => {
+            range::resolve(&range, len)
+        }
+        _ => ByteRange::Full,
+    };
+
+    let (status, body, content_type) = match range {
+        ByteRange::Full => (Status::OK, Some(Body::Bytes(binary)), content_type),
+        ByteRange::Partial(first, last) => {
+            headers.push((
+                "Content-Range".to_string(),
+                format!("bytes {}-{}/{}", first, last, len),
+            ));
+            let body = binary[first as usize..=last as usize].to_vec();
+            (
+                Status::PartialContent,
+                Some(Body::Bytes(body)),
+                content_type,
+            )
+        }
+        ByteRange::Multiple(ranges) => {
+            let boundary = multipart_boundary();
+            let body = range::multipart(&binary, &ranges, &content_type, &boundary);
+            (
+                Status::PartialContent,
+                Some(Body::Bytes(body)),
+                format!("multipart/byteranges; boundary={}", boundary),
+            )
+        }
+        ByteRange::Unsatisfiable => {
+            headers.push(("Content-Range".to_string(), format!("bytes */{}", len)));
+            (Status::RangeNotSatisfiable, None, content_type)
+        }
+    };
+
+    Response {
+        status,
+        content_type: body.as_ref().map(|_| content_type),
+        body,
+        headers,
+        trailers: None,
+    }
+}
+
+// Whether the browser should display the file ("inline") or save it
+// ("attachment"), under its own name in both cases. The name is a quoted
+// string, plus its UTF-8 form percent-encoded when it isn't plain ASCII.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc6266#section-4.1
+fn content_disposition(filepath: &Path, download: bool) -> (String, String) {
+    let name = filepath
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut value = if download { "attachment" } else { "inline" }.to_string();
+
+    let quoted: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && !c.is_ascii_control() {
+                c
+            } else {
+                '_'
+            }
+        })
+        .flat_map(|c| match c {
+            '"' | '\\' => vec!['\\', c],
+            c => vec![c],
+        })
+        .collect();
+    value.push_str(&format!("; filename=\"{}\"", quoted));
+
+    if !name.is_ascii() {
+        let encoded: String = name
+            .bytes()
+            .map(|b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' | b'~' => {
+                    (b as char).to_string()
+                }
+                b => format!("%{:02X}", b),
+            })
+            .collect();
+        value.push_str(&format!("; filename*=UTF-8''{}", encoded));
+    }
+
+    ("Content-Disposition".to_string(), value)
+}
+
+// Separator of the parts of a multipart/byteranges body. It must not occur
+// in the file itself, which a unique per-response value makes unlikely.
+fn multipart_boundary() -> String {
+    static RESPONSES: AtomicUsize = AtomicUsize::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    format!(
+        "byteranges-{:x}-{:x}",
+        nanos,
+        RESPONSES.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+// Strong entity tag made of the file size and modification time, which
+// changes whenever the file is rewritten.
+fn file_etag(metadata: &Metadata) -> String {
+    let modified = metadata
+        .modified
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+
+    format!("\"{:x}-{:x}\"", metadata.len, modified.as_nanos())
+}
+
+// Whether any of the entity tags in a list, as sent in If-None-Match, matches
+// the current one. "*" matches any, and tags are compared weakly: "W/" only
+// says the representation may differ in insignificant ways.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7232#section-3.2
+fn etag_matches(tags: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+
+    tags.trim() == "*" || tags.split(',').any(|tag| opaque(tag) == opaque(etag))
+}
+
+// Whether the file, last modified at the given HTTP-date, hasn't changed
+// since the date in If-Modified-Since. An invalid date, or one in the future,
+// is ignored.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7232#section-3.3
+fn unmodified_since(since: &str, last_modified: Option<&str>) -> bool {
+    match (
+        httpdate::parse(since),
+        last_modified.and_then(httpdate::parse),
+    ) {
+        (Some(since), Some(modified)) => since <= SystemTime::now() && modified <= since,
+        _ => false,
+    }
+}
+
+// A Range is only honored when the If-Range validator (if any) still
+// matches, otherwise the client's partial copy is stale and the whole file
+// is sent instead. The validator is either an entity tag, which must match
+// strongly, or a date, which must be the exact Last-Modified.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7233#section-3.2
+fn if_range_matches(req: &Request, etag: &str, last_modified: Option<&str>) -> bool {
+    let validator = match req.get_header("If-Range") {
+        Some(validator) => validator.trim().to_string(),
+        None => return true,
+    };
+
+    if validator.starts_with('"') || validator.starts_with("W/") {
+        // Weak tags never match.
+        return validator == etag;
+    }
+
+    match (
+        httpdate::parse(&validator),
+        last_modified.and_then(httpdate::parse),
+    ) {
+        (Some(date), Some(modified)) => date == modified,
+        _ => false,
+    }
+}
+
+// Lists the directory entries as a JSON array, sorted by name:
+//
+//  [{"name":"a.txt","type":"file","size":5},{"name":"docs","type":"directory"}]
+fn handle_list_directory(fs: &dyn FileSystem, dirpath: &Path) -> Result<Response, Error> {
+    let entries = match list_directory(fs, dirpath) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!(
+                "Error: Unexpected error listing directory: {:?}, err {}",
+                dirpath, e
+            );
+            return Ok(Response::new(Status::InternalServerError));
+        }
+    };
+
+    Ok(Response::json(Status::OK, &Value::Array(entries)))
+}
+
+fn list_directory(fs: &dyn FileSystem, dirpath: &Path) -> io::Result<Vec<Value>> {
+    let mut entries = vec![];
+
+    // Names are sent as JSON strings, the filesystem already converts non
+    // UTF-8 names lossily rather than producing an invalid document.
+    for entry in fs.read_dir(dirpath)? {
+        let mut fields = vec![("name".to_string(), Value::from(entry.name))];
+        if entry.metadata.is_dir {
+            fields.push(("type".to_string(), Value::from("directory")));
+        } else {
+            fields.push(("type".to_string(), Value::from("file")));
+            fields.push(("size".to_string(), Value::from(entry.metadata.len)));
+        }
+        entries.push(Value::Object(fields));
+    }
+
+    entries.sort_by(|a, b| {
+        a.get("name")
+            .and_then(Value::as_str)
+            .cmp(&b.get("name").and_then(Value::as_str))
+    });
+    Ok(entries)
+}
+
+// The message-body is copied from the connection into the file in chunks, so
+// memory usage doesn't grow with the size of the upload.
+pub fn handle_post_file(
+    req: &Request,
+    body: &mut dyn Read,
+    config: &Config,
+) -> Result<Response, Error> {
+    upload_file(req, body, config, false)
+}
+
+// Same as POST, except that replacing an existing file is told apart from
+// creating a new one: 204 No Content instead of 201 Created.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7231#section-4.3.4
+pub fn handle_put_file(
+    req: &Request,
+    body: &mut dyn Read,
+    config: &Config,
+) -> Result<Response, Error> {
+    upload_file(req, body, config, true)
+}
+
+fn upload_file(
+    req: &Request,
+    body: &mut dyn Read,
+    config: &Config,
+    put: bool,
+) -> Result<Response, Error> {
+    // The body is left unread, it is discarded before the next request.
+    if config.read_only {
+        return Ok(Response::new(Status::Forbidden));
+    }
+
+    // Files are written into existing directories, the directory itself
+    // can't be written.
+    let segments = match file_segments(&req.path) {
+        Some(segments) if !segments.is_empty() => segments,
+        _ => return Ok(Response::new(Status::NotFound)),
+    };
+    info!(config, "Segments {:?}", segments);
+
+    let dirpath = config
+        .directory_for(req)
+        .ok_or(Error::Response("error getting directory path".to_string()))?;
+
+    let mut filepath = PathBuf::from(dirpath);
+    filepath.extend(&segments);
+    info!(config, "File path {:?}", filepath);
+
+    // A file the server wouldn't serve can't be written either.
+    if !config.serves_extension(&filepath) {
+        info!(
+            config,
+            "Not writing {:?}, its extension is not allowed", filepath
+        );
+        return Ok(Response::new(Status::Forbidden));
+    }
+
+    let fs = config.filesystem.as_ref();
+    let existing = fs.metadata(&filepath).ok();
+
+    // Optimistic concurrency: only replace the file the client last saw.
+    if !write_preconditions_hold(req, existing.as_ref()) {
+        info!(config, "Not writing {:?}, a precondition failed", filepath);
+        return Ok(Response::new(Status::PreconditionFailed));
+    }
+
+    let mut res = match req.get_header("Content-MD5") {
+        Some(expected) => post_file_with_md5(config, body, &filepath, expected.trim()),
+        None => post_file(fs, body, &filepath),
+    };
+
+    if res.status == Status::Created && put && existing.is_some() {
+        return Ok(Response::new(Status::NoContent));
+    }
+
+    // Points to the file written (with the base path the server is mounted
+    // under).
+    //
+    // Reference: https://datatracker.ietf.org/doc/html/rfc7231#section-6.3.2
+    if res.status == Status::Created {
+        let location = format!(
+            "{}/files/{}",
+            config.base_path.as_deref().unwrap_or(""),
+            percent_encode_path(&segments.join("/"))
+        );
+        res.headers.push(("Location".to_string(), location));
+    }
+
+    Ok(res)
+}
+
+// Removes a file from the served directory. Directories aren't removed.
+pub fn handle_delete_file(req: &Request, config: &Config) -> Result<Response, Error> {
+    if config.read_only {
+        return Ok(Response::new(Status::Forbidden));
+    }
+
+    let segments = match file_segments(&req.path) {
+        Some(segments) if !segments.is_empty() => segments,
+        _ => return Ok(Response::new(Status::NotFound)),
+    };
+    info!(config, "Segments {:?}", segments);
+
+    let dirpath = config
+        .directory_for(req)
+        .ok_or(Error::Response("error getting directory path".to_string()))?;
+
+    let mut filepath = PathBuf::from(dirpath);
+    filepath.extend(&segments);
+    info!(config, "File path {:?}", filepath);
+
+    if !config.serves_extension(&filepath) {
+        info!(
+            config,
+            "Not deleting {:?}, its extension is not allowed", filepath
+        );
+        return Ok(Response::new(Status::Forbidden));
+    }
+
+    let fs = config.filesystem.as_ref();
+    let status = match fs.metadata(&filepath) {
+        Ok(metadata) if metadata.is_dir => Status::Forbidden,
+        Ok(metadata) if !write_preconditions_hold(req, Some(&metadata)) => {
+            Status::PreconditionFailed
+        }
+        Ok(_) => match fs.remove_file(&filepath) {
+            Ok(()) => Status::NoContent,
+            Err(e) => io_error_status(&filepath, &e),
+        },
+        Err(e) => io_error_status(&filepath, &e),
+    };
+
+    Ok(Response::new(status))
+}
+
+// 404 for a file which doesn't exist, 500 for any other failure.
+fn io_error_status(filepath: &Path, e: &io::Error) -> Status {
+    if e.kind() == io::ErrorKind::NotFound {
+        Status::NotFound
+    } else {
+        eprintln!(
+            "Error: Unexpected error deleting file: {:?}, err {}",
+            filepath, e
+        );
+        Status::InternalServerError
+    }
+}
+
+// Evaluates If-Match, or without it If-Unmodified-Since, against the file
+// about to be written (None if it doesn't exist yet). If-Match needs a
+// strong match, so a file only known by a weak tag is never overwritten,
+// and "*" only matches an existing file. An invalid If-Unmodified-Since, or
+// one for a file which doesn't exist, is ignored.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7232#section-6
+fn write_preconditions_hold(req: &Request, metadata: Option<&Metadata>) -> bool {
+    if let Some(tags) = req.get_header("If-Match") {
+        return match metadata {
+            Some(_) if tags.trim() == "*" => true,
+            Some(metadata) => {
+                let etag = file_etag(metadata);
+                tags.split(',').any(|tag| tag.trim() == etag)
+            }
+            None => false,
+        };
+    }
+
+    let since = req
+        .get_header("If-Unmodified-Since")
+        .and_then(|since| httpdate::parse(&since));
+    let modified = metadata
+        .and_then(|metadata| metadata.modified)
+        .map(httpdate::format)
+        .and_then(|modified| httpdate::parse(&modified));
+
+    match (since, modified) {
+        (Some(since), Some(modified)) => modified <= since,
+        _ => true,
+    }
+}
+
+// The body is streamed into a temporary file next to the target, renamed to
+// the target once complete. An upload failing halfway (malformed, oversized
+// or cut off body) leaves the previous file untouched.
+fn post_file(fs: &dyn FileSystem, body: &mut dyn Read, filepath: &Path) -> Response {
+    let status: Status;
+    let mut content_type: Option<String> = None;
+
+    let tmppath = upload_tmppath(filepath);
+    let written = fs
+        .write(&tmppath)
+        .and_then(|mut file| io::copy(body, &mut file))
+        .and_then(|_| fs.rename(&tmppath, filepath));
+
+    match written {
+        Ok(()) => {
+            status = Status::Created;
+            content_type = Some("application/octet-stream".to_string());
+        }
+        Err(e) => {
+            eprintln!(
+                "Error: Unexpected error writing file: {:?}, err {}",
+                filepath, e
+            );
+            // Don't leave a truncated file behind.
+            let _ = fs.remove_file(&tmppath);
+            status = upload_error_status(&e);
+        }
+    }
+
+    Response {
+        status,
+        body: None,
+        content_type,
+        headers: vec![],
+        trailers: None,
+    }
+}
+
+// A malformed (e.g. badly chunked) or oversized body is the client's fault,
+// as is writing into a directory which doesn't exist.
+fn upload_error_status(e: &io::Error) -> Status {
+    match e.kind() {
+        io::ErrorKind::NotFound => Status::NotFound,
+        io::ErrorKind::InvalidData => Status::BadRequest,
+        io::ErrorKind::FileTooLarge => Status::PayloadTooLarge,
+        _ => Status::InternalServerError,
+    }
+}
+
+// A temporary file next to the target, unique to this upload, for the body
+// to be written to before it replaces the target.
+fn upload_tmppath(filepath: &Path) -> PathBuf {
+    static UPLOADS: AtomicUsize = AtomicUsize::new(0);
+
+    let mut tmpname = OsString::from(".");
+    tmpname.push(filepath.file_name().unwrap_or_default());
+    tmpname.push(format!(
+        ".{}.upload",
+        UPLOADS.fetch_add(1, Ordering::Relaxed)
+    ));
+    filepath.with_file_name(tmpname)
+}
+
+// Content-MD5 holds the base64 encoded MD5 digest of the body, to detect
+// uploads corrupted on the way. The body is streamed into a temporary file
+// next to the target while being hashed, and only renamed to the target
+// when the digest matches, so a corrupted upload never replaces the file.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc1864
+fn post_file_with_md5(
+    config: &Config,
+    body: &mut dyn Read,
+    filepath: &Path,
+    expected: &str,
+) -> Response {
+    let fs = config.filesystem.as_ref();
+    let tmppath = upload_tmppath(filepath);
+    let written = fs.write(&tmppath).and_then(|mut file| {
+        let mut md5 = Md5::new();
+        let mut buf = vec![0u8; 64 * 1024];
+
+        loop {
+            let n = body.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            md5.update(&buf[..n]);
+            file.write_all(&buf[..n])?;
+        }
+
+        Ok(md5.finish())
+    });
+
+    let status = match written {
+        Ok(digest) if base64::encode(&digest) == expected => match fs.rename(&tmppath, filepath) {
+            Ok(()) => Status::Created,
+            Err(e) => {
+                eprintln!("Error: Unexpected error renaming {:?}, err {}", tmppath, e);
+                Status::InternalServerError
+            }
+        },
+        Ok(digest) => {
+            info!(
+                config,
+                "Content-MD5 mismatch for {:?}: expected {}, got {}",
+                filepath,
+                expected,
+                base64::encode(&digest)
+            );
+            Status::BadRequest
+        }
+        Err(e) => {
+            eprintln!(
+                "Error: Unexpected error writing file: {:?}, err {}",
+                tmppath, e
+            );
+            upload_error_status(&e)
+        }
+    };
+
+    if status != Status::Created {
+        let _ = fs.remove_file(&tmppath);
+    }
+
+    Response::new(status)
+}
+
+// Reports which build is running. The commit is only known when the
+// GIT_COMMIT environment variable is set at compile time, e.g.
+// `GIT_COMMIT=$(git rev-parse --short HEAD) cargo build --release`.
+pub fn handle_get_version() -> Result<Response, Error> {
+    let mut info = vec![(
+        "version".to_string(),
+        Value::String(env!("CARGO_PKG_VERSION").to_string()),
+    )];
+    if let Some(commit) = option_env!("GIT_COMMIT") {
+        info.push(("commit".to_string(), Value::String(commit.to_string())));
+    }
+
+    Ok(Response::json(Status::OK, &Value::Object(info)))
+}
+
+// Echoes the request headers exactly as parsed, to debug what a client
+// actually sends. Names are kept as sent and map to the list of their
+// values, so repeated headers show up as several values.
+pub fn handle_get_debug_headers(req: &Request) -> Result<Response, Error> {
+    let mut headers: Vec<(String, Value)> = vec![];
+
+    for (name, value) in req.headers.iter() {
+        let value = Value::String(value.to_string());
+        match headers.iter_mut().find(|(n, _)| n == name) {
+            Some((_, Value::Array(values))) => values.push(value),
+            _ => headers.push((name.to_string(), Value::Array(vec![value]))),
+        }
+    }
+
+    Ok(Response::json(Status::OK, &Value::Object(headers)))
+}
+
+pub fn handle_get_stats(config: &Config, cache: &StatsCache) -> Result<Response, Error> {
+    let dirpath = match &config.directory {
+        Some(dirpath) => dirpath,
+        None => return Ok(Response::new(Status::NotFound)),
+    };
+
+    let stats = match cache.get(config.filesystem.as_ref(), Path::new(dirpath)) {
+        Ok(stats) => stats,
+        Err(e) => {
+            eprintln!(
+                "Error: Unexpected error walking directory: {:?}, err {}",
+                dirpath, e
+            );
+            return Ok(Response::new(Status::InternalServerError));
+        }
+    };
+
+    let body = Value::Object(vec![
+        ("directory".to_string(), Value::from(dirpath.as_str())),
+        ("file_count".to_string(), Value::from(stats.file_count)),
+        ("total_bytes".to_string(), Value::from(stats.total_bytes)),
+    ]);
+
+    Ok(Response::json(Status::OK, &body))
+}
+
+// Stops the server, for controlled teardowns (e.g. from test environments
+// which can't send signals). The request must carry the configured admin
+// token, either as "Authorization: Bearer {token}" or as the bare token.
+//
+// The connections in progress are not interrupted, the server only stops
+// accepting new ones.
+pub fn handle_post_admin_shutdown(
+    req: &Request,
+    config: &Config,
+    shutdown: &ShutdownHandle,
+) -> Result<Response, Error> {
+    let expected = config
+        .admin_token
+        .as_ref()
+        .ok_or(Error::Response("admin token is not configured".to_string()))?;
+
+    let authorization = req.get_header("Authorization").unwrap_or_default();
+    let token = authorization
+        .strip_prefix("Bearer ")
+        .unwrap_or(&authorization)
+        .trim();
+
+    if !constant_time_eq(token.as_bytes(), expected.as_bytes()) {
+        let mut res = Response::new(Status::Unauthorized);
+        res.headers
+            .push(("WWW-Authenticate".to_string(), "Bearer".to_string()));
+        return Ok(res);
+    }
+
+    info!(config, "Shutdown requested through /admin/shutdown");
+    shutdown.shutdown();
+
+    Ok(Response::new(Status::Accepted))
+}
+
+// Compares the whole input regardless of where the first difference is, so
+// the response time doesn't leak how much of the token was guessed right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::MemoryFileSystem;
+    use std::sync::Arc;
+
+    fn get(path: &str) -> Request {
+        Request {
+            method: "GET".to_string(),
+            path: path.split('?').next().unwrap_or_default().to_string(),
+            target: path.to_string(),
+            http_info: "HTTP/1.1".to_string(),
+            headers: vec![],
+            body: vec![],
+        }
+    }
+
+    fn config(fs: MemoryFileSystem) -> Config {
+        Config {
+            directory: Some("/srv".to_string()),
+            filesystem: Arc::new(fs),
+            ..Config::default()
+        }
+    }
+
+    fn body(res: &Response) -> &[u8] {
+        match &res.body {
+            Some(Body::Bytes(bytes)) => bytes,
+            _ => &[],
+        }
+    }
+
+    #[test]
+    fn splits_file_segments() {
+        assert_eq!(file_segments("/files"), Some(vec![]));
+        assert_eq!(file_segments("/files/"), Some(vec![]));
+        assert_eq!(file_segments("/files/a.txt"), Some(vec!["a.txt"]));
+        assert_eq!(
+            file_segments("/files/docs//a.txt"),
+            Some(vec!["docs", "a.txt"])
+        );
+        assert_eq!(file_segments("/files/docs/../a.txt"), None);
+        assert_eq!(file_segments("/files/./a.txt"), None);
+    }
+
+    #[test]
+    fn serves_a_file() {
+        let config = config(MemoryFileSystem::new().with_file("/srv/a.txt", b"hello"));
+
+        let res = handle_get_file(&get("/files/a.txt"), &config).unwrap();
+        assert_eq!(res.status, Status::OK);
+        assert_eq!(body(&res), b"hello");
+    }
+
+    #[test]
+    fn serves_an_index_file_or_lists_the_directory() {
+        let config = config(
+            MemoryFileSystem::new()
+                .with_file("/srv/site/index.html", b"<h1>hi</h1>")
+                .with_file("/srv/docs/a.txt", b"a")
+                .with_file("/srv/docs/sub/b.txt", b"b"),
+        );
+
+        let res = handle_get_file(&get("/files/site"), &config).unwrap();
+        assert_eq!(body(&res), b"<h1>hi</h1>");
+
+        let res = handle_get_file(&get("/files/docs"), &config).unwrap();
+        assert_eq!(
+            body(&res),
+            br#"[{"name":"a.txt","type":"file","size":1},{"name":"sub","type":"directory"}]"#
+        );
+    }
+
+    #[test]
+    fn matches_entity_tags_weakly() {
+        assert!(etag_matches("\"a\"", "\"a\""));
+        assert!(etag_matches("W/\"a\"", "\"a\""));
+        assert!(etag_matches("\"x\", W/\"a\" ,\"y\"", "\"a\""));
+        assert!(etag_matches(" * ", "\"a\""));
+        assert!(!etag_matches("\"b\"", "\"a\""));
+        assert!(!etag_matches("\"a", "\"a\""));
+        assert!(!etag_matches("", "\"a\""));
+    }
+
+    #[test]
+    fn compares_modification_dates() {
+        let modified = "Sun, 06 Nov 1994 08:49:37 GMT";
+
+        assert!(unmodified_since(modified, Some(modified)));
+        assert!(unmodified_since(
+            "Sun, 06 Nov 1994 08:49:38 GMT",
+            Some(modified)
+        ));
+        assert!(unmodified_since(
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            Some(modified)
+        ));
+        assert!(!unmodified_since(
+            "Sun, 06 Nov 1994 08:49:36 GMT",
+            Some(modified)
+        ));
+        assert!(!unmodified_since("yesterday", Some(modified)));
+        assert!(!unmodified_since(modified, None));
+        // A date in the future is invalid.
+        assert!(!unmodified_since(
+            "Fri, 31 Dec 9999 00:00:00 GMT",
+            Some(modified)
+        ));
+    }
+
+    #[test]
+    fn write_preconditions() {
+        let metadata = Metadata {
+            is_dir: false,
+            len: 5,
+            modified: Some(UNIX_EPOCH + std::time::Duration::from_secs(784111777)),
+        };
+        let etag = file_etag(&metadata);
+        let with = |name: &str, value: &str| {
+            let mut req = get("/files/a.txt");
+            req.method = "POST".to_string();
+            req.headers = vec![(name.to_string(), value.to_string())];
+            req
+        };
+
+        assert!(write_preconditions_hold(
+            &get("/files/a.txt"),
+            Some(&metadata)
+        ));
+        assert!(write_preconditions_hold(
+            &with("If-Match", &etag),
+            Some(&metadata)
+        ));
+        assert!(write_preconditions_hold(
+            &with("If-Match", &format!("\"x\", {}", etag)),
+            Some(&metadata)
+        ));
+        assert!(write_preconditions_hold(
+            &with("If-Match", "*"),
+            Some(&metadata)
+        ));
+        assert!(!write_preconditions_hold(
+            &with("If-Match", "\"x\""),
+            Some(&metadata)
+        ));
+        assert!(!write_preconditions_hold(
+            &with("If-Match", &format!("W/{}", etag)),
+            Some(&metadata)
+        ));
+        assert!(!write_preconditions_hold(&with("If-Match", "*"), None));
+
+        let since = |value| with("If-Unmodified-Since", value);
+        assert!(write_preconditions_hold(
+            &since("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(&metadata)
+        ));
+        assert!(!write_preconditions_hold(
+            &since("Sun, 06 Nov 1994 08:49:36 GMT"),
+            Some(&metadata)
+        ));
+        assert!(write_preconditions_hold(
+            &since("not a date"),
+            Some(&metadata)
+        ));
+        assert!(write_preconditions_hold(
+            &since("Sun, 06 Nov 1994 08:49:36 GMT"),
+            None
+        ));
+    }
+
+    #[test]
+    fn deletes_a_file() {
+        let fs = Arc::new(MemoryFileSystem::new().with_file("/srv/a.txt", b"hello"));
+        let config = Config {
+            directory: Some("/srv".to_string()),
+            filesystem: fs.clone(),
+            ..Config::default()
+        };
+        let mut req = get("/files/a.txt");
+        req.method = "DELETE".to_string();
+
+        let res = handle_delete_file(&req, &config).unwrap();
+        assert_eq!(res.status, Status::NoContent);
+        assert_eq!(fs.file("/srv/a.txt"), None);
+
+        let res = handle_delete_file(&req, &config).unwrap();
+        assert_eq!(res.status, Status::NotFound);
+    }
+
+    #[test]
+    fn failed_delete_is_an_internal_error() {
+        let config = config(
+            MemoryFileSystem::new()
+                .with_file("/srv/a.txt", b"hello")
+                .with_error("/srv/a.txt", io::ErrorKind::PermissionDenied),
+        );
+        let mut req = get("/files/a.txt");
+        req.method = "DELETE".to_string();
+
+        let res = handle_delete_file(&req, &config).unwrap();
+        assert_eq!(res.status, Status::InternalServerError);
+    }
+
+    #[test]
+    fn missing_file_is_not_found() {
+        let config = config(MemoryFileSystem::new());
+
+        let res = handle_get_file(&get("/files/missing.txt"), &config).unwrap();
+        assert_eq!(res.status, Status::NotFound);
+    }
+
+    #[test]
+    fn unreadable_file_is_an_internal_error() {
+        let config = config(
+            MemoryFileSystem::new()
+                .with_file("/srv/a.txt", b"hello")
+                .with_error("/srv/a.txt", io::ErrorKind::PermissionDenied),
+        );
+
+        let res = handle_get_file(&get("/files/a.txt"), &config).unwrap();
+        assert_eq!(res.status, Status::InternalServerError);
+    }
+
+    #[test]
+    fn uploads_a_file() {
+        let fs = Arc::new(MemoryFileSystem::new());
+        let config = Config {
+            directory: Some("/srv".to_string()),
+            filesystem: fs.clone(),
+            ..Config::default()
+        };
+        let mut req = get("/files/new.txt");
+        req.method = "POST".to_string();
+
+        let res = handle_post_file(&req, &mut &b"uploaded"[..], &config).unwrap();
+        assert_eq!(res.status, Status::Created);
+        assert_eq!(fs.file("/srv/new.txt").as_deref(), Some(&b"uploaded"[..]));
+    }
+
+    #[test]
+    fn put_creates_or_replaces_a_file() {
+        let fs = Arc::new(MemoryFileSystem::new());
+        let config = Config {
+            directory: Some("/srv".to_string()),
+            filesystem: fs.clone(),
+            ..Config::default()
+        };
+        let mut req = get("/files/new.txt");
+        req.method = "PUT".to_string();
+
+        let res = handle_put_file(&req, &mut &b"first"[..], &config).unwrap();
+        assert_eq!(res.status, Status::Created);
+
+        let res = handle_put_file(&req, &mut &b"second"[..], &config).unwrap();
+        assert_eq!(res.status, Status::NoContent);
+        assert_eq!(fs.file("/srv/new.txt").as_deref(), Some(&b"second"[..]));
+    }
+
+    #[test]
+    fn failed_upload_is_an_internal_error() {
+        let config =
+            config(MemoryFileSystem::new().with_error("/srv/new.txt", io::ErrorKind::StorageFull));
+        let mut req = get("/files/new.txt");
+        req.method = "POST".to_string();
+
+        let res = handle_post_file(&req, &mut &b"uploaded"[..], &config).unwrap();
+        assert_eq!(res.status, Status::InternalServerError);
+    }
+
+    #[test]
+    fn content_disposition_escapes_the_filename() {
+        for (name, download, expected) in [
+            ("a.txt", false, "inline; filename=\"a.txt\""),
+            ("a.txt", true, "attachment; filename=\"a.txt\""),
+            (
+                "say \"hi\\\".txt",
+                true,
+                "attachment; filename=\"say \\\"hi\\\\\\\".txt\"",
+            ),
+            (
+                "résumé.pdf",
+                true,
+                "attachment; filename=\"r_sum_.pdf\"; filename*=UTF-8''r%C3%A9sum%C3%A9.pdf",
+            ),
+        ] {
+            let (key, value) = content_disposition(&Path::new("/srv").join(name), download);
+            assert_eq!(key, "Content-Disposition");
+            assert_eq!(value, expected, "{}", name);
+        }
+    }
+}
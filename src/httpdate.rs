@@ -0,0 +1,241 @@
+// HTTP dates, in the preferred IMF-fixdate format:
+//
+//  Sun, 06 Nov 1994 08:49:37 GMT
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7231#section-7.1.1.1
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+// Times before the epoch are formatted as the epoch itself.
+pub fn format(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let days = secs / 86400;
+    let (year, month, day) = civil_from_days(days as i64);
+    let rem = secs % 86400;
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        // 1970-01-01 was a Thursday.
+        DAYS[(days % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+// Date as written in access logs (Common Log Format), always in UTC:
+//
+//  06/Nov/1994:08:49:37 +0000
+pub fn format_common_log(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let rem = secs % 86400;
+
+    format!(
+        "{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+// Besides IMF-fixdate, the two obsolete formats recipients must still
+// accept are understood:
+//
+//  Sunday, 06-Nov-94 08:49:37 GMT  (RFC 850)
+//  Sun Nov  6 08:49:37 1994        (ANSI C's asctime())
+pub fn parse(value: &str) -> Option<SystemTime> {
+    let value = value.trim();
+
+    if let Some((_, rest)) = value.split_once(", ") {
+        return match rest.split(' ').collect::<Vec<_>>()[..] {
+            // IMF-fixdate: "06 Nov 1994 08:49:37 GMT"
+            [day, month, year, time, "GMT"] if year.len() == 4 => {
+                to_time(day, month, year.parse().ok()?, time)
+            }
+            // RFC 850: "06-Nov-94 08:49:37 GMT"
+            [date, time, "GMT"] => match date.split('-').collect::<Vec<_>>()[..] {
+                [day, month, year] if year.len() == 2 => {
+                    to_time(day, month, full_year(year.parse().ok()?), time)
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+    }
+
+    // asctime: "Nov  6 08:49:37 1994", the day padded with a space.
+    let (_, rest) = value.split_once(' ')?;
+    let parts: Vec<&str> = rest.split(' ').filter(|p| !p.is_empty()).collect();
+    if parts.len() != 4 || parts[3].len() != 4 {
+        return None;
+    }
+    to_time(parts[1], parts[0], parts[3].parse().ok()?, parts[2])
+}
+
+// A two-digit year is in the current century, unless that is more than 50
+// years in the future, then it is in the previous one.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7231#section-7.1.1.1
+fn full_year(year: i64) -> i64 {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / 86400;
+    let (now, _, _) = civil_from_days(days as i64);
+
+    let year = now - now % 100 + year;
+    if year > now + 50 {
+        year - 100
+    } else {
+        year
+    }
+}
+
+// Builds the time from its fields as written: the day ("06"), the month
+// name ("Nov") and the time of day ("08:49:37").
+fn to_time(day: &str, month: &str, year: i64, time: &str) -> Option<SystemTime> {
+    if day.len() > 2 {
+        return None;
+    }
+    let day: u32 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|m| *m == month)? as u32 + 1;
+    if !(1..=31).contains(&day) || year < 1970 {
+        return None;
+    }
+
+    let time: Vec<u64> = time
+        .split(':')
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    if time.len() != 3 || time[0] > 23 || time[1] > 59 || time[2] > 60 {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day) as u64;
+    let secs = days * 86400 + time[0] * 3600 + time[1] * 60 + time[2];
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+// Converts between days since the epoch and a (year, month, day) date in the
+// proleptic Gregorian calendar.
+//
+// Reference: https://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_imf_fixdate() {
+        assert_eq!(format(UNIX_EPOCH), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(
+            format(UNIX_EPOCH + Duration::from_secs(784111777)),
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+        assert_eq!(
+            format(UNIX_EPOCH + Duration::from_secs(951782400)),
+            "Tue, 29 Feb 2000 00:00:00 GMT"
+        );
+    }
+
+    #[test]
+    fn formats_common_log_date() {
+        assert_eq!(
+            format_common_log(UNIX_EPOCH + Duration::from_secs(784111777)),
+            "06/Nov/1994:08:49:37 +0000"
+        );
+    }
+
+    #[test]
+    fn parses_what_it_formats() {
+        for secs in [0, 784111777, 951782400, 1700000000, 4102444799] {
+            let time = UNIX_EPOCH + Duration::from_secs(secs);
+            assert_eq!(parse(&format(time)), Some(time), "{}", secs);
+        }
+    }
+
+    #[test]
+    fn parses_obsolete_formats() {
+        let time = UNIX_EPOCH + Duration::from_secs(784111777);
+
+        assert_eq!(parse("Sunday, 06-Nov-94 08:49:37 GMT"), Some(time));
+        assert_eq!(parse("Sun Nov  6 08:49:37 1994"), Some(time));
+        assert_eq!(
+            parse("Sun Nov 16 08:49:37 1994"),
+            Some(time + Duration::from_secs(10 * 86400))
+        );
+    }
+
+    #[test]
+    fn two_digit_years_are_at_most_50_years_ahead() {
+        let days = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            / 86400;
+        let (now, _, _) = civil_from_days(days as i64);
+
+        assert_eq!(full_year(now % 100), now);
+        assert_eq!(full_year((now + 50) % 100), now + 50);
+        assert_eq!(full_year((now + 51) % 100), now - 49);
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        for value in [
+            "",
+            "Sunday, 06-Nov-1994 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 94",
+            "Sun, 06 Nov 94 08:49:37 GMT",
+            "Sun, 006 Nov 1994 08:49:37 GMT",
+            "Sun, 06 Nov 1994 08:49:37 UTC",
+            "Sun, 06 Foo 1994 08:49:37 GMT",
+            "Sun, 06 Nov 1994 25:49:37 GMT",
+        ] {
+            assert_eq!(parse(value), None, "{:?}", value);
+        }
+    }
+}
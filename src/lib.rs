@@ -0,0 +1,46 @@
+// Basic HTTP implementation.
+//
+// HTTP/1.1 RFC - https://datatracker.ietf.org/doc/html/rfc2616/
+//
+// Other helpful references:
+// - https://developer.mozilla.org/en-US/docs/Web/HTTP
+// - https://thepacketgeek.com/rust/tcpstream/reading-and-writing/
+//
+// The server is exposed as a library so it can be embedded by the binary in
+// `main.rs` as well as by the integration tests, which boot it on an
+// ephemeral port.
+
+// Declared first, so its macros are available to the other modules.
+#[macro_use]
+mod log;
+
+mod accesslog;
+mod base64;
+pub mod brotli;
+mod chunked;
+mod config;
+mod cors;
+mod deflate;
+mod encoding;
+mod error;
+mod filesystem;
+pub mod gzip;
+mod handlers;
+mod httpdate;
+pub mod json;
+mod md5;
+mod range;
+mod ratelimit;
+mod request;
+mod response;
+mod server;
+mod sniff;
+mod stats;
+pub mod zlib;
+
+pub use config::{Config, Cors, TrailingSlash};
+pub use error::Error;
+pub use filesystem::{DirEntry, FileSystem, Metadata, OsFileSystem};
+pub use request::{parse_request, read_request, Request};
+pub use response::{Body, Response, Status, Trailers};
+pub use server::{Server, ShutdownHandle};
@@ -0,0 +1,58 @@
+// Informational output (accepted connections, parsed requests, completed
+// requests...), which `--quiet` turns off. Errors are always written to
+// stderr with `eprintln!` instead.
+//
+// The setting belongs to each server's config, so servers sharing a process
+// (e.g. in tests) don't silence each other.
+
+use std::fmt;
+use std::io;
+
+// Standard output, written through `print!` so the test harness still
+// captures it.
+pub struct Stdout;
+
+impl Stdout {
+    pub fn write_fmt(&mut self, args: fmt::Arguments) -> io::Result<()> {
+        print!("{}", args);
+        Ok(())
+    }
+}
+
+// Writes a line to `out` (as `writeln!` does), unless `quiet` is set. The
+// arguments are only evaluated when the line is actually written.
+macro_rules! info_to {
+    ($quiet:expr, $out:expr, $($arg:tt)*) => {
+        if !$quiet {
+            let _ = writeln!($out, $($arg)*);
+        }
+    };
+}
+
+// Same as `println!`, unless the config given first is quiet, e.g.
+// `info!(config, "Serving {:?}", path)`.
+macro_rules! info {
+    ($config:expr, $($arg:tt)*) => {
+        info_to!($config.quiet, $crate::log::Stdout, $($arg)*)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    #[test]
+    fn quiet_disables_info() {
+        let mut out = Vec::new();
+        // Nothing is written, and the arguments are not even formatted.
+        info_to!(true, &mut out, "{}", {
+            panic!("formatted while quiet");
+            #[allow(unreachable_code)]
+            ""
+        });
+        assert!(out.is_empty());
+
+        info_to!(false, &mut out, "accepted {}", 1);
+        assert_eq!(out, b"accepted 1\n");
+    }
+}
@@ -0,0 +1,261 @@
+// Byte range requests, e.g. "Range: bytes=0-499" for the first 500 bytes.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7233
+
+// Most ranges accepted in a single request. Many tiny (or overlapping)
+// ranges cost far more to serve than the file itself, so such requests
+// just get the whole file.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7233#section-6.1
+const MAX_RANGES: usize = 16;
+
+#[derive(Debug, PartialEq)]
+pub enum ByteRange {
+    // No usable range, the whole representation is sent.
+    Full,
+    // First and last byte positions, both inclusive.
+    Partial(u64, u64),
+    // Several ranges, sent as a multipart/byteranges body.
+    Multiple(Vec<(u64, u64)>),
+    // None of the requested bytes exist.
+    Unsatisfiable,
+}
+
+// Resolves a Range header value against a representation of `len` bytes,
+// e.g. "bytes=0-99,200-299" or "bytes=-500" for the last 500 bytes.
+// A header that can't be parsed is ignored, as the RFC requires. Ranges
+// outside of the representation are dropped, and only when none is left
+// the range is unsatisfiable. Overlapping or adjacent ranges are merged, so
+// no byte is sent twice.
+pub fn resolve(value: &str, len: u64) -> ByteRange {
+    // The range unit is case-insensitive.
+    let specs = match value.trim().split_once('=') {
+        // Empty list elements (e.g. "0-1,,5-6") are allowed and skipped.
+        Some((unit, specs)) if unit.eq_ignore_ascii_case("bytes") => {
+            specs.split(',').map(str::trim).filter(|s| !s.is_empty())
+        }
+        _ => return ByteRange::Full,
+    };
+
+    let mut ranges = vec![];
+    let mut specs = specs.peekable();
+    if specs.peek().is_none() {
+        return ByteRange::Full;
+    }
+    for spec in specs {
+        match resolve_spec(spec, len) {
+            Some(Some(range)) => ranges.push(range),
+            Some(None) => {}
+            None => return ByteRange::Full,
+        }
+    }
+
+    if ranges.len() > MAX_RANGES {
+        return ByteRange::Full;
+    }
+    let ranges = coalesce(ranges);
+
+    match ranges.len() {
+        0 => ByteRange::Unsatisfiable,
+        1 => ByteRange::Partial(ranges[0].0, ranges[0].1),
+        _ => ByteRange::Multiple(ranges),
+    }
+}
+
+// Resolves a single byte-range-spec ("first-last", "first-") or
+// suffix-byte-range-spec ("-length"). Returns None when it is invalid, and
+// Some(None) when it is valid but out of the representation.
+fn resolve_spec(spec: &str, len: u64) -> Option<Option<(u64, u64)>> {
+    let (first, last) = spec.split_once('-')?;
+
+    if first.is_empty() {
+        let suffix = parse_pos(last)?;
+        if suffix == 0 || len == 0 {
+            return Some(None);
+        }
+        // A suffix longer than the representation selects all of it.
+        return Some(Some((len.saturating_sub(suffix), len - 1)));
+    }
+
+    let first = parse_pos(first)?;
+    let last: Option<u64> = match last {
+        "" => None,
+        last => Some(parse_pos(last).filter(|last| *last >= first)?),
+    };
+
+    if first >= len {
+        return Some(None);
+    }
+
+    // A last position past the end just means "until the end".
+    let last = last.unwrap_or(len - 1).min(len - 1);
+    Some(Some((first, last)))
+}
+
+// Merges ranges which overlap or touch. They are kept in the requested
+// order unless some have to be merged, in which case they are sorted.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7233#section-4.1
+fn coalesce(mut ranges: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
+    let overlap = |a: &(u64, u64), b: &(u64, u64)| {
+        a.0 <= b.1.saturating_add(1) && b.0 <= a.1.saturating_add(1)
+    };
+    let any_overlap = ranges
+        .iter()
+        .enumerate()
+        .any(|(i, a)| ranges[i + 1..].iter().any(|b| overlap(a, b)));
+    if !any_overlap {
+        return ranges;
+    }
+
+    ranges.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = vec![];
+    for (first, last) in ranges {
+        match merged.last_mut() {
+            Some(prev) if first <= prev.1.saturating_add(1) => prev.1 = prev.1.max(last),
+            _ => merged.push((first, last)),
+        }
+    }
+    merged
+}
+
+// Byte positions are plain decimal digits, without the sign `parse` allows.
+fn parse_pos(value: &str) -> Option<u64> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+// Builds a multipart/byteranges body, where every part holds one of the
+// ranges with its own Content-Type and Content-Range headers:
+//
+//  --BOUNDARY
+//  Content-Type: text/plain
+//  Content-Range: bytes 0-4/20
+//
+//  hello
+//  --BOUNDARY--
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7233#appendix-A
+pub fn multipart(
+    data: &[u8],
+    ranges: &[(u64, u64)],
+    content_type: &str,
+    boundary: &str,
+) -> Vec<u8> {
+    let mut body = vec![];
+
+    for (first, last) in ranges {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                boundary,
+                content_type,
+                first,
+                last,
+                data.len()
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(&data[*first as usize..=*last as usize]);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_ranges() {
+        assert_eq!(resolve("bytes=0-4", 10), ByteRange::Partial(0, 4));
+        assert_eq!(resolve("bytes=5-", 10), ByteRange::Partial(5, 9));
+        assert_eq!(resolve("bytes=5-100", 10), ByteRange::Partial(5, 9));
+        assert_eq!(resolve("bytes=10-", 10), ByteRange::Unsatisfiable);
+        assert_eq!(resolve("Bytes=0-4", 10), ByteRange::Partial(0, 4));
+        assert_eq!(resolve("BYTES=-2", 10), ByteRange::Partial(8, 9));
+    }
+
+    #[test]
+    fn resolves_suffix_ranges() {
+        assert_eq!(resolve("bytes=-3", 10), ByteRange::Partial(7, 9));
+        assert_eq!(resolve("bytes=-100", 10), ByteRange::Partial(0, 9));
+        assert_eq!(resolve("bytes=-0", 10), ByteRange::Unsatisfiable);
+        assert_eq!(resolve("bytes=-5", 0), ByteRange::Unsatisfiable);
+    }
+
+    #[test]
+    fn resolves_multiple_ranges() {
+        assert_eq!(
+            resolve("bytes=0-1, 5-6,-2", 10),
+            ByteRange::Multiple(vec![(0, 1), (5, 6), (8, 9)])
+        );
+        // Unsatisfiable ranges are dropped from the set.
+        assert_eq!(resolve("bytes=0-1,20-30", 10), ByteRange::Partial(0, 1));
+        assert_eq!(resolve("bytes=20-30,40-", 10), ByteRange::Unsatisfiable);
+        assert_eq!(resolve("bytes=0-1,,", 10), ByteRange::Partial(0, 1));
+
+        // Out of order but disjoint ranges are served as asked.
+        assert_eq!(
+            resolve("bytes=6-7,0-1", 10),
+            ByteRange::Multiple(vec![(6, 7), (0, 1)])
+        );
+
+        let many = vec!["0-0"; MAX_RANGES + 1].join(",");
+        assert_eq!(resolve(&format!("bytes={}", many), 10), ByteRange::Full);
+    }
+
+    #[test]
+    fn coalesces_overlapping_ranges() {
+        assert_eq!(resolve("bytes=0-4,2-6", 10), ByteRange::Partial(0, 6));
+        assert_eq!(resolve("bytes=0-1,2-3", 10), ByteRange::Partial(0, 3));
+        assert_eq!(
+            resolve("bytes=5-,0-1,-3", 10),
+            ByteRange::Multiple(vec![(0, 1), (5, 9)])
+        );
+        assert_eq!(
+            resolve("bytes=8-9,0-0,3-4,4-5", 10),
+            ByteRange::Multiple(vec![(0, 0), (3, 5), (8, 9)])
+        );
+        assert_eq!(
+            resolve(&format!("bytes={}", vec!["0-0"; MAX_RANGES].join(",")), 10),
+            ByteRange::Partial(0, 0)
+        );
+    }
+
+    #[test]
+    fn builds_multipart_body() {
+        let body = multipart(b"0123456789", &[(0, 1), (8, 9)], "text/plain", "XYZ");
+
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "--XYZ\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-1/10\r\n\r\n01\r\n\
+             --XYZ\r\nContent-Type: text/plain\r\nContent-Range: bytes 8-9/10\r\n\r\n89\r\n\
+             --XYZ--\r\n"
+        );
+    }
+
+    #[test]
+    fn ignores_invalid_ranges() {
+        for value in [
+            "",
+            "bytes=",
+            "bytes=a-b",
+            "bytes=5-1",
+            "items=0-1",
+            "bytes=0",
+            "bytes=0-1,x",
+            "bytes=--5",
+            "bytes=+1-2",
+            "bytes=1-+2",
+            "bytes=-+2",
+            "bytes=0x1-2",
+        ] {
+            assert_eq!(resolve(value, 10), ByteRange::Full, "{:?}", value);
+        }
+    }
+}
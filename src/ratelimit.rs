@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Token bucket rate limiter keyed by the client IP address.
+//
+// Every client starts with a full bucket of `rate` tokens (at least one),
+// each request takes a token and tokens are refilled continuously at `rate`
+// per second. So a client can burst up to one second worth of requests, but
+// can't keep going faster than the configured rate.
+pub struct RateLimiter {
+    rate: f64,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64) -> RateLimiter {
+        RateLimiter {
+            rate,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn capacity(&self) -> f64 {
+        self.rate.max(1.0)
+    }
+
+    // Takes a token for the client. When its bucket is empty, returns how
+    // long the client has to wait until the next request is allowed.
+    pub fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        self.check_at(ip, Instant::now())
+    }
+
+    fn check_at(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        let capacity = self.capacity();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        // Buckets which have refilled completely are the same as new ones,
+        // drop them so the map doesn't grow with every client ever seen.
+        if buckets.len() > 1024 {
+            let rate = self.rate;
+            buckets.retain(|_, b| {
+                b.tokens + now.duration_since(b.updated).as_secs_f64() * rate < capacity
+            });
+        }
+
+        let bucket = buckets.entry(ip).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    const A: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    const B: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+    #[test]
+    fn allows_a_burst_up_to_the_rate() {
+        let limiter = RateLimiter::new(3.0);
+        let now = Instant::now();
+
+        assert!(limiter.check_at(A, now).is_ok());
+        assert!(limiter.check_at(A, now).is_ok());
+        assert!(limiter.check_at(A, now).is_ok());
+
+        let wait = limiter.check_at(A, now).unwrap_err();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_millis(334));
+    }
+
+    #[test]
+    fn refills_over_time() {
+        let limiter = RateLimiter::new(2.0);
+        let now = Instant::now();
+
+        assert!(limiter.check_at(A, now).is_ok());
+        assert!(limiter.check_at(A, now).is_ok());
+        assert!(limiter.check_at(A, now).is_err());
+        assert!(limiter
+            .check_at(A, now + Duration::from_millis(500))
+            .is_ok());
+    }
+
+    #[test]
+    fn clients_have_separate_buckets() {
+        let limiter = RateLimiter::new(1.0);
+        let now = Instant::now();
+
+        assert!(limiter.check_at(A, now).is_ok());
+        assert!(limiter.check_at(A, now).is_err());
+        assert!(limiter.check_at(B, now).is_ok());
+    }
+}
@@ -0,0 +1,1579 @@
+use std::io::{self, BufRead, Cursor, Read};
+
+use crate::chunked::{ChunkedReader, TrailerFields};
+use crate::error::is_disconnect;
+use crate::gzip;
+use crate::{Config, Error, Status};
+
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    // Percent-decoded path of the request-target, without the query, e.g.
+    // "/echo/hello world" for "/echo/hello%20world?x=1".
+    pub path: String,
+    // The request-target in origin-form as sent by the client, still
+    // percent-encoded and with its query.
+    pub target: String,
+    pub http_info: String,
+    // Use vector instead of a hash map because
+    // header keys are not unique and could there be multiple
+    // headers for the same key.
+    pub headers: Vec<(String, String)>,
+    // Raw bytes: the message-body is not necessarily text.
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    // Header names are case-insensitive.
+    pub fn get_header(&self, key: &str) -> Option<String> {
+        for (k, v) in self.headers.iter() {
+            if key.eq_ignore_ascii_case(k) {
+                return Some(v.to_string());
+            }
+        }
+
+        None
+    }
+
+    // Length of the message-body declared by the Content-Length header. The
+    // header value is validated while reading the request, repeated values
+    // are known to agree.
+    pub fn content_length(&self) -> usize {
+        self.get_header("Content-Length")
+            .and_then(|val| val.split(',').next()?.trim().parse().ok())
+            .unwrap_or(0)
+    }
+
+    // Transfer codings applied to the message-body, in the order they were
+    // applied, e.g. ["gzip", "chunked"] for "Transfer-Encoding: gzip, chunked".
+    pub fn transfer_codings(&self) -> Vec<String> {
+        self.headers
+            .iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case("Transfer-Encoding"))
+            .flat_map(|(_, v)| v.split(','))
+            .map(|coding| coding.trim().to_ascii_lowercase())
+            .filter(|coding| !coding.is_empty())
+            .collect()
+    }
+
+    // Adds the trailer fields the client announced in the Trailer header to
+    // the request's headers. Undeclared fields are dropped, as are the ones a
+    // sender must not put in a trailer (framing, routing, conditionals,
+    // authentication and content metadata) since they'd arrive too late to
+    // be acted on.
+    //
+    // Reference: https://datatracker.ietf.org/doc/html/rfc9110#section-6.5.1
+    fn merge_trailers(&mut self, trailers: Vec<(String, String)>) {
+        const FORBIDDEN: [&str; 14] = [
+            "Authorization",
+            "Cache-Control",
+            "Content-Encoding",
+            "Content-Length",
+            "Content-Range",
+            "Content-Type",
+            "Expect",
+            "Host",
+            "Max-Forwards",
+            "Range",
+            "TE",
+            "Trailer",
+            "Transfer-Encoding",
+            "Proxy-Authorization",
+        ];
+
+        let declared: Vec<String> = self
+            .headers
+            .iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case("Trailer"))
+            .flat_map(|(_, v)| v.split(','))
+            .map(|name| name.trim().to_string())
+            .collect();
+
+        for (name, value) in trailers {
+            let allowed = declared.iter().any(|d| d.eq_ignore_ascii_case(&name))
+                && !FORBIDDEN.iter().any(|f| f.eq_ignore_ascii_case(&name))
+                && !name.to_ascii_lowercase().starts_with("if-");
+            if allowed {
+                self.headers.push((name, value));
+            }
+        }
+    }
+
+    // Whether a message-body follows the head of the request.
+    pub fn has_body(&self) -> bool {
+        !self.transfer_codings().is_empty() || self.content_length() > 0
+    }
+
+    // The Host header holds the host (and optional port) of the target URI,
+    // e.g. "example.com:8080".
+    pub fn host(&self) -> Option<String> {
+        self.get_header("Host")
+    }
+
+    // Host name of the Host header without the port, e.g. "example.com" or
+    // "[::1]". The header is validated while reading the request.
+    pub fn host_name(&self) -> Option<String> {
+        let host = self.host()?;
+        split_host(host.trim()).map(|(name, _)| name.to_string())
+    }
+
+    // Port of the Host header, when it has one.
+    pub fn host_port(&self) -> Option<u16> {
+        split_host(self.host()?.trim())?.1
+    }
+
+    // HTTP version used for the response: HTTP/1.0 clients get HTTP/1.0
+    // responses, anything else is answered with HTTP/1.1.
+    pub fn response_version(&self) -> &'static str {
+        if self.http_info == "HTTP/1.0" {
+            "HTTP/1.0"
+        } else {
+            "HTTP/1.1"
+        }
+    }
+
+    // Whether the client asked for the connection to be closed after this
+    // request. The Connection header holds a comma-separated list of options,
+    // e.g. "Connection: close" or "Connection: keep-alive, Upgrade".
+    //
+    // HTTP/1.0 connections are closed by default, unless the client asks for
+    // "Connection: keep-alive".
+    //
+    // Reference: https://datatracker.ietf.org/doc/html/rfc7230#section-6.1
+    // Reference: https://datatracker.ietf.org/doc/html/rfc7230#appendix-A.1.2
+    pub fn wants_close(&self) -> bool {
+        if self.http_info == "HTTP/1.0" {
+            return !self.has_connection_option("keep-alive")
+                || self.has_connection_option("close");
+        }
+
+        self.has_connection_option("close")
+    }
+
+    fn has_connection_option(&self, name: &str) -> bool {
+        match self.get_header("Connection") {
+            Some(connection) => connection
+                .split(',')
+                .any(|option| option.trim().eq_ignore_ascii_case(name)),
+            None => false,
+        }
+    }
+
+    // Decodes an "application/x-www-form-urlencoded" body, as sent by HTML
+    // forms, into its name/value pairs, e.g. "a=1&b=hello%20world&c=".
+    // Repeated names are kept in order and a missing "=" means an empty value.
+    //
+    // Reference: https://url.spec.whatwg.org/#urlencoded-parsing
+    pub fn form(&self) -> Result<Vec<(String, String)>, Error> {
+        let content_type = self.get_header("Content-Type").unwrap_or_default();
+        let media_type = content_type.split(';').next().unwrap_or_default().trim();
+        if !media_type.eq_ignore_ascii_case("application/x-www-form-urlencoded") {
+            return Err(Error::Request(format!(
+                "expected a form body, got content type {:?}",
+                content_type
+            )));
+        }
+
+        let body = std::str::from_utf8(&self.body)
+            .map_err(|e| Error::Request(format!("error decoding form body: {}", e)))?;
+
+        urlencoded_pairs(body).collect()
+    }
+
+    // The query of the request-target, e.g. "a=1&b=two%20words" for
+    // "/echo/x?a=1&b=two%20words". Empty when there is none.
+    pub fn query_string(&self) -> &str {
+        self.target.split_once('?').map_or("", |(_, query)| query)
+    }
+
+    // Decodes the query into its name/value pairs, the same way as a form
+    // body: "?a=1&b=two%20words&b=again+and&c" gives a=1, b="two words",
+    // b="again and" and c="". Pairs which can't be decoded are left out.
+    pub fn query_pairs(&self) -> Vec<(String, String)> {
+        urlencoded_pairs(self.query_string())
+            .filter_map(Result::ok)
+            .collect()
+    }
+
+    // Value of the first query parameter with that name, decoded.
+    pub fn query(&self, name: &str) -> Option<String> {
+        self.query_pairs()
+            .into_iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+}
+
+// Splits "application/x-www-form-urlencoded" data (a form body or a query)
+// into its decoded name/value pairs. Repeated names are kept in order and a
+// missing "=" means an empty value.
+fn urlencoded_pairs(data: &str) -> impl Iterator<Item = Result<(String, String), Error>> + '_ {
+    data.split('&').filter(|pair| !pair.is_empty()).map(|pair| {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        Ok((form_decode(name)?, form_decode(value)?))
+    })
+}
+
+// Splits a Host header value into the host name and the port, checking both
+// are well-formed. The name is a registered name (e.g. "example.com"), an
+// IPv4 address, or an IP literal in brackets ("[::1]"); it may be empty.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7230#section-5.4
+// Reference: https://datatracker.ietf.org/doc/html/rfc3986#section-3.2.2
+fn split_host(host: &str) -> Option<(&str, Option<u16>)> {
+    let (name, port) = if host.starts_with('[') {
+        let end = host.find(']')? + 1;
+        let (name, rest) = host.split_at(end);
+        let literal = &name[1..end - 1];
+        if literal.is_empty()
+            || !literal
+                .bytes()
+                .all(|b| b.is_ascii_hexdigit() || b == b':' || b == b'.')
+        {
+            return None;
+        }
+        match rest {
+            "" => (name, None),
+            rest => (name, Some(rest.strip_prefix(':')?)),
+        }
+    } else {
+        let (name, port) = match host.split_once(':') {
+            Some((name, port)) => (name, Some(port)),
+            None => (host, None),
+        };
+        let reg_name = |b: u8| b.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=%".contains(&b);
+        if !name.bytes().all(reg_name) {
+            return None;
+        }
+        (name, port)
+    };
+
+    // An empty port is allowed and means the default one.
+    let port = match port {
+        None | Some("") => None,
+        Some(port) if port.bytes().all(|b| b.is_ascii_digit()) => Some(port.parse().ok()?),
+        Some(_) => return None,
+    };
+
+    Some((name, port))
+}
+
+// Percent-decodes a form name or value, where "+" stands for a space.
+fn form_decode(value: &str) -> Result<String, Error> {
+    percent_decode(&value.replace('+', " "))
+}
+
+// Decodes "%XX" escapes, where both X are hex digits. The decoded bytes
+// must be valid UTF-8.
+pub(crate) fn percent_decode(value: &str) -> Result<String, Error> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            // from_str_radix alone would also take a sign, as in "%+f".
+            let hex = bytes
+                .get(i + 1..i + 3)
+                .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| {
+                    Error::Request(format!("invalid percent-encoding in {:?}", value))
+                })?;
+            out.push(hex);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(out)
+        .map_err(|_| Error::Request(format!("percent-encoded {:?} is not UTF-8", value)))
+}
+
+// Reads exactly one request out of the reader. Any bytes after the request's
+// message-body (e.g. a pipelined request) are left in the reader for the
+// next call.
+//
+// Returns `None` when the client closed the connection before sending
+// anything, e.g. health-check probes and port scanners which connect and
+// immediately disconnect. There is nobody to answer in that case.
+pub fn read_request<R: BufRead>(reader: &mut R, config: &Config) -> Result<Option<Request>, Error> {
+    let mut req = match read_request_head(reader, config)? {
+        Some(req) => req,
+        None => return Ok(None),
+    };
+
+    let mut body = body_reader(reader, &req, config.max_body_size)?;
+    read_body(&mut body, &mut req)?;
+    drop(body);
+
+    info!(config, "Request {:?}", req);
+    Ok(Some(req))
+}
+
+// Parses a complete request out of the bytes, without touching any IO.
+//
+// Invariant: parse_request never panics. Whatever the input, it returns
+// either a Request or an Error, which makes it safe to throw untrusted (or
+// fuzzed) data at it.
+pub fn parse_request(bytes: &[u8]) -> Result<Request, Error> {
+    let mut reader = bytes;
+
+    match read_request(&mut reader, &Config::default())? {
+        Some(req) => Ok(req),
+        None => Err(Error::Request("empty request".to_string())),
+    }
+}
+
+// Room for the method and the HTTP version around the request-target in the
+// request-line, e.g. "OPTIONS " and " HTTP/1.1\r\n".
+const REQUEST_LINE_OVERHEAD: usize = 64;
+
+fn uri_too_long(config: &Config) -> Error {
+    Error::Status(
+        Status::UriTooLong,
+        format!("request-target longer than {} bytes", config.max_uri_length),
+    )
+}
+
+// Reads the request-line and the headers, leaving the message-body in the
+// reader so it can be streamed by the handler instead of being buffered.
+pub fn read_request_head<R: BufRead>(
+    reader: &mut R,
+    config: &Config,
+) -> Result<Option<Request>, Error> {
+    let mut req = Request {
+        method: String::new(),
+        path: String::new(),
+        target: String::new(),
+        http_info: String::new(),
+        headers: vec![],
+        body: vec![],
+    };
+
+    let mut is_first_line = true;
+    // Bytes of header lines read so far.
+    let mut header_size = 0;
+
+    // Read request data
+    //
+    // A request message from a client to a server includes, within the
+    // first line of that message, the method to be applied to the resource,
+    // the identifier of the resource, and the protocol version in use.
+    //
+    //      Request       = Request-Line
+    //                      *(( general-header
+    //                       | request-header
+    //                       | entity-header ) CRLF)
+    //                      CRLF
+    //                      [ message-body ]
+    //
+    // Reference: https://datatracker.ietf.org/doc/html/rfc2616/#section-5
+    loop {
+        let mut buf: Vec<u8> = Vec::new();
+        // Every line is bounded, so an overlong request-target or header is
+        // rejected without being buffered whole.
+        let limit = if is_first_line {
+            (config.max_uri_length + REQUEST_LINE_OVERHEAD) as u64
+        } else {
+            // Room for the CRLF.
+            (config.max_header_line_length + 2) as u64
+        };
+        // Read each request-line one by one.
+        let bytes = (&mut *reader)
+            .take(limit)
+            .read_until(b'\n', &mut buf)
+            .map_err(|e| {
+                if let io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock = e.kind() {
+                    Error::Status(
+                        Status::RequestTimeout,
+                        format!("request head not received in time: {}", e),
+                    )
+                } else if is_disconnect(&e) {
+                    Error::Disconnected(format!("client went away while reading request: {}", e))
+                } else {
+                    Error::Request(format!("error reading buffer: {}", e))
+                }
+            })?;
+
+        if bytes == 0 {
+            // It is empty, nothing else to read.
+            if is_first_line {
+                return Ok(None);
+            }
+
+            return Err(Error::Request(
+                "connection closed before the end of the request header".to_string(),
+            ));
+        };
+
+        if bytes as u64 == limit && !buf.ends_with(b"\n") {
+            if is_first_line {
+                return Err(uri_too_long(config));
+            }
+            return Err(Error::Status(
+                Status::RequestHeaderFieldsTooLarge,
+                format!(
+                    "header line longer than {} bytes",
+                    config.max_header_line_length
+                ),
+            ));
+        }
+
+        if !is_first_line {
+            header_size += bytes;
+            if header_size > config.max_header_size {
+                return Err(Error::Status(
+                    Status::RequestHeaderFieldsTooLarge,
+                    format!("headers larger than {} bytes", config.max_header_size),
+                ));
+            }
+        }
+
+        let line = std::str::from_utf8(&buf)
+            .map_err(|e| bad_request(format!("request line is not valid UTF-8: {}", e)))?;
+
+        info!(config, "line {:?}", line);
+
+        if line == "\r\n" {
+            // Empty lines before the request-line are ignored, some clients
+            // send an extra CRLF after a message-body. They count towards
+            // the header size, so a client can't keep sending them forever.
+            //
+            // Reference: https://datatracker.ietf.org/doc/html/rfc9112#section-2.2
+            if is_first_line {
+                header_size += bytes;
+                if header_size > config.max_header_size {
+                    return Err(Error::Status(
+                        Status::RequestHeaderFieldsTooLarge,
+                        format!("headers larger than {} bytes", config.max_header_size),
+                    ));
+                }
+                continue;
+            }
+
+            // This means the whole header has been read,
+            // and any data next is part of the body.
+            break;
+        }
+
+        let line = line
+            .strip_suffix("\r\n")
+            .ok_or_else(|| bad_request(format!("line not ended by CRLF {:?}", line)))?;
+
+        // Process the general-header, which is always the first request-line.
+        // Example: "GET /pub/WWW/TheProject.html HTTP/1.1".
+        if is_first_line {
+            is_first_line = false;
+
+            let parts: Vec<&str> = line.split(' ').collect();
+            if parts.len() != 3 || !is_token(parts[0]) {
+                return Err(bad_request(format!(
+                    "bad general-header format {:?}",
+                    parts
+                )));
+            }
+
+            if parts[1].len() > config.max_uri_length {
+                return Err(uri_too_long(config));
+            }
+
+            req.method = parts[0].to_string();
+            req.target = origin_form(parts[0], parts[1])?;
+            req.path = normalize_path(&decode_path(&req.target)?);
+            req.http_info = http_version(parts[2])?;
+            continue;
+        }
+
+        // Process request-headers. No whitespace is allowed between the
+        // field-name and the colon, and obsolete line folding (a line
+        // starting with whitespace) isn't supported: both are a 400 as the
+        // way other parsers read them differs.
+        //
+        // Reference: https://datatracker.ietf.org/doc/html/rfc7230#section-3.2.4
+        {
+            let (key, val) = line
+                .split_once(':')
+                .filter(|(key, _)| is_token(key))
+                .ok_or_else(|| bad_request(format!("invalid header field {:?}", line)))?;
+            let key = key.to_string();
+            let val = val.trim_matches([' ', '\t']).to_string();
+
+            // Bound the number of headers so a client can't exhaust the
+            // memory by sending thousands of tiny header lines.
+            if req.headers.len() == config.max_headers {
+                return Err(Error::Status(
+                    Status::RequestHeaderFieldsTooLarge,
+                    format!("more than {} headers", config.max_headers),
+                ));
+            }
+
+            req.headers.push((key, val));
+        }
+    }
+
+    // A client MUST send a Host header field in all HTTP/1.1 request
+    // messages, and a server MUST respond with a 400 (Bad Request) to any
+    // HTTP/1.1 request message which lacks a Host header field or contains
+    // more than one.
+    //
+    // Reference: https://datatracker.ietf.org/doc/html/rfc7230#section-5.4
+    if req.http_info == "HTTP/1.1" {
+        let hosts = req
+            .headers
+            .iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case("Host"))
+            .count();
+
+        if hosts != 1 {
+            return Err(Error::Status(
+                Status::BadRequest,
+                format!("expected exactly one Host header, got {}", hosts),
+            ));
+        }
+    }
+    if let Some(host) = req.host() {
+        if split_host(host.trim()).is_none() {
+            return Err(bad_request(format!("invalid Host header {:?}", host)));
+        }
+    }
+
+    validate_framing(&req)?;
+    validate_transfer_codings(&req)?;
+
+    Ok(Some(req))
+}
+
+fn bad_request(reason: String) -> Error {
+    Error::Status(Status::BadRequest, reason)
+}
+
+// Method and header field names are tokens: visible characters other than
+// delimiters.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7230#section-3.2.6
+fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+// The protocol version is "HTTP/" followed by a major and minor digit. Only
+// HTTP/1.0 and HTTP/1.1 are implemented, a higher minor version is answered
+// as HTTP/1.1 (the client can handle it) and any other major version gets a
+// 505.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7230#section-2.6
+fn http_version(version: &str) -> Result<String, Error> {
+    match version.strip_prefix("HTTP/").map(str::as_bytes) {
+        Some([b'1', b'.', b'0']) => Ok("HTTP/1.0".to_string()),
+        Some([b'1', b'.', minor]) if minor.is_ascii_digit() => Ok("HTTP/1.1".to_string()),
+        Some([major, b'.', minor]) if major.is_ascii_digit() && minor.is_ascii_digit() => {
+            Err(Error::Status(
+                Status::HttpVersionNotSupported,
+                format!("unsupported protocol version {:?}", version),
+            ))
+        }
+        _ => Err(bad_request(format!(
+            "invalid protocol version {:?}",
+            version
+        ))),
+    }
+}
+
+// Percent-decodes the path of an origin-form target, e.g. "/my%20file.txt"
+// becomes "/my file.txt". The query is left out. Unlike in a query (or a form
+// body), "+" is just a "+" in a path.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc3986#section-2.1
+fn decode_path(target: &str) -> Result<String, Error> {
+    let path = target.split('?').next().unwrap_or_default();
+
+    let decoded = percent_decode(path).map_err(|e| match e {
+        Error::Request(reason) => bad_request(reason),
+        e => e,
+    })?;
+    // A NUL can't be part of a file name, and truncates it in some APIs.
+    if decoded.contains('\0') {
+        return Err(bad_request(format!("NUL in request path {:?}", path)));
+    }
+
+    Ok(decoded)
+}
+
+// Removes empty, "." and ".." segments from a decoded path, so equivalent
+// paths route the same and a path can never climb above the root:
+// "/files/../../etc/passwd" is "/etc/passwd" and "/echo//a/./b" is
+// "/echo/a/b". A ".." at the root stays at the root. A trailing slash is
+// kept (and left to the trailing slash policy), as is one implied by a final
+// "." or "..".
+//
+// It runs after percent-decoding, so "%2E%2E" is a ".." segment too.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc3986#section-5.2.4
+fn normalize_path(path: &str) -> String {
+    // Only origin-form paths have segments, not "*".
+    if !path.starts_with('/') {
+        return path.to_string();
+    }
+
+    let mut segments: Vec<&str> = vec![];
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    let last = path.rsplit('/').next().unwrap_or_default();
+    let trailing_slash = matches!(last, "" | "." | "..");
+
+    let mut normalized = format!("/{}", segments.join("/"));
+    if trailing_slash && !segments.is_empty() {
+        normalized.push('/');
+    }
+    normalized
+}
+
+// Percent-encodes a decoded path back for use in a URI, e.g. in a Location
+// header. "/" is kept as the segment separator.
+pub(crate) fn percent_encode_path(path: &str) -> String {
+    path.bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"-._~/!$&'()*+,;=:@".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect()
+}
+
+// Validates the request-target, returning it in origin-form ("/path?query")
+// which is what the routes match on:
+//
+//  * origin-form: "/where?q=now", the usual form.
+//  * absolute-form: "http://www.example.org/pub/WWW/", sent to proxies but
+//    servers must accept it too. Only its path and query are kept.
+//  * asterisk-form: "*", only for a server-wide OPTIONS.
+//
+// Anything else (including an empty target) is a 400.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7230#section-5.3
+fn origin_form(method: &str, target: &str) -> Result<String, Error> {
+    let bad_target = || {
+        Error::Status(
+            Status::BadRequest,
+            format!("invalid request-target {:?}", target),
+        )
+    };
+
+    if target.starts_with('/') {
+        return Ok(target.to_string());
+    }
+    if target == "*" {
+        return match method {
+            "OPTIONS" => Ok(target.to_string()),
+            _ => Err(bad_target()),
+        };
+    }
+
+    let scheme = target.split_once("://").map(|(scheme, _)| scheme);
+    let rest = match scheme {
+        Some(scheme)
+            if scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https") =>
+        {
+            &target[scheme.len() + 3..]
+        }
+        _ => return Err(bad_target()),
+    };
+
+    let path_start = rest.find(['/', '?']).unwrap_or(rest.len());
+    if path_start == 0 {
+        // No authority, e.g. "http:///path".
+        return Err(bad_target());
+    }
+
+    match &rest[path_start..] {
+        "" => Ok("/".to_string()),
+        path if path.starts_with('?') => Ok(format!("/{}", path)),
+        path => Ok(path.to_string()),
+    }
+}
+
+// Transfer codings this server can decode.
+const TRANSFER_CODINGS: [&str; 3] = ["chunked", "gzip", "x-gzip"];
+
+// The message-body length must be unambiguous: if this server and a proxy
+// in front of it disagree on where a request ends, the leftover bytes are
+// taken for another request (request smuggling). So instead of picking one
+// interpretation, requests are rejected when:
+//
+//  * a Content-Length value isn't a plain decimal number.
+//  * Content-Length is repeated (or a list) with differing values.
+//  * both Content-Length and Transfer-Encoding are sent.
+//
+// A 400 closes the connection, the rest of the stream can't be trusted.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7230#section-3.3.3
+fn validate_framing(req: &Request) -> Result<(), Error> {
+    let lengths: Vec<&str> = req
+        .headers
+        .iter()
+        .filter(|(k, _)| k.eq_ignore_ascii_case("Content-Length"))
+        .flat_map(|(_, v)| v.split(','))
+        .map(str::trim)
+        .collect();
+
+    if let Some(invalid) = lengths
+        .iter()
+        .find(|len| len.is_empty() || !len.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return Err(bad_request(format!("invalid Content-Length {:?}", invalid)));
+    }
+    // Leading zeros don't change the value, "05" and "5" agree.
+    let mut values: Vec<&str> = lengths
+        .iter()
+        .map(|len| match len.trim_start_matches('0') {
+            "" => "0",
+            len => len,
+        })
+        .collect();
+    values.dedup();
+    if values.len() > 1 {
+        return Err(bad_request(format!(
+            "conflicting Content-Length values {:?}",
+            lengths
+        )));
+    }
+    if values
+        .first()
+        .is_some_and(|len| len.parse::<usize>().is_err())
+    {
+        return Err(bad_request(format!(
+            "Content-Length {} is too large",
+            values[0]
+        )));
+    }
+
+    let transfer_encoding = req
+        .headers
+        .iter()
+        .any(|(k, _)| k.eq_ignore_ascii_case("Transfer-Encoding"));
+    if transfer_encoding && !lengths.is_empty() {
+        return Err(bad_request(
+            "both Content-Length and Transfer-Encoding are set".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+// Chunked must be applied exactly once and last, otherwise the end of the
+// message-body can't be found. Codings the server doesn't implement get a
+// 501 (Not Implemented).
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7230#section-3.3.1
+fn validate_transfer_codings(req: &Request) -> Result<(), Error> {
+    let codings = req.transfer_codings();
+    if codings.is_empty() {
+        return Ok(());
+    }
+
+    if let Some(coding) = codings
+        .iter()
+        .find(|coding| !TRANSFER_CODINGS.contains(&coding.as_str()))
+    {
+        return Err(Error::Status(
+            Status::NotImplemented,
+            format!("unsupported transfer coding {:?}", coding),
+        ));
+    }
+
+    let chunked = codings.iter().filter(|c| *c == "chunked").count();
+    if chunked != 1 || codings.last().map(String::as_str) != Some("chunked") {
+        return Err(Error::Status(
+            Status::BadRequest,
+            format!(
+                "chunked must be the final transfer coding, got {:?}",
+                codings
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+// The message-body of a request, as returned by `body_reader`.
+pub struct MessageBody<'a> {
+    reader: Box<dyn Read + 'a>,
+    trailers: TrailerFields,
+    // Whether a read failed (malformed, oversized, cut off or timed out).
+    failed: bool,
+}
+
+impl MessageBody<'_> {
+    // Whether reading the body failed. The position of the next request on
+    // the connection is unknown then.
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+
+    // Trailer fields sent after a chunked body. Only complete once the body
+    // has been read to the end.
+    pub fn trailers(&self) -> Vec<(String, String)> {
+        self.trailers.borrow().clone()
+    }
+}
+
+impl Read for MessageBody<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf);
+        if read
+            .as_ref()
+            .is_err_and(|e| e.kind() != io::ErrorKind::Interrupted)
+        {
+            self.failed = true;
+        }
+        read
+    }
+}
+
+// Reader for the message-body of the request, with its transfer codings (if
+// any) undone. A body without transfer codings is exactly Content-Length
+// bytes long.
+//
+// Codings are removed in the reverse order they were applied: a
+// "gzip, chunked" body is dechunked and then decompressed. Decompressing
+// needs the whole gzip stream, so such bodies are buffered here.
+//
+// Reading more than `max_size` bytes (before or after decompressing) fails,
+// so a client can't make the server buffer or store an unbounded body.
+pub fn body_reader<'a, R: BufRead + 'a>(
+    reader: &'a mut R,
+    req: &Request,
+    max_size: Option<u64>,
+) -> Result<MessageBody<'a>, Error> {
+    let max_size = max_size.unwrap_or(u64::MAX);
+
+    let codings = req.transfer_codings();
+    if codings.is_empty() {
+        return Ok(MessageBody {
+            reader: Box::new(SizeLimit::new(
+                reader.take(req.content_length() as u64),
+                max_size,
+            )),
+            trailers: TrailerFields::default(),
+            failed: false,
+        });
+    }
+
+    let chunked = ChunkedReader::new(reader);
+    let trailers = chunked.trailers();
+    let mut body: Box<dyn Read + 'a> = Box::new(SizeLimit::new(chunked, max_size));
+
+    for coding in codings.iter().rev().skip(1) {
+        match coding.as_str() {
+            "gzip" | "x-gzip" => {
+                let mut compressed = vec![];
+                body.read_to_end(&mut compressed)
+                    .map_err(|e| body_error(&e))?;
+                let max_len = usize::try_from(max_size).unwrap_or(usize::MAX);
+                let decoded = gzip::decode_limited(&compressed, max_len).map_err(|e| {
+                    if e.kind() == io::ErrorKind::FileTooLarge {
+                        body_error(&e)
+                    } else {
+                        Error::Status(Status::BadRequest, format!("invalid gzip body: {}", e))
+                    }
+                })?;
+                body = Box::new(SizeLimit::new(Cursor::new(decoded), max_size));
+            }
+            coding => {
+                return Err(Error::Status(
+                    Status::NotImplemented,
+                    format!("unsupported transfer coding {:?}", coding),
+                ))
+            }
+        }
+    }
+
+    Ok(MessageBody {
+        reader: body,
+        trailers,
+        failed: false,
+    })
+}
+
+// Fails with `FileTooLarge` once more than `max` bytes would be read.
+struct SizeLimit<R> {
+    inner: R,
+    max: u64,
+    read: u64,
+}
+
+impl<R: Read> SizeLimit<R> {
+    fn new(inner: R, max: u64) -> SizeLimit<R> {
+        SizeLimit {
+            inner,
+            max,
+            read: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for SizeLimit<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let too_large = || {
+            io::Error::new(
+                io::ErrorKind::FileTooLarge,
+                format!("message-body is over {} bytes", self.max),
+            )
+        };
+        if self.read > self.max {
+            return Err(too_large());
+        }
+
+        // One byte past the limit is enough to tell the body is too large.
+        let room = (self.max - self.read).saturating_add(1);
+        let len = buf.len().min(usize::try_from(room).unwrap_or(usize::MAX));
+
+        let n = self.inner.read(&mut buf[..len])?;
+        self.read += n as u64;
+        if self.read > self.max {
+            return Err(too_large());
+        }
+        Ok(n)
+    }
+}
+
+// Malformed framing is the client's fault and gets a 400, as does a body
+// over the size limit with a 413. Anything else means the body couldn't be
+// read at all.
+pub(crate) fn body_error(e: &io::Error) -> Error {
+    match e.kind() {
+        io::ErrorKind::InvalidData => {
+            Error::Status(Status::BadRequest, format!("invalid message-body: {}", e))
+        }
+        io::ErrorKind::FileTooLarge => Error::Status(Status::PayloadTooLarge, e.to_string()),
+        _ => Error::Request(format!("error reading message-body: {}", e)),
+    }
+}
+
+// Read the message-body out of the header loop because the message-body
+// might not end with a `\n` so we cannot rely on "read until \n"
+// otherwise the reader would stuck forever waiting for a `\n`.
+// Therefore, if it was detected there is a message-body, it reads
+// exactly Content-Length bytes (or up to the last chunk), so a pipelined
+// request sent right after this one is not swallowed as part of this body.
+//
+// Trailer fields announced by the request's Trailer header are merged into
+// its headers once the body has been read.
+pub fn read_body(reader: &mut MessageBody, req: &mut Request) -> Result<(), Error> {
+    let content_length = req.content_length();
+    let chunked = !req.transfer_codings().is_empty();
+
+    if content_length > 0 || chunked {
+        // Don't preallocate the declared length: it comes from the client
+        // and could be anything.
+        let mut received: Vec<u8> = vec![];
+        reader
+            .read_to_end(&mut received)
+            .map_err(|e| body_error(&e))?;
+
+        if !chunked && received.len() != content_length {
+            return Err(Error::Request(format!(
+                "message-body ended after {} of {} bytes",
+                received.len(),
+                content_length
+            )));
+        }
+
+        req.body = received;
+        req.merge_trailers(reader.trailers());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(http_info: &str, headers: &[(&str, &str)]) -> Request {
+        Request {
+            method: "GET".to_string(),
+            path: "/".to_string(),
+            target: "/".to_string(),
+            http_info: http_info.to_string(),
+            headers: headers
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            body: vec![],
+        }
+    }
+
+    #[test]
+    fn wants_close() {
+        assert!(!request("HTTP/1.1", &[]).wants_close());
+        assert!(request("HTTP/1.1", &[("Connection", "close")]).wants_close());
+        assert!(request("HTTP/1.1", &[("connection", "Keep-Alive, Close")]).wants_close());
+        assert!(!request("HTTP/1.1", &[("Connection", "keep-alive")]).wants_close());
+        assert!(request("HTTP/1.0", &[]).wants_close());
+        assert!(!request("HTTP/1.0", &[("Connection", "Keep-Alive")]).wants_close());
+        assert!(request("HTTP/1.0", &[("Connection", "keep-alive, close")]).wants_close());
+    }
+
+    fn read(raw: &str) -> Result<Option<Request>, Error> {
+        read_request(
+            &mut Cursor::new(raw.as_bytes().to_vec()),
+            &Config::default(),
+        )
+    }
+
+    #[test]
+    fn http_1_1_requires_a_host() {
+        let res = read("GET / HTTP/1.1\r\n\r\n");
+        assert!(matches!(res, Err(Error::Status(Status::BadRequest, _))));
+
+        let req = read("GET / HTTP/1.1\r\nHost: example.com\r\n\r\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(req.host().as_deref(), Some("example.com"));
+
+        let res = read("GET / HTTP/1.1\r\nHost: a.com\r\nHost: b.com\r\n\r\n");
+        assert!(matches!(res, Err(Error::Status(Status::BadRequest, _))));
+    }
+
+    #[test]
+    fn parses_the_host() {
+        for (host, name, port) in [
+            ("example.com", "example.com", None),
+            ("example.com:8080", "example.com", Some(8080)),
+            ("127.0.0.1:4221", "127.0.0.1", Some(4221)),
+            ("[::1]", "[::1]", None),
+            ("[::1]:4221", "[::1]", Some(4221)),
+            ("example.com:", "example.com", None),
+            ("", "", None),
+        ] {
+            let raw = format!("GET / HTTP/1.1\r\nHost: {}\r\n\r\n", host);
+            let req = read(&raw).unwrap().unwrap();
+            assert_eq!(req.host_name().as_deref(), Some(name), "{:?}", host);
+            assert_eq!(req.host_port(), port, "{:?}", host);
+        }
+
+        for host in [
+            "exa mple.com",
+            "example.com:80:80",
+            "example.com:http",
+            "example.com:65536",
+            "a/b",
+            "a@b",
+            "[::1",
+            "[]",
+            "[::1]x",
+            "[zz::1]",
+        ] {
+            let raw = format!("GET / HTTP/1.1\r\nHost: {}\r\n\r\n", host);
+            assert!(
+                matches!(read(&raw), Err(Error::Status(Status::BadRequest, _))),
+                "{:?}",
+                host
+            );
+        }
+    }
+
+    #[test]
+    fn http_1_0_does_not_require_a_host() {
+        let req = read("GET / HTTP/1.0\r\n\r\n").unwrap().unwrap();
+        assert_eq!(req.host(), None);
+    }
+
+    fn form_request(body: &str) -> Request {
+        let mut req = request(
+            "HTTP/1.1",
+            &[("Content-Type", "application/x-www-form-urlencoded")],
+        );
+        req.body = body.as_bytes().to_vec();
+        req
+    }
+
+    #[test]
+    fn parses_form_body() {
+        let req = form_request("a=1&b=hello%20world&c=&b=again+and%2Bplus&d");
+
+        assert_eq!(
+            req.form().unwrap(),
+            vec![
+                ("a".to_string(), "1".to_string()),
+                ("b".to_string(), "hello world".to_string()),
+                ("c".to_string(), "".to_string()),
+                ("b".to_string(), "again and+plus".to_string()),
+                ("d".to_string(), "".to_string()),
+            ]
+        );
+        assert_eq!(form_request("").form().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn rejects_invalid_form_body() {
+        for body in ["a=%", "a=%2", "a=%zz", "a=%ff"] {
+            assert!(form_request(body).form().is_err(), "{:?}", body);
+        }
+
+        let mut req = form_request("a=1");
+        req.body = b"a=\xff".to_vec();
+        assert!(req.form().is_err());
+
+        let mut req = form_request("a=1");
+        req.headers = vec![("Content-Type".to_string(), "text/plain".to_string())];
+        assert!(req.form().is_err());
+    }
+
+    #[test]
+    fn get_body_is_consumed() {
+        for raw in [
+            "GET /echo/a HTTP/1.1\r\nHost: x\r\nContent-Length: 5\r\n\r\nhello",
+            "GET /echo/a HTTP/1.1\r\nHost: x\r\nTransfer-Encoding: chunked\r\n\r\n\
+             5\r\nhello\r\n0\r\n\r\n",
+        ] {
+            let mut reader = Cursor::new(format!("{}GET /echo/b HTTP/1.1\r\nHost: x\r\n\r\n", raw));
+
+            let first = read_request(&mut reader, &Config::default())
+                .unwrap()
+                .unwrap();
+            assert_eq!(first.body, b"hello");
+
+            let second = read_request(&mut reader, &Config::default())
+                .unwrap()
+                .unwrap();
+            assert_eq!(second.method, "GET");
+            assert_eq!(second.path, "/echo/b");
+        }
+    }
+
+    #[test]
+    fn reads_a_body_larger_than_the_buffer() {
+        let body = "x".repeat(100);
+        let raw = format!(
+            "POST /echo/a HTTP/1.1\r\nHost: x\r\nContent-Length: {}\r\n\r\n{}\
+             GET /echo/b HTTP/1.1\r\nHost: x\r\n\r\n",
+            body.len(),
+            body
+        );
+        // A buffer much smaller than the body, so it takes many reads.
+        let mut reader = std::io::BufReader::with_capacity(8, Cursor::new(raw));
+
+        let first = read_request(&mut reader, &Config::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.body, body.as_bytes());
+
+        let second = read_request(&mut reader, &Config::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(second.path, "/echo/b");
+    }
+
+    #[test]
+    fn parses_a_complete_request() {
+        let req = parse_request(
+            b"POST /files/a HTTP/1.1\r\nHost: localhost\r\nContent-Length: 3\r\n\r\nabc",
+        )
+        .unwrap();
+
+        assert_eq!(req.method, "POST");
+        assert_eq!(req.path, "/files/a");
+        assert_eq!(req.http_info, "HTTP/1.1");
+        assert_eq!(req.body, b"abc");
+    }
+
+    #[test]
+    fn parses_a_binary_body() {
+        let req = parse_request(
+            b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 4\r\n\r\n\xff\x00\xfe\x01",
+        )
+        .unwrap();
+
+        assert_eq!(req.body, b"\xff\x00\xfe\x01");
+    }
+
+    #[test]
+    fn parses_a_chunked_request() {
+        let req = parse_request(
+            b"POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n",
+        )
+        .unwrap();
+
+        assert_eq!(req.transfer_codings(), vec!["chunked"]);
+        assert_eq!(req.body, b"abc");
+    }
+
+    #[test]
+    fn merges_declared_trailers() {
+        let req = parse_request(
+            b"POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\
+              Trailer: x-checksum, Host, If-Match\r\n\r\n\
+              3\r\nabc\r\n0\r\nX-Checksum: 1\r\nHost: b\r\nIf-Match: *\r\nX-Other: 2\r\n\r\n",
+        )
+        .unwrap();
+
+        assert_eq!(req.body, b"abc");
+        assert_eq!(req.get_header("X-Checksum"), Some("1".to_string()));
+        assert_eq!(req.get_header("Host"), Some("a".to_string()));
+        assert_eq!(req.get_header("If-Match"), None);
+        assert_eq!(req.get_header("X-Other"), None);
+    }
+
+    #[test]
+    fn bad_request_line_is_an_error() {
+        for raw in [
+            &b"GET /\r\n\r\n"[..],
+            b"GET  / HTTP/1.1 x\r\n\r\n",
+            b"\xff\xfe\r\n\r\n",
+            b"GET / HTTP/1.1\r\nHost: a\r\nbad header\r\n\r\n",
+            b"GET / HTTP/1.1\r\nHost: a\r\n\tfolded\r\n\r\n",
+            b"GET / HTTP/1.1\nHost: a\n\n",
+        ] {
+            assert!(
+                matches!(
+                    parse_request(raw),
+                    Err(Error::Status(Status::BadRequest, _))
+                ),
+                "{:?}",
+                raw
+            );
+        }
+    }
+
+    #[test]
+    fn parses_the_protocol_version() {
+        assert_eq!(http_version("HTTP/1.0").unwrap(), "HTTP/1.0");
+        assert_eq!(http_version("HTTP/1.1").unwrap(), "HTTP/1.1");
+        assert_eq!(http_version("HTTP/1.9").unwrap(), "HTTP/1.1");
+        assert!(matches!(
+            http_version("HTTP/2.0"),
+            Err(Error::Status(Status::HttpVersionNotSupported, _))
+        ));
+        for version in ["HTTP/1", "HTTP/1.10", "http/1.1", "HTTP/a.b", ""] {
+            assert!(
+                matches!(
+                    http_version(version),
+                    Err(Error::Status(Status::BadRequest, _))
+                ),
+                "{:?}",
+                version
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_request_target_is_a_bad_request() {
+        for raw in [
+            &b"GET  HTTP/1.1\r\nHost: a\r\n\r\n"[..],
+            b"GET echo/abc HTTP/1.1\r\nHost: a\r\n\r\n",
+            b"GET * HTTP/1.1\r\nHost: a\r\n\r\n",
+            b"GET ftp://a/echo/abc HTTP/1.1\r\nHost: a\r\n\r\n",
+            b"GET http:///echo/abc HTTP/1.1\r\nHost: a\r\n\r\n",
+        ] {
+            let res = parse_request(raw);
+            assert!(
+                matches!(res, Err(Error::Status(Status::BadRequest, _))),
+                "{:?}: {:?}",
+                raw,
+                res
+            );
+        }
+    }
+
+    #[test]
+    fn parses_the_query() {
+        let req = parse_request(
+            b"GET /echo/x?a=1&b=two%20words&b=again+and&c&&bad=%zz HTTP/1.1\r\nHost: a\r\n\r\n",
+        )
+        .unwrap();
+
+        assert_eq!(req.path, "/echo/x");
+        assert_eq!(
+            req.query_string(),
+            "a=1&b=two%20words&b=again+and&c&&bad=%zz"
+        );
+        assert_eq!(
+            req.query_pairs(),
+            vec![
+                ("a".to_string(), "1".to_string()),
+                ("b".to_string(), "two words".to_string()),
+                ("b".to_string(), "again and".to_string()),
+                ("c".to_string(), "".to_string()),
+            ]
+        );
+        assert_eq!(req.query("b"), Some("two words".to_string()));
+        assert_eq!(req.query("c"), Some("".to_string()));
+        assert_eq!(req.query("bad"), None);
+        assert_eq!(req.query("missing"), None);
+
+        let req = parse_request(b"GET /echo/x HTTP/1.1\r\nHost: a\r\n\r\n").unwrap();
+        assert_eq!(req.query_string(), "");
+        assert!(req.query_pairs().is_empty());
+    }
+
+    #[test]
+    fn percent_decodes_the_path() {
+        for (target, path) in [
+            ("/echo/hello%20world", "/echo/hello world"),
+            ("/files/my%20file.txt?download=1", "/files/my file.txt"),
+            ("/echo/a+b", "/echo/a+b"),
+            ("/echo/%C3%A9t%c3%a9", "/echo/été"),
+            ("/echo/a%3Fb?c=d%20e", "/echo/a?b"),
+        ] {
+            let raw = format!("GET {} HTTP/1.1\r\nHost: a\r\n\r\n", target);
+            let req = parse_request(raw.as_bytes()).unwrap();
+            assert_eq!(req.path, path, "{}", target);
+            assert_eq!(req.target, target);
+        }
+
+        for target in [
+            "/echo/%zz",
+            "/echo/%4",
+            "/echo/%ff",
+            "/files/a%00.txt",
+            "/echo/a%+fb",
+            "/echo/a%-1b",
+        ] {
+            let raw = format!("GET {} HTTP/1.1\r\nHost: a\r\n\r\n", target);
+            assert!(
+                matches!(
+                    parse_request(raw.as_bytes()),
+                    Err(Error::Status(Status::BadRequest, _))
+                ),
+                "{}",
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn normalizes_the_path() {
+        for (path, normalized) in [
+            ("/", "/"),
+            ("/echo/abc", "/echo/abc"),
+            ("/echo//a///b", "/echo/a/b"),
+            ("/echo/./a/.", "/echo/a/"),
+            ("/files/../../etc/passwd", "/etc/passwd"),
+            ("/files/a/../b", "/files/b"),
+            ("/files/a/..", "/files/"),
+            ("/..", "/"),
+            ("/files/", "/files/"),
+            ("//", "/"),
+            ("*", "*"),
+        ] {
+            assert_eq!(normalize_path(path), normalized, "{}", path);
+        }
+
+        let req = parse_request(b"GET /files/%2E%2E/%2e%2e/etc/passwd HTTP/1.1\r\nHost: a\r\n\r\n")
+            .unwrap();
+        assert_eq!(req.path, "/etc/passwd");
+    }
+
+    #[test]
+    fn percent_encodes_paths() {
+        assert_eq!(
+            percent_encode_path("/files/my file.txt"),
+            "/files/my%20file.txt"
+        );
+        assert_eq!(
+            percent_encode_path("/echo/été?#"),
+            "/echo/%C3%A9t%C3%A9%3F%23"
+        );
+        assert_eq!(percent_encode_path("/a-b_c.d~e/f+g"), "/a-b_c.d~e/f+g");
+    }
+
+    #[test]
+    fn accepts_every_request_target_form() {
+        for (target, path) in [
+            ("/echo/abc?x=1", "/echo/abc?x=1"),
+            ("http://example.com/echo/abc", "/echo/abc"),
+            ("HTTP://example.com:8080", "/"),
+            ("https://example.com?x=1", "/?x=1"),
+        ] {
+            let raw = format!("GET {} HTTP/1.1\r\nHost: a\r\n\r\n", target);
+            let req = parse_request(raw.as_bytes()).unwrap();
+            assert_eq!(req.target, path, "{}", target);
+        }
+
+        let req = parse_request(b"OPTIONS * HTTP/1.1\r\nHost: a\r\n\r\n").unwrap();
+        assert_eq!(req.path, "*");
+    }
+
+    #[test]
+    fn ambiguous_framing_is_a_bad_request() {
+        for raw in [
+            "POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 3\r\nTransfer-Encoding: chunked\r\n\r\n",
+            "POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 3\r\nContent-Length: 4\r\n\r\n",
+            "POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 3, 4\r\n\r\n",
+            "POST / HTTP/1.1\r\nHost: a\r\nContent-Length: +3\r\n\r\n",
+            "POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 0x3\r\n\r\n",
+            "POST / HTTP/1.1\r\nHost: a\r\nContent-Length: \r\n\r\n",
+        ] {
+            let res = read(raw);
+            assert!(
+                matches!(res, Err(Error::Status(Status::BadRequest, _))),
+                "{:?}: {:?}",
+                raw,
+                res
+            );
+        }
+    }
+
+    #[test]
+    fn repeated_equal_content_lengths_are_accepted() {
+        for raw in [
+            "POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 3\r\nContent-Length: 3\r\n\r\nabc",
+            "POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 3, 03\r\n\r\nabc",
+        ] {
+            let req = read(raw).unwrap().unwrap();
+            assert_eq!(req.body, b"abc", "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn body_over_the_size_limit_is_an_error() {
+        let mut config = Config {
+            max_body_size: Some(3),
+            ..Config::default()
+        };
+
+        for raw in [
+            "POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 3\r\n\r\nabc",
+            "POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n",
+        ] {
+            let req = read_request(&mut raw.as_bytes(), &config).unwrap().unwrap();
+            assert_eq!(req.body, b"abc");
+        }
+
+        config.max_body_size = Some(2);
+        let res = read_request(
+            &mut &b"POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n"[..],
+            &config,
+        );
+        assert!(
+            matches!(res, Err(Error::Status(Status::PayloadTooLarge, _))),
+            "{:?}",
+            res
+        );
+    }
+
+    #[test]
+    fn huge_content_length_is_an_error() {
+        let res = parse_request(
+            b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 99999999999999\r\n\r\nabc",
+        );
+        assert!(matches!(res, Err(Error::Request(_))));
+
+        let res = parse_request(
+            b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 99999999999999999999999\r\n\r\nabc",
+        );
+        assert!(matches!(res, Err(Error::Status(Status::BadRequest, _))));
+    }
+
+    // Small xorshift generator, so the property tests are reproducible
+    // without extra dependencies.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    #[test]
+    fn never_panics_on_random_bytes() {
+        let mut rng = Rng(0x2545f4914f6cdd1d);
+
+        for _ in 0..5000 {
+            let len = rng.below(256);
+            let bytes: Vec<u8> = (0..len).map(|_| rng.next() as u8).collect();
+            let _ = parse_request(&bytes);
+        }
+    }
+
+    #[test]
+    fn never_panics_on_mutated_requests() {
+        let valid: &[u8] =
+            b"POST /files/a HTTP/1.1\r\nHost: localhost\r\nContent-Length: 3\r\n\r\nabc";
+        // Bytes with a special meaning for the parser.
+        let interesting = b" :\r\n0123456789\xff";
+        let mut rng = Rng(0x9e3779b97f4a7c15);
+
+        for _ in 0..5000 {
+            let mut bytes = valid.to_vec();
+
+            for _ in 0..1 + rng.below(4) {
+                let pos = rng.below(bytes.len());
+                match rng.below(3) {
+                    0 => bytes[pos] = interesting[rng.below(interesting.len())],
+                    1 => {
+                        bytes.remove(pos);
+                    }
+                    _ => bytes.insert(pos, interesting[rng.below(interesting.len())]),
+                }
+                if bytes.is_empty() {
+                    break;
+                }
+            }
+
+            let _ = parse_request(&bytes);
+        }
+    }
+
+    #[test]
+    fn empty_lines_before_the_request_line_are_ignored() {
+        let req = read("\r\n\r\nGET /echo/a HTTP/1.1\r\nHost: a\r\n\r\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(req.method, "GET");
+        assert_eq!(req.path, "/echo/a");
+
+        // Only empty lines, then the client closed the connection.
+        assert!(read("\r\n\r\n").unwrap().is_none());
+
+        let config = Config {
+            max_header_size: 8,
+            ..Config::default()
+        };
+        let raw = "\r\n".repeat(5) + "GET / HTTP/1.1\r\nHost: a\r\n\r\n";
+        assert!(matches!(
+            read_request(&mut raw.as_bytes(), &config),
+            Err(Error::Status(Status::RequestHeaderFieldsTooLarge, _))
+        ));
+    }
+
+    #[test]
+    fn nothing_sent_is_not_a_request() {
+        let mut reader = Cursor::new(b"".to_vec());
+        let req = read_request(&mut reader, &Config::default()).unwrap();
+
+        assert!(req.is_none());
+    }
+
+    #[test]
+    fn closed_in_the_middle_of_the_header_is_an_error() {
+        let mut reader = Cursor::new(b"GET / HTTP/1.1\r\nHost: localhost\r\n".to_vec());
+        let res = read_request(&mut reader, &Config::default());
+
+        assert!(matches!(res, Err(Error::Request(_))));
+    }
+}
@@ -0,0 +1,791 @@
+use std::fmt;
+use std::io;
+use std::io::{Read, Write};
+
+use crate::chunked::write_chunked;
+use crate::error::is_disconnect;
+use crate::json::Value;
+use crate::Error;
+
+#[derive(Debug, PartialEq)]
+pub enum Status {
+    // 1xx
+    Continue,   // 100
+    EarlyHints, // 103
+
+    // 2xx
+    OK,             // 200
+    Created,        // 201
+    Accepted,       // 202
+    NoContent,      // 204
+    PartialContent, // 206
+
+    // 3xx
+    MovedPermanently, // 301
+    NotModified,      // 304
+
+    // 4xx
+    BadRequest,                  // 400
+    Unauthorized,                // 401
+    Forbidden,                   // 403
+    NotFound,                    // 404
+    MethodNotAllowed,            // 405
+    NotAcceptable,               // 406
+    RequestTimeout,              // 408
+    PreconditionFailed,          // 412
+    PayloadTooLarge,             // 413
+    UriTooLong,                  // 414
+    RangeNotSatisfiable,         // 416
+    ExpectationFailed,           // 417
+    TooManyRequests,             // 429
+    RequestHeaderFieldsTooLarge, // 431
+
+    // 5xx
+    InternalServerError,     // 500
+    NotImplemented,          // 501
+    HttpVersionNotSupported, // 505
+
+    // Any other status code, with its reason phrase.
+    Custom(u16, String),
+}
+
+// The status-line is made of the protocol version, the status code and its
+// reason phrase, e.g. "HTTP/1.1 404 Not Found".
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7230#section-3.1.2
+impl Status {
+    pub fn code(&self) -> u16 {
+        match self {
+            Status::Continue => 100,
+            Status::EarlyHints => 103,
+            Status::OK => 200,
+            Status::Created => 201,
+            Status::Accepted => 202,
+            Status::NoContent => 204,
+            Status::PartialContent => 206,
+            Status::MovedPermanently => 301,
+            Status::NotModified => 304,
+            Status::BadRequest => 400,
+            Status::Unauthorized => 401,
+            Status::Forbidden => 403,
+            Status::NotFound => 404,
+            Status::MethodNotAllowed => 405,
+            Status::NotAcceptable => 406,
+            Status::RequestTimeout => 408,
+            Status::PreconditionFailed => 412,
+            Status::PayloadTooLarge => 413,
+            Status::UriTooLong => 414,
+            Status::RangeNotSatisfiable => 416,
+            Status::ExpectationFailed => 417,
+            Status::TooManyRequests => 429,
+            Status::RequestHeaderFieldsTooLarge => 431,
+            Status::InternalServerError => 500,
+            Status::NotImplemented => 501,
+            Status::HttpVersionNotSupported => 505,
+            Status::Custom(code, _) => *code,
+        }
+    }
+
+    pub fn reason(&self) -> &str {
+        match self {
+            Status::Continue => "Continue",
+            Status::EarlyHints => "Early Hints",
+            Status::OK => "OK",
+            Status::Created => "Created",
+            Status::Accepted => "Accepted",
+            Status::NoContent => "No Content",
+            Status::PartialContent => "Partial Content",
+            Status::MovedPermanently => "Moved Permanently",
+            Status::NotModified => "Not Modified",
+            Status::BadRequest => "Bad Request",
+            Status::Unauthorized => "Unauthorized",
+            Status::Forbidden => "Forbidden",
+            Status::NotFound => "Not Found",
+            Status::MethodNotAllowed => "Method Not Allowed",
+            Status::NotAcceptable => "Not Acceptable",
+            Status::RequestTimeout => "Request Timeout",
+            Status::PreconditionFailed => "Precondition Failed",
+            Status::PayloadTooLarge => "Payload Too Large",
+            Status::UriTooLong => "URI Too Long",
+            Status::RangeNotSatisfiable => "Range Not Satisfiable",
+            Status::ExpectationFailed => "Expectation Failed",
+            Status::TooManyRequests => "Too Many Requests",
+            Status::RequestHeaderFieldsTooLarge => "Request Header Fields Too Large",
+            Status::InternalServerError => "Internal Server Error",
+            Status::NotImplemented => "Not Implemented",
+            Status::HttpVersionNotSupported => "HTTP Version Not Supported",
+            Status::Custom(_, reason) => reason,
+        }
+    }
+}
+
+pub enum Body {
+    Bytes(Vec<u8>),
+    // Streamed from the reader as it is written, for bodies too big (or too
+    // slow to produce) to be held in memory. With a known length it is sent
+    // with a Content-Length, otherwise with the chunked transfer coding.
+    Reader(Box<dyn Read + Send>, Option<u64>),
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Body::Bytes(bytes) => f.debug_tuple("Bytes").field(bytes).finish(),
+            Body::Reader(_, len) => f.debug_tuple("Reader").field(len).finish(),
+        }
+    }
+}
+
+// Trailer fields sent after a chunked body, see `Response::set_trailers`.
+pub struct Trailers(Box<dyn FnOnce() -> Vec<(String, String)> + Send>);
+
+impl fmt::Debug for Trailers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Trailers")
+    }
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: Status,
+    pub body: Option<Body>,
+    pub content_type: Option<String>,
+    // Extra response headers, written as-is after the status line.
+    pub headers: Vec<(String, String)>,
+    pub trailers: Option<Trailers>,
+}
+
+impl Response {
+    pub fn new(status: Status) -> Response {
+        Response {
+            status,
+            body: None,
+            content_type: None,
+            headers: vec![],
+            trailers: None,
+        }
+    }
+
+    // Response with the value serialized as its JSON body.
+    pub fn json(status: Status, value: &Value) -> Response {
+        Response {
+            status,
+            body: Some(Body::Bytes(value.to_string().into_bytes())),
+            content_type: Some("application/json".to_string()),
+            headers: vec![],
+            trailers: None,
+        }
+    }
+
+    // Declares the trailer fields `names` in a Trailer header, their values
+    // are produced by `trailers` once the body is written, e.g. a checksum of
+    // a streamed body. They are only sent with a chunked body (a Reader of
+    // unknown length answered with HTTP/1.1), otherwise they are dropped as
+    // there is nowhere to put them.
+    //
+    // Reference: https://datatracker.ietf.org/doc/html/rfc7230#section-4.4
+    pub fn set_trailers<F>(&mut self, names: &[&str], trailers: F)
+    where
+        F: FnOnce() -> Vec<(String, String)> + Send + 'static,
+    {
+        self.headers.push(("Trailer".to_string(), names.join(", ")));
+        self.trailers = Some(Trailers(Box::new(trailers)));
+    }
+
+    // Header names are case-insensitive.
+    pub fn get_header(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| key.eq_ignore_ascii_case(k))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+// Every text body this server produces is UTF-8, but text media types
+// default to US-ASCII (or whatever the client guesses) when the charset is
+// missing. Binary types don't take a charset and are left unchanged.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc6657#section-4
+fn with_charset(content_type: &str) -> String {
+    let is_text = content_type.starts_with("text/");
+    let has_charset = content_type.to_ascii_lowercase().contains("charset=");
+
+    if is_text && !has_charset {
+        format!("{}; charset=utf-8", content_type)
+    } else {
+        content_type.to_string()
+    }
+}
+
+// A client closing the connection early is expected (e.g. a cancelled
+// download), so it is reported apart from actual write failures.
+fn write_error(what: &str, e: io::Error) -> Error {
+    if is_disconnect(&e) {
+        Error::Disconnected(format!("client went away while writing {}: {}", what, e))
+    } else {
+        Error::Response(format!("error writing {}: {}", what, e))
+    }
+}
+
+// Writes an informational (1xx) response, e.g. "100 Continue". These are
+// interim responses: the client keeps waiting for the final response, which
+// must be written with `write_response` afterwards on the same connection.
+// They have no body, so just the status-line and headers are sent.
+//
+// HTTP/1.0 doesn't define 1xx responses, so nothing is written for HTTP/1.0
+// clients.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7231#section-6.2
+pub fn write_interim_response<W: Write>(
+    mut stream: W,
+    status: &Status,
+    headers: &[(String, String)],
+    http_version: &str,
+) -> Result<(), Error> {
+    if !(100..200).contains(&status.code()) {
+        return Err(Error::Response(format!(
+            "{} is not an informational status",
+            status.code()
+        )));
+    }
+
+    if http_version == "HTTP/1.0" {
+        return Ok(());
+    }
+
+    write!(
+        &mut stream,
+        "{} {} {}\r\n",
+        http_version,
+        status.code(),
+        status.reason()
+    )
+    .map_err(|e| write_error("interim response status-line", e))?;
+
+    for (key, val) in headers.iter() {
+        write!(&mut stream, "{}: {}\r\n", key, val)
+            .map_err(|e| write_error(&format!("interim response {} header", key), e))?;
+    }
+
+    write!(&mut stream, "\r\n").map_err(|e| write_error("interim response CRLF", e))?;
+
+    stream
+        .flush()
+        .map_err(|e| write_error("connection stream flush", e))
+}
+
+// The status-line echoes the request's HTTP version ("HTTP/1.0" or
+// "HTTP/1.1"), so HTTP/1.0 clients get a response they understand.
+pub fn write_response<W: Write>(
+    stream: W,
+    res: &mut Response,
+    http_version: &str,
+) -> Result<(), Error> {
+    write_message(stream, res, http_version, true)
+}
+
+// The response to a HEAD request: the same status-line and headers as the
+// GET response it stands for, Content-Length included, but no body.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7231#section-4.3.2
+pub fn write_head_response<W: Write>(
+    stream: W,
+    res: &mut Response,
+    http_version: &str,
+) -> Result<(), Error> {
+    write_message(stream, res, http_version, false)
+}
+
+fn write_message<W: Write>(
+    mut stream: W,
+    res: &mut Response,
+    http_version: &str,
+    send_body: bool,
+) -> Result<(), Error> {
+    // A 1xx never ends the exchange, see `write_interim_response`.
+    if (100..200).contains(&res.status.code()) {
+        return Err(Error::Response(format!(
+            "{} can't be a final response",
+            res.status.code()
+        )));
+    }
+
+    // 204 and 304 responses never have a message-body: the client doesn't
+    // read one, so it would be taken for the start of the next response.
+    // Their other headers (e.g. the ETag and Cache-Control a 304 carries
+    // over from the 200 it stands for) are still sent.
+    //
+    // Reference: https://datatracker.ietf.org/doc/html/rfc7230#section-3.3.3
+    // Reference: https://datatracker.ietf.org/doc/html/rfc7232#section-4.1
+    let bodiless = matches!(res.status.code(), 204 | 304);
+    if bodiless {
+        res.body = None;
+    }
+
+    // HTTP/1.0 has no chunked transfer coding, so a body of unknown length
+    // is read up front to send its Content-Length. With no trailer section
+    // either, the trailer fields (known once the body is read) are sent as
+    // header fields instead.
+    if let Some(Body::Reader(reader, None)) = &mut res.body {
+        if http_version == "HTTP/1.0" {
+            let mut bytes = vec![];
+            reader
+                .read_to_end(&mut bytes)
+                .map_err(|e| Error::Response(format!("error reading response body: {}", e)))?;
+            res.body = Some(Body::Bytes(bytes));
+
+            res.headers
+                .retain(|(k, _)| !k.eq_ignore_ascii_case("Trailer"));
+            if let Some(Trailers(trailers)) = res.trailers.take() {
+                res.headers.extend(trailers());
+            }
+        }
+    }
+
+    // Write the response:
+    //
+    // Respond with "HTTP/1.1 200 OK\r\n\r\n" (there are two \r\ns at the end)
+    //
+    // * "HTTP/1.1 200 OK" is the HTTP Status Line.
+    // * "\r\n", also known as CRLF, is the end-of-line marker that HTTP uses.
+    // * The first "\r\n" signifies the end of the status line.
+    // * The second "\r\n" signifies the end of the response headers section (which is empty in this case).
+    //
+    write!(
+        &mut stream,
+        "{} {} {}\r\n",
+        http_version,
+        res.status.code(),
+        res.status.reason()
+    )
+    .map_err(|e| write_error("response general-header", e))?;
+
+    for (key, val) in res.headers.iter() {
+        write!(&mut stream, "{}: {}\r\n", key, val)
+            .map_err(|e| write_error(&format!("response {} header", key), e))?;
+    }
+
+    if let (Some(_), Some(content_type)) = (&res.body, &res.content_type) {
+        write!(
+            &mut stream,
+            "Content-Type: {}\r\n",
+            with_charset(content_type)
+        )
+        .map_err(|e| write_error("response Content-Type header", e))?;
+    }
+
+    // Content-Length is always sent (even when it is 0) because the connection
+    // is reused for further requests, so the client needs to know where
+    // this response ends. The exceptions are 204 and 304, which never have a
+    // body (a Content-Length on a 304 would describe the selected
+    // representation, not this response), and bodies of unknown length, which
+    // are delimited by the chunked coding instead.
+    //
+    // Reference: https://datatracker.ietf.org/doc/html/rfc7230#section-3.3.3
+    if !bodiless {
+        match &res.body {
+            Some(Body::Reader(_, None)) => write!(&mut stream, "Transfer-Encoding: chunked\r\n")
+                .map_err(|e| write_error("response Transfer-Encoding header", e))?,
+            body => {
+                let content_length = match body {
+                    Some(Body::Bytes(bytes)) => bytes.len() as u64,
+                    Some(Body::Reader(_, Some(len))) => *len,
+                    _ => 0,
+                };
+                write!(&mut stream, "Content-Length: {}\r\n", content_length)
+                    .map_err(|e| write_error("response Content-Length header", e))?;
+            }
+        }
+    }
+
+    write!(&mut stream, "\r\n").map_err(|e| write_error("response CRLF", e))?;
+
+    if !send_body {
+        res.body = None;
+    }
+
+    match &mut res.body {
+        // A single write may only take part of the body (e.g. when the
+        // socket buffer is full), write_all keeps writing until all of it
+        // is sent, retrying interrupted writes.
+        Some(Body::Bytes(bytes)) => {
+            stream
+                .write_all(bytes)
+                .map_err(|e| write_error("message-body", e))?;
+        }
+        Some(Body::Reader(reader, Some(len))) => {
+            let copied = io::copy(&mut reader.take(*len), &mut stream)
+                .map_err(|e| write_error("message-body", e))?;
+            // The client would wait forever for the missing bytes.
+            if copied != *len {
+                return Err(Error::Response(format!(
+                    "response body ended after {} of {} bytes",
+                    copied, len
+                )));
+            }
+        }
+        Some(Body::Reader(reader, None)) => {
+            let trailers = res.trailers.take();
+            write_chunked(reader, &mut stream, || match trailers {
+                Some(Trailers(trailers)) => trailers(),
+                None => vec![],
+            })
+            .map_err(|e| write_error("message-body", e))?;
+        }
+        None => {}
+    }
+
+    // Flush connection stream.
+    stream
+        .flush()
+        .map_err(|e| write_error("connection stream flush", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn written(res: &mut Response) -> String {
+        let mut out: Vec<u8> = vec![];
+        write_response(&mut out, res, "HTTP/1.1").unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn standard_status_line() {
+        let status = Status::NotFound;
+        assert_eq!(status.code(), 404);
+        assert_eq!(status.reason(), "Not Found");
+
+        let res = written(&mut Response::new(status));
+        assert!(res.starts_with("HTTP/1.1 404 Not Found\r\n"), "{}", res);
+    }
+
+    #[test]
+    fn http_1_0_status_line() {
+        let mut out: Vec<u8> = vec![];
+        write_response(&mut out, &mut Response::new(Status::OK), "HTTP/1.0").unwrap();
+
+        assert!(out.starts_with(b"HTTP/1.0 200 OK\r\n"));
+    }
+
+    #[test]
+    fn head_response_has_headers_but_no_body() {
+        let head = |res: &mut Response| {
+            let mut out: Vec<u8> = vec![];
+            write_head_response(&mut out, res, "HTTP/1.1").unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let mut res = Response::new(Status::OK);
+        res.body = Some(Body::Bytes(b"hello".to_vec()));
+        res.content_type = Some("text/plain".to_string());
+        assert_eq!(
+            head(&mut res),
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: 5\r\n\r\n"
+        );
+
+        assert_eq!(
+            head(&mut reader_response(b"hello", Some(5))),
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n"
+        );
+        assert_eq!(
+            head(&mut reader_response(b"hello", None)),
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n"
+        );
+    }
+
+    // Writer whose peer has already closed the connection.
+    struct ClosedStream(io::ErrorKind);
+
+    impl Write for ClosedStream {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::from(self.0))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn closed_connection_is_a_disconnect() {
+        for kind in [io::ErrorKind::BrokenPipe, io::ErrorKind::ConnectionReset] {
+            let res = write_response(
+                ClosedStream(kind),
+                &mut Response::new(Status::OK),
+                "HTTP/1.1",
+            );
+            assert!(matches!(res, Err(Error::Disconnected(_))), "{:?}", res);
+        }
+
+        let res = write_response(
+            ClosedStream(io::ErrorKind::PermissionDenied),
+            &mut Response::new(Status::OK),
+            "HTTP/1.1",
+        );
+        assert!(matches!(res, Err(Error::Response(_))), "{:?}", res);
+    }
+
+    // Writer whose peer closes the connection as `at` is being written.
+    struct ClosingStream {
+        written: Vec<u8>,
+        at: &'static [u8],
+    }
+
+    impl Write for ClosingStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let mut written = self.written.clone();
+            written.extend_from_slice(buf);
+            if written.windows(self.at.len()).any(|w| w == self.at) {
+                return Err(io::Error::from(io::ErrorKind::BrokenPipe));
+            }
+            self.written = written;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn closed_connection_while_writing_headers_is_a_disconnect() {
+        for at in [&b"Content-Type"[..], b"Content-Length", b"hello"] {
+            let mut res = Response::new(Status::OK);
+            res.body = Some(Body::Bytes(b"hello".to_vec()));
+            res.content_type = Some("text/plain".to_string());
+
+            let stream = ClosingStream {
+                written: vec![],
+                at,
+            };
+            let res = write_response(stream, &mut res, "HTTP/1.1");
+            assert!(
+                matches!(res, Err(Error::Disconnected(_))),
+                "{:?}: {:?}",
+                String::from_utf8_lossy(at),
+                res
+            );
+        }
+    }
+
+    #[test]
+    fn interim_response_before_final_response() {
+        let mut out: Vec<u8> = vec![];
+        write_interim_response(&mut out, &Status::Continue, &[], "HTTP/1.1").unwrap();
+        write_response(&mut out, &mut Response::new(Status::OK), "HTTP/1.1").unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn interim_and_final_responses_are_not_interchangeable() {
+        let mut out: Vec<u8> = vec![];
+
+        assert!(write_interim_response(&mut out, &Status::OK, &[], "HTTP/1.1").is_err());
+        assert!(
+            write_response(&mut out, &mut Response::new(Status::Continue), "HTTP/1.1").is_err()
+        );
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn no_interim_response_for_http_1_0() {
+        let mut out: Vec<u8> = vec![];
+        write_interim_response(&mut out, &Status::Continue, &[], "HTTP/1.0").unwrap();
+
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn custom_status_line() {
+        let status = Status::Custom(418, "I'm a teapot".to_string());
+        assert_eq!(status.code(), 418);
+        assert_eq!(status.reason(), "I'm a teapot");
+
+        let res = written(&mut Response::new(status));
+        assert!(res.starts_with("HTTP/1.1 418 I'm a teapot\r\n"), "{}", res);
+    }
+
+    // Writer which takes at most a few bytes per call, and is interrupted
+    // every other call, like a slow client's socket.
+    struct TrickleStream {
+        written: Vec<u8>,
+        calls: usize,
+    }
+
+    impl Write for TrickleStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.calls += 1;
+            if self.calls.is_multiple_of(2) {
+                return Err(io::Error::from(io::ErrorKind::Interrupted));
+            }
+
+            let n = buf.len().min(3);
+            self.written.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn short_writes_send_the_whole_body() {
+        let body: Vec<u8> = (0..1000u32).map(|i| (i % 256) as u8).collect();
+        let mut res = Response::new(Status::OK);
+        res.body = Some(Body::Bytes(body.clone()));
+
+        let mut stream = TrickleStream {
+            written: vec![],
+            calls: 0,
+        };
+        write_response(&mut stream, &mut res, "HTTP/1.1").unwrap();
+
+        assert!(stream
+            .written
+            .starts_with(b"HTTP/1.1 200 OK\r\nContent-Length: 1000\r\n\r\n"));
+        assert!(stream.written.ends_with(&body));
+    }
+
+    fn reader_response(data: &'static [u8], len: Option<u64>) -> Response {
+        let mut res = Response::new(Status::OK);
+        res.body = Some(Body::Reader(Box::new(data), len));
+        res
+    }
+
+    #[test]
+    fn reader_body_of_known_length() {
+        let res = written(&mut reader_response(b"hello", Some(5)));
+
+        assert_eq!(res, "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello");
+    }
+
+    #[test]
+    fn reader_body_of_unknown_length_is_chunked() {
+        let res = written(&mut reader_response(b"hello", None));
+
+        assert_eq!(
+            res,
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn trailers_after_the_last_chunk() {
+        let body: Arc<Mutex<Vec<u8>>> = Arc::default();
+        let read = Arc::clone(&body);
+        let reader = Tee(&b"hello"[..], body);
+
+        let mut res = Response::new(Status::OK);
+        res.body = Some(Body::Reader(Box::new(reader), None));
+        res.set_trailers(&["X-Length"], move || {
+            let len = read.lock().unwrap().len();
+            vec![("X-Length".to_string(), len.to_string())]
+        });
+
+        assert_eq!(
+            written(&mut res),
+            "HTTP/1.1 200 OK\r\nTrailer: X-Length\r\nTransfer-Encoding: chunked\r\n\r\n\
+             5\r\nhello\r\n0\r\nX-Length: 5\r\n\r\n"
+        );
+    }
+
+    // Keeps a copy of everything read.
+    struct Tee(&'static [u8], Arc<Mutex<Vec<u8>>>);
+
+    impl Read for Tee {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.0.read(buf)?;
+            self.1.lock().unwrap().extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn reader_body_of_unknown_length_for_http_1_0() {
+        let mut out: Vec<u8> = vec![];
+        write_response(&mut out, &mut reader_response(b"hello", None), "HTTP/1.0").unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "HTTP/1.0 200 OK\r\nContent-Length: 5\r\n\r\nhello"
+        );
+    }
+
+    #[test]
+    fn trailers_become_headers_for_http_1_0() {
+        let mut res = reader_response(b"hello", None);
+        res.set_trailers(&["X-Length"], || {
+            vec![("X-Length".to_string(), "5".to_string())]
+        });
+
+        let mut out: Vec<u8> = vec![];
+        write_response(&mut out, &mut res, "HTTP/1.0").unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "HTTP/1.0 200 OK\r\nX-Length: 5\r\nContent-Length: 5\r\n\r\nhello"
+        );
+    }
+
+    #[test]
+    fn reader_body_shorter_than_its_length() {
+        let mut out: Vec<u8> = vec![];
+        let res = write_response(&mut out, &mut reader_response(b"hi", Some(5)), "HTTP/1.1");
+
+        assert!(matches!(res, Err(Error::Response(_))), "{:?}", res);
+    }
+
+    #[test]
+    fn not_modified_has_no_body() {
+        let mut res = Response::new(Status::NotModified);
+        res.headers
+            .push(("ETag".to_string(), "\"abc\"".to_string()));
+        res.headers.push((
+            "Cache-Control".to_string(),
+            "public, max-age=60".to_string(),
+        ));
+        res.body = Some(Body::Bytes(b"stale".to_vec()));
+        res.content_type = Some("text/plain".to_string());
+
+        assert_eq!(
+            written(&mut res),
+            "HTTP/1.1 304 Not Modified\r\nETag: \"abc\"\r\nCache-Control: public, max-age=60\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn no_content_has_no_body() {
+        let mut res = reader_response(b"hello", None);
+        res.status = Status::NoContent;
+
+        assert_eq!(written(&mut res), "HTTP/1.1 204 No Content\r\n\r\n");
+    }
+
+    #[test]
+    fn json_response() {
+        let value = Value::Object(vec![
+            ("name".to_string(), Value::from("a \"quoted\" name")),
+            ("size".to_string(), Value::from(42u64)),
+            (
+                "tags".to_string(),
+                Value::Array(vec![Value::from(true), Value::Null]),
+            ),
+        ]);
+        let mut res = Response::json(Status::OK, &value);
+
+        assert_eq!(res.content_type.as_deref(), Some("application/json"));
+        let out = written(&mut res);
+        let (_, body) = out.split_once("\r\n\r\n").unwrap();
+        assert_eq!(crate::json::parse(body), Ok(value));
+    }
+}
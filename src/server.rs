@@ -0,0 +1,1183 @@
+use std::any::Any;
+use std::fs;
+use std::io;
+use std::io::{BufRead, BufReader, Read};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::accesslog::AccessLog;
+use crate::cors;
+use crate::encoding;
+use crate::handlers::*;
+use crate::ratelimit::RateLimiter;
+use crate::request::{body_reader, percent_encode_path, read_body, read_request_head, MessageBody};
+use crate::response::{write_head_response, write_interim_response, write_response};
+use crate::stats::StatsCache;
+use crate::{Body, Config, Error, Request, Response, Status, TrailingSlash};
+
+// How long a shutdown waits for the in-flight requests to complete.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Server {
+    listener: TcpListener,
+    state: Arc<State>,
+    shutdown: Arc<AtomicBool>,
+}
+
+// State shared by every connection: the immutable config plus whatever
+// the handlers need to keep between requests.
+pub(crate) struct State {
+    pub config: Config,
+    pub stats: StatsCache,
+    // Contents of the configured error pages, loaded once at startup.
+    pub error_pages: Vec<(u16, Vec<u8>)>,
+    pub rate_limiter: Option<RateLimiter>,
+    // Lets handlers stop the server (see POST /admin/shutdown).
+    pub shutdown: ShutdownHandle,
+    // Requests being handled right now, which a shutdown waits for.
+    pub in_flight: AtomicUsize,
+    pub access_log: Option<AccessLog>,
+}
+
+// Counts a request as in flight for as long as it lives, so the count is
+// right even when handling the request fails or panics.
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl<'a> InFlight<'a> {
+    fn new(count: &'a AtomicUsize) -> InFlight<'a> {
+        count.fetch_add(1, Ordering::SeqCst);
+        InFlight(count)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Handle used to stop a running server from another thread.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    addr: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl ShutdownHandle {
+    pub fn shutdown(&self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+
+        // The accept loop is blocked waiting for a new connection, so it
+        // only notices the flag once something connects. Wake it up with a
+        // throwaway connection.
+        let _ = TcpStream::connect(self.addr);
+    }
+
+    fn is_shutting_down(&self) -> bool {
+        self.shutdown.load(Ordering::SeqCst)
+    }
+}
+
+impl Server {
+    // Binding to port 0 lets the OS pick any free port, which can then be
+    // read back through `local_addr`.
+    //
+    // NOTE: The listen backlog is the std default (128), std has no way to
+    // configure it.
+    pub fn bind<A: ToSocketAddrs>(addr: A, config: Config) -> io::Result<Server> {
+        let error_pages = load_error_pages(&config)?;
+        let access_log = match &config.access_log {
+            Some(path) => Some(AccessLog::open(path).map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("error opening access log {:?}: {}", path, e),
+                )
+            })?),
+            None => None,
+        };
+        let listener = TcpListener::bind(addr)?;
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let shutdown_handle = ShutdownHandle {
+            addr: listener.local_addr()?,
+            shutdown: Arc::clone(&shutdown),
+        };
+
+        Ok(Server {
+            listener,
+            // Creates an ARC (Atomically Reference Counted) to share this
+            // value across multiple threads.
+            state: Arc::new(State {
+                rate_limiter: config.rate_limit.map(RateLimiter::new),
+                config,
+                stats: StatsCache::new(),
+                error_pages,
+                shutdown: shutdown_handle,
+                in_flight: AtomicUsize::new(0),
+                access_log,
+            }),
+            shutdown,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn shutdown_handle(&self) -> io::Result<ShutdownHandle> {
+        Ok(self.state.shutdown.clone())
+    }
+
+    // Runs the accept loop until a shutdown is requested, then waits (up to
+    // DRAIN_TIMEOUT) for the requests in flight to complete.
+    pub fn run(self) {
+        // Consecutive accept errors, to back off while they last.
+        let mut failures = 0;
+
+        for stream in self.listener.incoming() {
+            if self.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+
+            match stream {
+                Ok(stream) => {
+                    failures = 0;
+
+                    // Here there is no value specification as it is a pointer to a
+                    // reference in the memory heap.
+                    // This creates another pointer to the same allocation, increasing the
+                    // strong reference count.
+                    let state = Arc::clone(&self.state);
+
+                    // Handle connection in a thread so this server
+                    // can handle multiple concurrent connections.
+                    thread::spawn(move || {
+                        // The client may already be gone (e.g. a reset right
+                        // after the connection was accepted), there is nothing
+                        // left to serve then.
+                        let peer = match stream.peer_addr() {
+                            Ok(peer) => peer,
+                            Err(e) => {
+                                eprintln!("Error: reading peer address: {}", e);
+                                return;
+                            }
+                        };
+                        info!(state.config, "Accepted new connection ({})", peer);
+                        if let Err(e) = configure_stream(&stream, &state.config) {
+                            eprintln!("Error: {}", e);
+                        }
+
+                        // Number of requests served on this connection, to see
+                        // whether clients actually reuse their connections.
+                        let mut served = 0;
+                        // A panic only takes down the thread of its own
+                        // connection, the next connections get fresh threads.
+                        // It is caught to report which connection it killed.
+                        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                            handle_connection(stream, Arc::clone(&state), &mut served)
+                        }));
+                        match result {
+                            Ok(Ok(())) => {}
+                            Ok(Err(Error::Disconnected(reason))) => {
+                                info!(state.config, "{}", reason)
+                            }
+                            // TODO: Should we shutdown the connection on errors?
+                            Ok(Err(err)) => eprintln!("Error: {:?}", err),
+                            Err(panic) => eprintln!(
+                                "Error: connection handler panicked ({}): {}",
+                                peer,
+                                panic_message(&panic)
+                            ),
+                        }
+                        info!(state.config, "Connection closed ({} reqs={})", peer, served);
+                    });
+                }
+                Err(e) if is_fatal_accept_error(&e) => {
+                    eprintln!("Error: accepting connections: {}", e);
+                    break;
+                }
+                // Most errors go away by themselves (a connection reset before
+                // it was accepted) or after a while (running out of file
+                // descriptors). Retrying right away would spin and flood the
+                // log in the meantime.
+                Err(e) => {
+                    failures += 1;
+                    let wait = accept_backoff(failures);
+                    eprintln!(
+                        "Error: accepting connection: {} (retrying in {:?})",
+                        e, wait
+                    );
+                    thread::sleep(wait);
+                }
+            }
+        }
+
+        self.drain(DRAIN_TIMEOUT);
+    }
+
+    // Connections are left running on their own threads, which end with the
+    // process, so any request still in flight would be cut off.
+    fn drain(&self, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let in_flight = self.state.in_flight.load(Ordering::SeqCst);
+            if in_flight == 0 {
+                return;
+            }
+            if Instant::now() >= deadline {
+                info!(
+                    self.state.config,
+                    "Shutting down with {} requests in flight", in_flight
+                );
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+// The message given to panic!, when there is one.
+fn panic_message(panic: &Box<dyn Any + Send>) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+// Errors which mean the listener itself is unusable, e.g. it was closed, so
+// no connection will ever be accepted again.
+fn is_fatal_accept_error(e: &io::Error) -> bool {
+    // EBADF, the same on every Unix.
+    const BAD_FILE_DESCRIPTOR: i32 = 9;
+
+    e.kind() == io::ErrorKind::InvalidInput
+        || (cfg!(unix) && e.raw_os_error() == Some(BAD_FILE_DESCRIPTOR))
+}
+
+// How long to wait before accepting again after `failures` consecutive
+// errors: doubling from 5ms, up to 1s.
+fn accept_backoff(failures: u32) -> Duration {
+    let max = Duration::from_secs(1);
+    let exponent = failures.saturating_sub(1).min(10);
+
+    (Duration::from_millis(5) * 2u32.pow(exponent)).min(max)
+}
+
+// Why the declared message-body of a request won't be accepted, if so. The
+// size of a chunked body isn't known in advance, it is checked while being
+// read instead, see `body_reader`.
+fn unacceptable_body(req: &Request, config: &Config) -> Option<String> {
+    let max = config.max_body_size?;
+    let len = req.content_length() as u64;
+
+    (len > max).then(|| format!("message-body of {} bytes is over {} bytes", len, max))
+}
+
+// Address of the client which sent the request. Behind a trusted proxy the
+// peer is the proxy itself, so the original client is taken from the first
+// X-Forwarded-For entry instead, e.g. "X-Forwarded-For: 203.0.113.7, 10.0.0.1".
+//
+// The header is ignored unless the proxy is trusted, since any client could
+// send it to impersonate another address.
+fn client_ip(req: &Request, peer: IpAddr, config: &Config) -> IpAddr {
+    if !config.trust_proxy {
+        return peer;
+    }
+
+    req.get_header("X-Forwarded-For")
+        .and_then(|forwarded| forwarded.split(',').next()?.trim().parse().ok())
+        .unwrap_or(peer)
+}
+
+fn configure_stream(stream: &TcpStream, config: &Config) -> io::Result<()> {
+    stream.set_nodelay(config.tcp_nodelay)
+}
+
+fn load_error_pages(config: &Config) -> io::Result<Vec<(u16, Vec<u8>)>> {
+    let mut pages = vec![];
+
+    for (status, path) in config.error_pages.iter() {
+        let body = fs::read(path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("error reading {} error page {:?}: {}", status, path, e),
+            )
+        })?;
+        pages.push((*status, body));
+    }
+
+    Ok(pages)
+}
+
+// Replaces the body of an error response with the configured error page
+// for its status, if any.
+fn apply_error_page(res: &mut Response, state: &State) {
+    let code = res.status.code();
+
+    if let Some((_, body)) = state.error_pages.iter().find(|(s, _)| *s == code) {
+        res.body = Some(Body::Bytes(body.clone()));
+        res.content_type = Some("text/html".to_string());
+    }
+}
+
+// Sends a 103 Early Hints response with the Link headers configured for the
+// requested path, so browsers can start preloading those resources while
+// the final response is being prepared.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc8297
+fn send_early_hints(stream: &TcpStream, req: &Request, config: &Config) -> Result<(), Error> {
+    if req.method != "GET" || config.early_hints.is_empty() {
+        return Ok(());
+    }
+
+    // Hints are configured for the paths routes see. A request answered
+    // before routing (a redirect, or outside of the base path) gets none.
+    let mut routed = req.clone();
+    if normalize_route_path(&mut routed, config).is_some() {
+        return Ok(());
+    }
+
+    let links: Vec<(String, String)> = config
+        .early_hints
+        .iter()
+        .filter(|(path, _)| *path == routed.path)
+        .map(|(_, link)| ("Link".to_string(), link.clone()))
+        .collect();
+
+    if links.is_empty() {
+        return Ok(());
+    }
+
+    write_interim_response(stream, &Status::EarlyHints, &links, req.response_version())
+}
+
+// Connection stream whose reads fail with `TimedOut` once the deadline (if
+// any) has passed, however the bytes trickle in.
+struct DeadlineStream<'a> {
+    stream: &'a TcpStream,
+    deadline: Option<Instant>,
+    // Whether the socket currently has a read timeout, to avoid resetting
+    // it on every read.
+    has_timeout: bool,
+}
+
+impl Read for DeadlineStream<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let timeout = match self.deadline {
+            Some(deadline) => {
+                let left = deadline.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    return Err(io::Error::new(io::ErrorKind::TimedOut, "deadline exceeded"));
+                }
+                Some(left)
+            }
+            None => None,
+        };
+
+        if timeout.is_some() || self.has_timeout {
+            self.stream.set_read_timeout(timeout)?;
+            self.has_timeout = timeout.is_some();
+        }
+        (&*self.stream).read(buf)
+    }
+}
+
+// Answers a request which can't be processed any further, e.g. because its
+// framing is invalid. The rest of the stream can't be trusted after that,
+// so the caller must drop the connection.
+fn reject(
+    stream: &TcpStream,
+    state: &State,
+    status: Status,
+    reason: &str,
+    http_version: &str,
+) -> Result<(), Error> {
+    info!(state.config, "Rejecting request: {}", reason);
+
+    let mut res = Response::new(status);
+    res.headers
+        .push(("Connection".to_string(), "close".to_string()));
+    apply_error_page(&mut res, state);
+    apply_configured_headers(&mut res, &state.config);
+    apply_cache_control(&mut res, &state.config, false);
+
+    write_response(stream, &mut res, http_version)?;
+    let _ = stream.shutdown(Shutdown::Both);
+    Ok(())
+}
+
+// Adds the headers configured with --header to a response, unless the
+// handler already set a header with the same name.
+fn apply_configured_headers(res: &mut Response, config: &Config) {
+    for (key, value) in config.headers.iter() {
+        if res.get_header(key).is_none() {
+            res.headers.push((key.clone(), value.clone()));
+        }
+    }
+}
+
+// Files can be cached (for as long as configured), while every other
+// response is generated for the request at hand, or is an error, so it must
+// not be stored. A Cache-Control set by the handler or the configured
+// headers is left alone.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7234#section-5.2.2
+fn apply_cache_control(res: &mut Response, config: &Config, file: bool) {
+    if res.get_header("Cache-Control").is_some() {
+        return;
+    }
+
+    let value = if file {
+        format!("public, max-age={}", config.file_cache_max_age)
+    } else {
+        "no-store".to_string()
+    };
+    res.headers.push(("Cache-Control".to_string(), value));
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    state: Arc<State>,
+    served: &mut usize,
+) -> Result<(), Error> {
+    // NOTE: We must read the data before writing any response,
+    // otherwise the stream will automatically close the connection
+    // and return "Recv failure: Connection reset by peer" to the client.
+
+    // The same buffered reader is kept for the whole connection, since it may
+    // already hold bytes of the next request when a client pipelines
+    // several requests in a single write.
+    let mut reader = BufReader::new(DeadlineStream {
+        stream: &stream,
+        deadline: None,
+        has_timeout: false,
+    });
+    let peer = stream
+        .peer_addr()
+        .map_err(|e| Error::Request(format!("error reading peer address: {}", e)))?;
+
+    loop {
+        // The time budget for the request head starts with its first byte,
+        // an idle keep-alive connection is not a slow request.
+        if let Some(timeout) = state.config.request_timeout {
+            match reader.fill_buf() {
+                Ok([]) => break,
+                Ok(_) => reader.get_mut().deadline = Some(Instant::now() + timeout),
+                // Reported by read_request_head on its own read.
+                Err(_) => {}
+            }
+        }
+
+        let head = read_request_head(&mut reader, &state.config);
+        reader.get_mut().deadline = None;
+
+        let mut req = match head {
+            Ok(Some(req)) => req,
+            // Nothing else has been sent and the client closed its side of the
+            // connection, so there is no further request to handle.
+            Ok(None) => break,
+            Err(Error::Status(status, reason)) => {
+                // The request-line may not have been parsed, so answer with
+                // the version this server implements.
+                reject(&stream, &state, status, &reason, "HTTP/1.1")?;
+                break;
+            }
+            Err(err) => return Err(err),
+        };
+        let _in_flight = InFlight::new(&state.in_flight);
+
+        let client = client_ip(&req, peer.ip(), &state.config);
+        // As received, routing may rewrite the path.
+        let request_line = format!("{} {} {}", req.method, req.target, req.http_info);
+        let received_path = req.target.clone();
+
+        // A client over its rate limit is told when to come back, and the
+        // connection is closed so it can't keep pipelining requests.
+        if let Some(limiter) = &state.rate_limiter {
+            if let Err(wait) = limiter.check(client) {
+                info!(state.config, "Rate limiting {}", client);
+                let mut res = Response::new(Status::TooManyRequests);
+                // Retry-After is in whole seconds, round up so retrying right
+                // on time doesn't get rejected again.
+                let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+                res.headers
+                    .push(("Retry-After".to_string(), seconds.to_string()));
+                res.headers
+                    .push(("Connection".to_string(), "close".to_string()));
+                apply_error_page(&mut res, &state);
+                apply_configured_headers(&mut res, &state.config);
+                apply_cache_control(&mut res, &state.config, false);
+                write_response(&stream, &mut res, req.response_version())?;
+                let _ = stream.shutdown(Shutdown::Both);
+                break;
+            }
+        }
+
+        send_early_hints(&stream, &req, &state.config)?;
+
+        // A client sending "Expect: 100-continue" waits for the go-ahead
+        // before sending the message-body. Any other expectation can't be
+        // met, and HTTP/1.0 clients don't know about interim responses, so
+        // theirs is ignored.
+        //
+        // Reference: https://datatracker.ietf.org/doc/html/rfc7231#section-5.1.1
+        let expect = req.get_header("Expect");
+        if let Some(expect) = &expect {
+            if !expect.trim().eq_ignore_ascii_case("100-continue") {
+                reject(
+                    &stream,
+                    &state,
+                    Status::ExpectationFailed,
+                    &format!("unsupported expectation {:?}", expect),
+                    req.response_version(),
+                )?;
+                break;
+            }
+        }
+
+        // A declared body over the size limit is refused before it is read
+        // (or even sent, with 100-continue). The connection is closed rather
+        // than reading the body only to discard it.
+        if let Some(reason) = unacceptable_body(&req, &state.config) {
+            reject(
+                &stream,
+                &state,
+                Status::PayloadTooLarge,
+                &reason,
+                req.response_version(),
+            )?;
+            break;
+        }
+
+        if expect.is_some() && req.response_version() == "HTTP/1.1" && req.has_body() {
+            write_interim_response(&stream, &Status::Continue, &[], req.response_version())?;
+        }
+
+        // The message-body is left in the reader, bounded to its end, so
+        // handlers can stream it instead of buffering it.
+        let mut body = match body_reader(&mut reader, &req, state.config.max_body_size) {
+            Ok(body) => body,
+            Err(Error::Status(status, reason)) => {
+                reject(&stream, &state, status, &reason, req.response_version())?;
+                break;
+            }
+            Err(err) => return Err(err),
+        };
+        let mut res = match route(&mut req, &mut body, &state) {
+            Ok(res) => res,
+            Err(Error::Status(status, reason)) => {
+                reject(&stream, &state, status, &reason, req.response_version())?;
+                break;
+            }
+            Err(err) => return Err(err),
+        };
+
+        // Discard whatever the handler didn't read, so the next request on
+        // this connection starts at the right place. If that fails, the
+        // position of the next request is unknown: answer this one and close.
+        // A body the handler already failed to read isn't read any further,
+        // the client could be left waiting for the response meanwhile.
+        let in_sync = !body.failed()
+            && match io::copy(&mut body, &mut io::sink()) {
+                Ok(_) => true,
+                Err(e) => {
+                    eprintln!("Error: discarding message-body: {}", e);
+                    false
+                }
+            };
+        drop(body);
+
+        encoding::compress_response(&req, &mut res, &state.config);
+        cors::apply_headers(&req, &mut res, &state.config.cors);
+        apply_error_page(&mut res, &state);
+        apply_configured_headers(&mut res, &state.config);
+        // A 304 stands in for the file it validates, so it refreshes the
+        // same caching policy.
+        let code = res.status.code();
+        let file = (req.method == "GET" || req.method == "HEAD")
+            && (req.path == "/files" || req.path.starts_with("/files/"))
+            && ((200..300).contains(&code) || code == 304);
+        apply_cache_control(&mut res, &state.config, file);
+        if state.config.echo_request {
+            res.headers
+                .push(("X-Echo-Method".to_string(), req.method.clone()));
+            res.headers.push(("X-Echo-Path".to_string(), received_path));
+        }
+
+        // The last request allowed on this connection, the client has to
+        // open a new one for the next.
+        let exhausted = state
+            .config
+            .keepalive_max_requests
+            .is_some_and(|max| *served + 1 >= max);
+
+        // While shutting down, keep-alive connections are closed after their
+        // current request instead of waiting for the next one.
+        let close = req.wants_close() || !in_sync || exhausted || state.shutdown.is_shutting_down();
+        if close {
+            res.headers
+                .push(("Connection".to_string(), "close".to_string()));
+        } else if req.response_version() == "HTTP/1.0" {
+            // HTTP/1.0 clients assume the connection is closed unless told
+            // otherwise. The body always has a Content-Length there, see
+            // `write_response`.
+            res.headers
+                .push(("Connection".to_string(), "keep-alive".to_string()));
+        }
+
+        let body_len = match &res.body {
+            _ if req.method == "HEAD" => Some(0),
+            Some(Body::Bytes(bytes)) => Some(bytes.len() as u64),
+            Some(Body::Reader(_, len)) => *len,
+            None => Some(0),
+        };
+
+        if req.method == "HEAD" {
+            write_head_response(&stream, &mut res, req.response_version())?;
+        } else {
+            write_response(&stream, &mut res, req.response_version())?;
+        }
+        *served += 1;
+
+        if let Some(access_log) = &state.access_log {
+            access_log.log(client, &request_line, res.status.code(), body_len);
+        }
+
+        info!(
+            state.config,
+            "Request completed ({} {} {} {})",
+            client,
+            req.method,
+            req.path,
+            res.status.code()
+        );
+
+        if close {
+            stream
+                .shutdown(Shutdown::Both)
+                .map_err(|e| Error::Response(format!("error closing connection: {}", e)))?;
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn route(req: &mut Request, body: &mut MessageBody, state: &State) -> Result<Response, Error> {
+    let config = &state.config;
+
+    if let Some(res) = normalize_route_path(req, config) {
+        return Ok(res);
+    }
+
+    if req.method == "OPTIONS" {
+        let allowed = allowed_methods(&req.path, config);
+        if let Some(res) = cors::handle_preflight(req, &config.cors, allowed) {
+            return Ok(res);
+        }
+        return Ok(handle_options(req, config));
+    }
+
+    // Uploads are streamed straight from the connection into the file, every
+    // other route gets the message-body buffered in `req.body`.
+    let streams_body =
+        (req.method == "POST" || req.method == "PUT") && req.path.starts_with("/files/");
+    if !streams_body {
+        read_body(body, req)?;
+    }
+
+    // HEAD is served by the GET routes, the body is left out when writing
+    // the response.
+    let method = match req.method.as_str() {
+        "HEAD" => "GET",
+        method => method,
+    };
+
+    let res = match ROUTES
+        .iter()
+        .find(|route| route.method == method && route.serves(&req.path, config))
+    {
+        Some(route) => (route.handler)(req, body, state)?,
+        // The path is served, just not with this method.
+        //
+        // Reference: https://datatracker.ietf.org/doc/html/rfc7231#section-6.5.5
+        None => match allowed_methods(&req.path, config) {
+            Some(allow) => {
+                let mut res = Response::new(Status::MethodNotAllowed);
+                res.headers.push(("Allow".to_string(), allow));
+                res
+            }
+            None if config.dev => not_found_with_suggestions(&req.path, config),
+            None => Response::new(Status::NotFound),
+        },
+    };
+
+    Ok(res)
+}
+
+type Handler = fn(&Request, &mut dyn Read, &State) -> Result<Response, Error>;
+
+// A route of the routing table: requests with the method, for a path it
+// matches, are answered by the handler.
+struct Route {
+    method: &'static str,
+    matches: fn(&str) -> bool,
+    // Whether the config turns the route on at all.
+    enabled: fn(&Config) -> bool,
+    handler: Handler,
+    // Whether it changes the files served, which read-only mode forbids.
+    writes: bool,
+}
+
+impl Route {
+    fn serves(&self, path: &str, config: &Config) -> bool {
+        (self.enabled)(config) && (self.matches)(path)
+    }
+
+    // Whether the route is worth advertising (e.g. in Allow): it is turned
+    // on, and its requests aren't all refused by read-only mode.
+    fn allowed(&self, config: &Config) -> bool {
+        (self.enabled)(config) && !(self.writes && config.read_only)
+    }
+}
+
+const ROUTES: &[Route] = &[
+    Route {
+        method: "GET",
+        matches: |path| path == "/",
+        enabled: |_| true,
+        handler: |req, _, _| handle_get_root(req),
+        writes: false,
+    },
+    Route {
+        method: "GET",
+        matches: |path| path.starts_with("/echo/"),
+        enabled: |_| true,
+        handler: |req, _, _| handle_get_echo(req),
+        writes: false,
+    },
+    Route {
+        method: "GET",
+        matches: |path| path == "/user-agent",
+        enabled: |_| true,
+        handler: |req, _, _| handle_get_user_agent(req),
+        writes: false,
+    },
+    Route {
+        method: "GET",
+        matches: |path| path == "/version",
+        enabled: |_| true,
+        handler: |_, _, _| handle_get_version(),
+        writes: false,
+    },
+    Route {
+        method: "GET",
+        matches: |path| path == "/stats",
+        enabled: |config| config.stats,
+        handler: |_, _, state| handle_get_stats(&state.config, &state.stats),
+        writes: false,
+    },
+    Route {
+        method: "GET",
+        matches: |path| path == "/debug/headers",
+        enabled: |config| config.dev,
+        handler: |req, _, _| handle_get_debug_headers(req),
+        writes: false,
+    },
+    Route {
+        method: "GET",
+        matches: |path| path == "/files" || path.starts_with("/files/"),
+        enabled: |_| true,
+        handler: |req, _, state| handle_get_file(req, &state.config),
+        writes: false,
+    },
+    Route {
+        method: "POST",
+        matches: |path| path.starts_with("/files/"),
+        enabled: |_| true,
+        handler: |req, body, state| handle_post_file(req, body, &state.config),
+        writes: true,
+    },
+    Route {
+        method: "PUT",
+        matches: |path| path.starts_with("/files/"),
+        enabled: |_| true,
+        handler: |req, body, state| handle_put_file(req, body, &state.config),
+        writes: true,
+    },
+    Route {
+        method: "DELETE",
+        matches: |path| path.starts_with("/files/"),
+        enabled: |_| true,
+        handler: |req, _, state| handle_delete_file(req, &state.config),
+        writes: true,
+    },
+    Route {
+        method: "POST",
+        matches: |path| path == "/admin/shutdown",
+        enabled: |config| config.admin_token.is_some(),
+        handler: |req, _, state| handle_post_admin_shutdown(req, &state.config, &state.shutdown),
+        writes: false,
+    },
+];
+
+// Lists methods as in the Allow header, adding HEAD wherever GET is
+// supported (it is served by the GET routes) and OPTIONS, which is always
+// supported.
+fn allow_header<'a>(methods: impl Iterator<Item = &'a str>) -> String {
+    let mut allow: Vec<&str> = vec![];
+
+    for method in methods {
+        if !allow.contains(&method) {
+            allow.push(method);
+            if method == "GET" {
+                allow.push("HEAD");
+            }
+        }
+    }
+    allow.push("OPTIONS");
+
+    allow.join(", ")
+}
+
+// Patterns of the routes served with the given config. A trailing "/" means
+// the route matches any path under it.
+fn routes(config: &Config) -> Vec<&'static str> {
+    let mut routes = vec!["/", "/echo/", "/user-agent", "/version", "/files/"];
+
+    if config.stats {
+        routes.push("/stats");
+    }
+    if config.admin_token.is_some() {
+        routes.push("/admin/shutdown");
+    }
+    if config.dev {
+        routes.push("/debug/headers");
+    }
+
+    routes
+}
+
+// 404 listing the routes which look like what the client meant, e.g.
+// "/eco/foo" suggests "/echo/". Only used in development mode, to keep
+// production responses terse.
+fn not_found_with_suggestions(path: &str, config: &Config) -> Response {
+    let segment = |path: &str| {
+        path.trim_start_matches('/')
+            .split(['/', '?'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+    };
+    let wanted = segment(path);
+
+    let suggestions: Vec<&str> = routes(config)
+        .into_iter()
+        .filter(|route| {
+            let known = segment(route);
+            !known.is_empty()
+                && !wanted.is_empty()
+                && (known.starts_with(&wanted)
+                    || wanted.starts_with(&known)
+                    || edit_distance(&known, &wanted) <= 2)
+        })
+        .collect();
+
+    let mut body = format!("No route matches {}.\n", path);
+    if !suggestions.is_empty() {
+        body.push_str(&format!("Did you mean: {}\n", suggestions.join(", ")));
+    }
+
+    Response {
+        status: Status::NotFound,
+        body: Some(Body::Bytes(body.into_bytes())),
+        content_type: Some("text/plain".to_string()),
+        headers: vec![],
+        trailers: None,
+    }
+}
+
+// Levenshtein distance: number of single character insertions, deletions or
+// substitutions needed to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+// Methods supported by the resource at the given path, as listed by the
+// routing table, or None when no route serves it.
+fn allowed_methods(path: &str, config: &Config) -> Option<String> {
+    let mut routes = ROUTES
+        .iter()
+        .filter(|route| route.serves(path, config))
+        .peekable();
+    routes.peek()?;
+
+    Some(allow_header(
+        routes
+            .filter(|route| route.allowed(config))
+            .map(|route| route.method),
+    ))
+}
+
+// OPTIONS describes the communication options of a resource through the
+// Allow header. The response has no body, but still carries a
+// "Content-Length: 0" so clients don't hang waiting for one.
+//
+// The asterisk-form target ("OPTIONS * HTTP/1.1") applies to the server in
+// general rather than to a specific resource.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc7231#section-4.3.7
+fn handle_options(req: &Request, config: &Config) -> Response {
+    let allow = if req.path == "*" {
+        Some(allow_header(
+            ROUTES
+                .iter()
+                .filter(|route| route.allowed(config))
+                .map(|route| route.method),
+        ))
+    } else {
+        allowed_methods(&req.path, config)
+    };
+
+    match allow {
+        Some(allow) => {
+            let mut res = Response::new(Status::OK);
+            res.headers.push(("Allow".to_string(), allow));
+            res
+        }
+        None => Response::new(Status::NotFound),
+    }
+}
+
+// Turns the request path into the one routes match against, applying the
+// trailing slash policy and removing the base path. Returns a response when
+// the request should not be routed at all.
+fn normalize_route_path(req: &mut Request, config: &Config) -> Option<Response> {
+    if let Some(res) = normalize_trailing_slash(req, config.trailing_slash) {
+        return Some(res);
+    }
+
+    if let Some(base_path) = &config.base_path {
+        match strip_base_path(&req.path, base_path) {
+            Some(path) => req.path = path,
+            None => return Some(Response::new(Status::NotFound)),
+        }
+    }
+    None
+}
+
+// Removes the base path from a request path, e.g. "/api/echo/x" becomes
+// "/echo/x" and "/api" becomes "/". The prefix only matches whole segments,
+// so "/apis" is not under "/api". Returns None for paths outside of it.
+fn strip_base_path(path: &str, base_path: &str) -> Option<String> {
+    let rest = path.strip_prefix(base_path)?;
+
+    if rest.is_empty() || rest.starts_with('?') {
+        Some(format!("/{}", rest))
+    } else if rest.starts_with('/') {
+        Some(rest.to_string())
+    } else {
+        None
+    }
+}
+
+// Applies the trailing slash policy to the request path before routing.
+// Returns a response when the request should not be routed at all (redirect).
+fn normalize_trailing_slash(req: &mut Request, policy: TrailingSlash) -> Option<Response> {
+    // The root path is the only one which is allowed to end with a slash.
+    if req.path == "/" || !req.path.ends_with('/') {
+        return None;
+    }
+
+    match policy {
+        TrailingSlash::Strict => None,
+        TrailingSlash::Ignore => {
+            req.path.pop();
+            None
+        }
+        TrailingSlash::Redirect => {
+            let mut location = percent_encode_path(&req.path[..req.path.len() - 1]);
+            if let Some((_, query)) = req.target.split_once('?') {
+                location.push('?');
+                location.push_str(query);
+            }
+
+            let mut res = Response::new(Status::MovedPermanently);
+            res.headers.push(("Location".to_string(), location));
+            Some(res)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn accepted_stream(config: &Config) -> TcpStream {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+
+        configure_stream(&stream, config).unwrap();
+        stream
+    }
+
+    fn forwarded_request(forwarded: &str) -> Request {
+        Request {
+            method: "GET".to_string(),
+            path: "/".to_string(),
+            target: "/".to_string(),
+            http_info: "HTTP/1.1".to_string(),
+            headers: vec![("X-Forwarded-For".to_string(), forwarded.to_string())],
+            body: vec![],
+        }
+    }
+
+    #[test]
+    fn client_ip_from_trusted_proxy() {
+        let peer: IpAddr = "10.0.0.1".parse().unwrap();
+        let trusted = Config {
+            trust_proxy: true,
+            ..Config::default()
+        };
+
+        let req = forwarded_request("203.0.113.7, 10.0.0.1");
+        assert_eq!(client_ip(&req, peer, &trusted).to_string(), "203.0.113.7");
+        assert_eq!(client_ip(&req, peer, &Config::default()), peer);
+
+        let req = forwarded_request("not-an-ip");
+        assert_eq!(client_ip(&req, peer, &trusted), peer);
+    }
+
+    #[test]
+    fn computes_edit_distance() {
+        assert_eq!(edit_distance("echo", "echo"), 0);
+        assert_eq!(edit_distance("eco", "echo"), 1);
+        assert_eq!(edit_distance("fiels", "files"), 2);
+        assert_eq!(edit_distance("", "stats"), 5);
+    }
+
+    #[test]
+    fn allowed_methods_follow_the_routing_table() {
+        let config = Config::default();
+        let allowed = |path| allowed_methods(path, &config);
+
+        assert_eq!(allowed("/").as_deref(), Some("GET, HEAD, OPTIONS"));
+        assert_eq!(allowed("/files").as_deref(), Some("GET, HEAD, OPTIONS"));
+        assert_eq!(
+            allowed("/files/a.txt").as_deref(),
+            Some("GET, HEAD, POST, PUT, DELETE, OPTIONS")
+        );
+        assert_eq!(allowed("/stats"), None);
+        assert_eq!(allowed("/admin/shutdown"), None);
+
+        let config = Config {
+            read_only: true,
+            admin_token: Some("secret".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(
+            allowed_methods("/files/a.txt", &config).as_deref(),
+            Some("GET, HEAD, OPTIONS")
+        );
+        assert_eq!(
+            allowed_methods("/admin/shutdown", &config).as_deref(),
+            Some("POST, OPTIONS")
+        );
+    }
+
+    #[test]
+    fn strips_base_path() {
+        assert_eq!(
+            strip_base_path("/api/echo/x", "/api"),
+            Some("/echo/x".to_string())
+        );
+        assert_eq!(strip_base_path("/api", "/api"), Some("/".to_string()));
+        assert_eq!(
+            strip_base_path("/api?a=1", "/api"),
+            Some("/?a=1".to_string())
+        );
+        assert_eq!(strip_base_path("/apis/echo/x", "/api"), None);
+        assert_eq!(strip_base_path("/echo/x", "/api"), None);
+    }
+
+    #[test]
+    fn counts_requests_served_on_a_connection() {
+        let server = Server::bind("127.0.0.1:0", Config::default()).unwrap();
+        let mut client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        let (stream, _) = server.listener.accept().unwrap();
+
+        client
+            .write_all(
+                b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n\
+                  GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            )
+            .unwrap();
+
+        let mut served = 0;
+        handle_connection(stream, Arc::clone(&server.state), &mut served).unwrap();
+
+        assert_eq!(served, 2);
+    }
+
+    #[test]
+    fn accept_backoff_doubles_up_to_a_second() {
+        let waits: Vec<u128> = (1..=10).map(|n| accept_backoff(n).as_millis()).collect();
+
+        assert_eq!(waits, [5, 10, 20, 40, 80, 160, 320, 640, 1000, 1000]);
+        assert_eq!(accept_backoff(u32::MAX), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn fatal_accept_errors() {
+        for kind in [
+            io::ErrorKind::ConnectionAborted,
+            io::ErrorKind::ConnectionReset,
+            io::ErrorKind::Interrupted,
+            io::ErrorKind::OutOfMemory,
+        ] {
+            assert!(!is_fatal_accept_error(&io::Error::from(kind)), "{:?}", kind);
+        }
+        // EMFILE, too many open files.
+        assert!(!is_fatal_accept_error(&io::Error::from_raw_os_error(24)));
+
+        assert!(is_fatal_accept_error(&io::Error::from(
+            io::ErrorKind::InvalidInput
+        )));
+        #[cfg(unix)]
+        assert!(is_fatal_accept_error(&io::Error::from_raw_os_error(9)));
+    }
+
+    #[test]
+    fn sets_nodelay_on_accepted_streams() {
+        assert!(accepted_stream(&Config::default()).nodelay().unwrap());
+
+        let config = Config {
+            tcp_nodelay: false,
+            ..Config::default()
+        };
+        assert!(!accepted_stream(&config).nodelay().unwrap());
+    }
+}
@@ -0,0 +1,75 @@
+// Guesses the content type of a file from its first bytes, for files whose
+// name doesn't tell (e.g. "LICENSE" or "Makefile"). Only a few unambiguous
+// signatures are recognized, anything else is left to the caller's default.
+//
+// Reference: https://mimesniff.spec.whatwg.org/#matching-an-image-type-pattern
+
+// How much of the file is looked at.
+const SNIFF_LEN: usize = 512;
+
+const SIGNATURES: [(&[u8], &str); 5] = [
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+];
+
+pub fn content_type(data: &[u8]) -> Option<&'static str> {
+    let head = &data[..data.len().min(SNIFF_LEN)];
+
+    if let Some((_, content_type)) = SIGNATURES.iter().find(|(sig, _)| head.starts_with(sig)) {
+        return Some(content_type);
+    }
+
+    is_text(head, head.len() < data.len()).then_some("text/plain")
+}
+
+// UTF-8 without control characters (other than whitespace and escape). When
+// the data is `truncated`, it may end in the middle of a character.
+fn is_text(data: &[u8], truncated: bool) -> bool {
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(e) if truncated && e.error_len().is_none() => {
+            // Valid up to a character cut by the truncation.
+            std::str::from_utf8(&data[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return false,
+    };
+
+    text.chars()
+        .all(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r' | '\x0c' | '\x1b'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_signatures() {
+        for (data, expected) in [
+            (&b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"[..], "image/png"),
+            (b"\xff\xd8\xff\xe0\0\x10JFIF", "image/jpeg"),
+            (b"GIF89a\x01\0\x01\0", "image/gif"),
+            (b"%PDF-1.7\n", "application/pdf"),
+            (b"MIT License\n\nCopyright (c)", "text/plain"),
+            ("Grüße\n".as_bytes(), "text/plain"),
+        ] {
+            assert_eq!(content_type(data), Some(expected), "{:?}", data);
+        }
+    }
+
+    #[test]
+    fn binary_data_is_unknown() {
+        assert_eq!(content_type(b"\x7fELF\x02\x01\x01\0"), None);
+        assert_eq!(content_type(b"\xff\xfe\xfd"), None);
+    }
+
+    #[test]
+    fn text_cut_in_the_middle_of_a_character() {
+        let mut data = "a".repeat(SNIFF_LEN - 1).into_bytes();
+        data.extend_from_slice("é".as_bytes());
+
+        assert_eq!(content_type(&data), Some("text/plain"));
+    }
+}
@@ -0,0 +1,101 @@
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::filesystem::FileSystem;
+
+// Walking a big directory tree on every hit would be expensive, so the result
+// is reused for a short while.
+const CACHE_TTL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DirStats {
+    pub file_count: u64,
+    pub total_bytes: u64,
+}
+
+pub struct StatsCache {
+    entry: Mutex<Option<(Instant, DirStats)>>,
+}
+
+impl StatsCache {
+    pub fn new() -> StatsCache {
+        StatsCache {
+            entry: Mutex::new(None),
+        }
+    }
+
+    pub fn get(&self, fs: &dyn FileSystem, dir: &Path) -> io::Result<DirStats> {
+        let mut entry = self.entry.lock().unwrap();
+
+        if let Some((at, stats)) = *entry {
+            if at.elapsed() < CACHE_TTL {
+                return Ok(stats);
+            }
+        }
+
+        let stats = walk(fs, dir)?;
+        *entry = Some((Instant::now(), stats));
+        Ok(stats)
+    }
+}
+
+// Recursively sums the size of every regular file under the directory.
+// Symlinks are not followed to avoid cycles.
+fn walk(fs: &dyn FileSystem, dir: &Path) -> io::Result<DirStats> {
+    let mut stats = DirStats {
+        file_count: 0,
+        total_bytes: 0,
+    };
+
+    for entry in fs.read_dir(dir)? {
+        if entry.is_symlink {
+            continue;
+        }
+
+        if entry.metadata.is_dir {
+            let sub = walk(fs, &dir.join(&entry.name))?;
+            stats.file_count += sub.file_count;
+            stats.total_bytes += sub.total_bytes;
+        } else {
+            stats.file_count += 1;
+            stats.total_bytes += entry.metadata.len;
+        }
+    }
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::MemoryFileSystem;
+
+    #[test]
+    fn walks_nested_directories() {
+        let fs = MemoryFileSystem::new()
+            .with_file("/srv/a.txt", b"hello")
+            .with_file("/srv/docs/b.txt", b"hi")
+            .with_file("/srv/docs/sub/c.bin", &[0; 10])
+            .with_file("/other/d.txt", b"not counted");
+
+        assert_eq!(
+            StatsCache::new().get(&fs, Path::new("/srv")).unwrap(),
+            DirStats {
+                file_count: 3,
+                total_bytes: 17,
+            }
+        );
+    }
+
+    #[test]
+    fn walk_errors_are_reported() {
+        let fs = MemoryFileSystem::new()
+            .with_file("/srv/docs/a.txt", b"hello")
+            .with_error("/srv/docs", io::ErrorKind::PermissionDenied);
+
+        let err = StatsCache::new().get(&fs, Path::new("/srv")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    }
+}
@@ -0,0 +1,116 @@
+// ZLIB format, a DEFLATE stream wrapped with a two-byte header and an
+// Adler-32 trailer. Used for the "deflate" content-coding, which despite its
+// name is a zlib stream rather than raw DEFLATE.
+//
+// Reference: https://datatracker.ietf.org/doc/html/rfc1950
+
+use std::io;
+
+use crate::deflate;
+
+// CM = 8 (deflate) with CINFO = 7 (32K window).
+const CMF: u8 = 0x78;
+
+// Largest prime smaller than 65536.
+const ADLER_MOD: u32 = 65521;
+
+pub fn encode(data: &[u8], level: u32) -> Vec<u8> {
+    // FLEVEL is informative only, FCHECK makes the header a multiple of 31.
+    let flevel: u8 = match level {
+        0 | 1 => 0,
+        2..=5 => 1,
+        6 => 2,
+        _ => 3,
+    };
+    let flg = flevel << 6;
+    let fcheck = ((31 - (CMF as u16 * 256 + flg as u16) % 31) % 31) as u8;
+
+    let mut out = vec![CMF, flg | fcheck];
+    out.extend_from_slice(&deflate::compress(data, level));
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+pub fn decode(data: &[u8]) -> io::Result<Vec<u8>> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+    if data.len() < 6 || !u16::from_be_bytes([data[0], data[1]]).is_multiple_of(31) {
+        return Err(invalid("not a zlib stream"));
+    }
+    if data[0] & 0x0f != 8 {
+        return Err(invalid("unsupported zlib compression method"));
+    }
+    // FDICT: a preset dictionary this side doesn't know about.
+    if data[1] & 0x20 != 0 {
+        return Err(invalid("zlib preset dictionaries are not supported"));
+    }
+
+    let (out, consumed) = deflate::inflate(&data[2..])?;
+
+    let trailer = data
+        .get(2 + consumed..2 + consumed + 4)
+        .ok_or_else(|| invalid("truncated zlib trailer"))?;
+    let adler = u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+
+    if adler != adler32(&out) {
+        return Err(invalid("zlib checksum mismatch"));
+    }
+
+    Ok(out)
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+
+    // 5552 bytes is the most that can be summed before `b` could overflow.
+    for block in data.chunks(5552) {
+        for &byte in block {
+            a += byte as u32;
+            b += a;
+        }
+        a %= ADLER_MOD;
+        b %= ADLER_MOD;
+    }
+
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adler32_check_value() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+        assert_eq!(adler32(b""), 1);
+    }
+
+    #[test]
+    fn round_trips() {
+        let data = "hello zlib ".repeat(100);
+
+        for level in [0, 1, 6, 9] {
+            let encoded = encode(data.as_bytes(), level);
+            assert!(u16::from_be_bytes([encoded[0], encoded[1]]).is_multiple_of(31));
+            assert_eq!(decode(&encoded).unwrap(), data.as_bytes());
+        }
+    }
+
+    #[test]
+    fn decodes_reference_stream() {
+        // `python3 -c 'import zlib; print(zlib.compress(b"hello"))'`
+        let encoded = b"x\x9c\xcbH\xcd\xc9\xc9\x07\x00\x06,\x02\x15";
+
+        assert_eq!(decode(encoded).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn rejects_corrupt_streams() {
+        let mut encoded = encode(b"hello", 6);
+        let last = encoded.len() - 1;
+        encoded[last] ^= 1;
+        assert!(decode(&encoded).is_err());
+
+        assert!(decode(b"\x1f\x8b\x08\x00\x00\x00").is_err());
+    }
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use std::fs;
+
+use common::{status_line, TempDir, TestServer};
+use http_server_starter_rust::{Config, Server};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn requests_are_appended_to_the_access_log() {
+    let dir = TempDir::new();
+    let path = dir.path().join("access.log");
+    fs::write(&path, "previous line\n").unwrap();
+
+    let server = TestServer::with_config(Config {
+        access_log: Some(path.clone()),
+        ..Config::default()
+    });
+    let res = server.send(b"GET /echo/abc HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    server.send(b"GET /missing HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    let log = fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = log.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", log);
+    assert_eq!(lines[0], "previous line");
+
+    for (line, end) in [
+        (lines[1], "] \"GET /echo/abc HTTP/1.1\" 200 3"),
+        (lines[2], "] \"GET /missing HTTP/1.1\" 404 0"),
+    ] {
+        assert!(line.starts_with("127.0.0.1 - - ["), "{}", line);
+        assert!(line.ends_with(end), "{}", line);
+    }
+}
+
+#[test]
+fn unopenable_access_log_is_an_error() {
+    let dir = TempDir::new();
+    let config = Config {
+        access_log: Some(dir.path().join("missing").join("access.log")),
+        ..Config::default()
+    };
+
+    let err = Server::bind("127.0.0.1:0", config).err().unwrap();
+    assert!(err.to_string().contains("access log"), "{}", err);
+}
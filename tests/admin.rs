@@ -0,0 +1,75 @@
+mod common;
+
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::{header, status_line, TestServer};
+use http_server_starter_rust::Config;
+use pretty_assertions::assert_eq;
+
+fn admin_server() -> TestServer {
+    TestServer::with_config(Config {
+        admin_token: Some("s3cret".to_string()),
+        ..Config::default()
+    })
+}
+
+fn shutdown_request(authorization: &str) -> String {
+    format!(
+        "POST /admin/shutdown HTTP/1.1\r\nHost: localhost\r\n{}Content-Length: 0\r\n\r\n",
+        authorization
+    )
+}
+
+#[test]
+fn valid_token_shuts_the_server_down() {
+    let server = admin_server();
+    let res = server.send_str(&shutdown_request("Authorization: Bearer s3cret\r\n"));
+
+    assert_eq!(status_line(res.as_bytes()), "HTTP/1.1 202 Accepted");
+
+    // Once the accept loop is gone, the listener is closed and connecting
+    // is refused.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while TcpStream::connect(server.addr).is_ok() {
+        assert!(Instant::now() < deadline, "server is still accepting");
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn missing_or_wrong_token_is_unauthorized() {
+    let server = admin_server();
+
+    for authorization in [
+        "",
+        "Authorization: Bearer wrong\r\n",
+        "Authorization: s3cre\r\n",
+    ] {
+        let res = server.send_str(&shutdown_request(authorization));
+
+        assert_eq!(
+            status_line(res.as_bytes()),
+            "HTTP/1.1 401 Unauthorized",
+            "{:?}",
+            authorization
+        );
+        assert_eq!(
+            header(res.as_bytes(), "WWW-Authenticate").as_deref(),
+            Some("Bearer")
+        );
+    }
+
+    // Still up.
+    let res = server.send_str("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(status_line(res.as_bytes()), "HTTP/1.1 200 OK");
+}
+
+#[test]
+fn endpoint_is_disabled_without_a_token() {
+    let server = TestServer::start();
+    let res = server.send_str(&shutdown_request("Authorization: Bearer s3cret\r\n"));
+
+    assert_eq!(status_line(res.as_bytes()), "HTTP/1.1 404 Not Found");
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use common::{body, dir_config, header, status_line, TempDir, TestServer};
+use http_server_starter_rust::Config;
+use pretty_assertions::assert_eq;
+
+fn server_under(base_path: &str) -> TestServer {
+    TestServer::with_config(Config {
+        base_path: Some(base_path.to_string()),
+        ..Config::default()
+    })
+}
+
+fn get(server: &TestServer, path: &str) -> Vec<u8> {
+    server.send(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes())
+}
+
+#[test]
+fn routes_paths_under_the_base_path() {
+    let server = server_under("/api");
+
+    let res = get(&server, "/api/echo/x");
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(body(&res), b"x");
+
+    assert_eq!(status_line(&get(&server, "/api")), "HTTP/1.1 200 OK");
+}
+
+#[test]
+fn paths_outside_the_base_path_are_not_found() {
+    let server = server_under("/api");
+
+    assert_eq!(
+        status_line(&get(&server, "/echo/x")),
+        "HTTP/1.1 404 Not Found"
+    );
+    assert_eq!(
+        status_line(&get(&server, "/apis/echo/x")),
+        "HTTP/1.1 404 Not Found"
+    );
+}
+
+#[test]
+fn created_location_includes_the_base_path() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(Config {
+        base_path: Some("/api".to_string()),
+        ..dir_config(&dir)
+    });
+
+    let res = server.send(
+        b"POST /api/files/new.txt HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\n\r\nhi",
+    );
+
+    assert_eq!(status_line(&res), "HTTP/1.1 201 Created");
+    assert_eq!(
+        header(&res, "Location").as_deref(),
+        Some("/api/files/new.txt")
+    );
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use std::fs;
+
+use common::{dir_config, header, status_line, TempDir, TestServer};
+use http_server_starter_rust::Config;
+use pretty_assertions::assert_eq;
+
+fn cache_control(server: &TestServer, path: &str) -> Option<String> {
+    let res = server.send(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes());
+    header(&res, "Cache-Control")
+}
+
+#[test]
+fn files_are_cacheable_for_the_configured_max_age() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("a.txt"), "a").unwrap();
+    let server = TestServer::with_config(Config {
+        file_cache_max_age: 3600,
+        ..dir_config(&dir)
+    });
+
+    assert_eq!(
+        cache_control(&server, "/files/a.txt").as_deref(),
+        Some("public, max-age=3600")
+    );
+}
+
+#[test]
+fn files_must_be_revalidated_by_default() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("a.txt"), "a").unwrap();
+    let server = TestServer::with_config(dir_config(&dir));
+
+    assert_eq!(
+        cache_control(&server, "/files/a.txt").as_deref(),
+        Some("public, max-age=0")
+    );
+}
+
+#[test]
+fn not_modified_files_keep_the_file_policy() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("a.txt"), "a").unwrap();
+    let server = TestServer::with_config(Config {
+        file_cache_max_age: 3600,
+        ..dir_config(&dir)
+    });
+
+    let res = server.send(b"GET /files/a.txt HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    let etag = header(&res, "ETag").unwrap();
+    let res = server.send(
+        format!(
+            "GET /files/a.txt HTTP/1.1\r\nHost: localhost\r\nIf-None-Match: {}\r\n\r\n",
+            etag
+        )
+        .as_bytes(),
+    );
+
+    assert_eq!(status_line(&res), "HTTP/1.1 304 Not Modified");
+    assert_eq!(
+        header(&res, "Cache-Control").as_deref(),
+        Some("public, max-age=3600")
+    );
+}
+
+#[test]
+fn dynamic_and_error_responses_are_not_stored() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(Config {
+        file_cache_max_age: 3600,
+        ..dir_config(&dir)
+    });
+
+    for path in ["/echo/abc", "/user-agent", "/files/missing.txt", "/unknown"] {
+        assert_eq!(
+            cache_control(&server, path).as_deref(),
+            Some("no-store"),
+            "{}",
+            path
+        );
+    }
+
+    // Rejected before routing.
+    let res = server.send(b"GET / HTTP/1.1\r\n\r\n");
+    assert_eq!(status_line(&res), "HTTP/1.1 400 Bad Request");
+    assert_eq!(header(&res, "Cache-Control").as_deref(), Some("no-store"));
+}
+
+#[test]
+fn configured_cache_control_wins() {
+    let server = TestServer::with_config(Config {
+        headers: vec![("Cache-Control".to_string(), "private".to_string())],
+        ..Config::default()
+    });
+
+    assert_eq!(
+        cache_control(&server, "/echo/abc").as_deref(),
+        Some("private")
+    );
+}
@@ -0,0 +1,167 @@
+// Shared helpers for the integration tests.
+//
+// Every test boots its own server on an ephemeral port (127.0.0.1:0) so tests
+// can run in parallel without fighting over the hardcoded 4221 port.
+
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use http_server_starter_rust::{Config, Server, ShutdownHandle};
+
+pub struct TestServer {
+    pub addr: SocketAddr,
+    handle: ShutdownHandle,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl TestServer {
+    pub fn start() -> TestServer {
+        TestServer::with_config(Config::default())
+    }
+
+    pub fn with_config(config: Config) -> TestServer {
+        let server = Server::bind("127.0.0.1:0", config).expect("bind test server");
+        let addr = server.local_addr().expect("read test server address");
+        let handle = server.shutdown_handle().expect("create shutdown handle");
+        let thread = thread::spawn(move || server.run());
+
+        TestServer {
+            addr,
+            handle,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn connect(&self) -> TcpStream {
+        TcpStream::connect(self.addr).expect("connect to test server")
+    }
+
+    // Sends a raw request, closes the write side of the socket and returns
+    // everything the server wrote back until it closed the connection.
+    pub fn send(&self, raw: &[u8]) -> Vec<u8> {
+        let mut stream = self.connect();
+        stream.write_all(raw).expect("write request");
+        stream.shutdown(Shutdown::Write).expect("shutdown write");
+
+        read_until_closed(&mut stream)
+    }
+
+    pub fn send_str(&self, raw: &str) -> String {
+        String::from_utf8(self.send(raw.as_bytes())).expect("utf-8 response")
+    }
+}
+
+// Reads everything until the server closes the connection. A reset is treated
+// as a close too: the server may drop the connection with unread request
+// bytes still pending (e.g. after rejecting an oversized request).
+pub fn read_until_closed(stream: &mut TcpStream) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    loop {
+        match stream.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            Err(e) if e.kind() == ErrorKind::ConnectionReset => break,
+            Err(e) => panic!("read response: {}", e),
+        }
+    }
+
+    buf
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.handle.shutdown();
+        if let Some(thread) = self.thread.take() {
+            thread.join().expect("join server thread");
+        }
+    }
+}
+
+// A uniquely named directory under the system temp dir, removed on drop.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new() -> TempDir {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!(
+            "http-server-test-{}-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst),
+            nanos
+        ));
+        fs::create_dir_all(&path).expect("create temp dir");
+
+        TempDir { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn path_string(&self) -> String {
+        self.path.to_string_lossy().to_string()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+// Default config serving files out of the given temp directory.
+pub fn dir_config(dir: &TempDir) -> Config {
+    Config {
+        directory: Some(dir.path_string()),
+        ..Config::default()
+    }
+}
+
+// Splits a raw response into its head (status line + headers) and body.
+pub fn split_response(res: &[u8]) -> (String, Vec<u8>) {
+    let pos = res
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .expect("response has a header terminator");
+
+    let head = String::from_utf8(res[..pos].to_vec()).expect("utf-8 head");
+    (head, res[pos + 4..].to_vec())
+}
+
+pub fn status_line(res: &[u8]) -> String {
+    let (head, _) = split_response(res);
+    head.lines().next().unwrap_or_default().to_string()
+}
+
+// Returns the first header value matching the key (case-insensitive).
+pub fn header(res: &[u8], key: &str) -> Option<String> {
+    let (head, _) = split_response(res);
+    head.lines().skip(1).find_map(|line| {
+        let (k, v) = line.split_once(':')?;
+        if k.trim().eq_ignore_ascii_case(key) {
+            Some(v.trim().to_string())
+        } else {
+            None
+        }
+    })
+}
+
+pub fn body(res: &[u8]) -> Vec<u8> {
+    split_response(res).1
+}
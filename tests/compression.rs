@@ -0,0 +1,292 @@
+mod common;
+
+use std::fs;
+
+use common::{body, dir_config, header, status_line, TempDir, TestServer};
+use http_server_starter_rust::{brotli, gzip, zlib, Config};
+use pretty_assertions::assert_eq;
+
+fn echo(server: &TestServer, text: &str) -> Vec<u8> {
+    server.send(
+        format!(
+            "GET /echo/{} HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: gzip\r\n\r\n",
+            text
+        )
+        .as_bytes(),
+    )
+}
+
+#[test]
+fn short_body_stays_uncompressed() {
+    let server = TestServer::start();
+    let res = echo(&server, "abc");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(header(&res, "Content-Encoding"), None);
+    assert_eq!(body(&res), b"abc");
+}
+
+#[test]
+fn long_body_is_gzipped() {
+    let server = TestServer::start();
+    let text = "abc".repeat(1000);
+    let res = echo(&server, &text);
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(header(&res, "Content-Encoding").as_deref(), Some("gzip"));
+    assert_eq!(header(&res, "Vary").as_deref(), Some("Accept-Encoding"));
+
+    let compressed = body(&res);
+    assert_eq!(
+        header(&res, "Content-Length"),
+        Some(compressed.len().to_string())
+    );
+    assert_eq!(gzip::decode(&compressed).unwrap(), text.as_bytes());
+}
+
+#[test]
+fn long_body_is_not_gzipped_without_accept_encoding() {
+    let server = TestServer::start();
+    let text = "abc".repeat(1000);
+    let res =
+        server.send(format!("GET /echo/{} HTTP/1.1\r\nHost: localhost\r\n\r\n", text).as_bytes());
+
+    assert_eq!(header(&res, "Content-Encoding"), None);
+    assert_eq!(body(&res), text.as_bytes());
+}
+
+#[test]
+fn threshold_is_configurable() {
+    let server = TestServer::with_config(Config {
+        compression_min_size: 0,
+        ..Config::default()
+    });
+    let res = echo(&server, "abc");
+
+    assert_eq!(header(&res, "Content-Encoding").as_deref(), Some("gzip"));
+    assert_eq!(gzip::decode(&body(&res)).unwrap(), b"abc");
+}
+
+#[test]
+fn gzip_level_is_configurable() {
+    let text = "compressible-text-".repeat(200);
+
+    let sizes: Vec<usize> = [0, 9]
+        .into_iter()
+        .map(|level| {
+            let server = TestServer::with_config(Config {
+                gzip_level: level,
+                ..Config::default()
+            });
+            let compressed = body(&echo(&server, &text));
+            assert_eq!(gzip::decode(&compressed).unwrap(), text.as_bytes());
+            compressed.len()
+        })
+        .collect();
+
+    // Level 0 only stores the data, so it can't be smaller than the input.
+    assert!(sizes[0] > text.len(), "{:?}", sizes);
+    assert!(sizes[1] < sizes[0], "{:?}", sizes);
+}
+
+fn echo_accepting(server: &TestServer, text: &str, accept_encoding: &str) -> Vec<u8> {
+    server.send(
+        format!(
+            "GET /echo/{} HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: {}\r\n\r\n",
+            text, accept_encoding
+        )
+        .as_bytes(),
+    )
+}
+
+#[test]
+fn long_body_is_brotli_compressed() {
+    let server = TestServer::start();
+    let text = "abc".repeat(1000);
+    let res = echo_accepting(&server, &text, "br");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(header(&res, "Content-Encoding").as_deref(), Some("br"));
+    assert_eq!(header(&res, "Vary").as_deref(), Some("Accept-Encoding"));
+
+    let compressed = body(&res);
+    assert!(compressed.len() < text.len() / 10);
+    assert_eq!(brotli::decompress(&compressed).unwrap(), text.as_bytes());
+}
+
+#[test]
+fn preferred_encoding_is_used() {
+    let server = TestServer::start();
+    let text = "abc".repeat(1000);
+
+    for (accept_encoding, expected) in [
+        ("gzip, deflate, br", "br"),
+        ("gzip;q=1.0, br;q=0.5", "gzip"),
+        ("br;q=0.2, gzip;q=0.8", "gzip"),
+        ("gzip;q=0.5, br", "br"),
+        ("br;q=0, *", "gzip"),
+        ("*", "br"),
+    ] {
+        let res = echo_accepting(&server, &text, accept_encoding);
+        assert_eq!(
+            header(&res, "Content-Encoding").as_deref(),
+            Some(expected),
+            "{}",
+            accept_encoding
+        );
+    }
+}
+
+#[test]
+fn long_body_is_deflate_compressed() {
+    let server = TestServer::start();
+    let text = "abc".repeat(1000);
+    let res = echo_accepting(&server, &text, "deflate");
+
+    assert_eq!(header(&res, "Content-Encoding").as_deref(), Some("deflate"));
+    assert_eq!(zlib::decode(&body(&res)).unwrap(), text.as_bytes());
+}
+
+#[test]
+fn configured_encodings_are_used_in_order() {
+    let server = TestServer::with_config(Config {
+        encodings: vec!["deflate".to_string(), "gzip".to_string()],
+        ..Config::default()
+    });
+    let text = "abc".repeat(1000);
+
+    for (accept_encoding, expected) in [
+        ("br, gzip, deflate", Some("deflate")),
+        ("gzip;q=1.0, deflate;q=0.5", Some("gzip")),
+        ("br", None),
+    ] {
+        let res = echo_accepting(&server, &text, accept_encoding);
+        assert_eq!(
+            header(&res, "Content-Encoding").as_deref(),
+            expected,
+            "{}",
+            accept_encoding
+        );
+    }
+}
+
+#[test]
+fn no_configured_encodings_disables_compression() {
+    let server = TestServer::with_config(Config {
+        encodings: vec![],
+        ..Config::default()
+    });
+    let text = "abc".repeat(1000);
+    let res = echo_accepting(&server, &text, "br, gzip, deflate");
+
+    assert_eq!(header(&res, "Content-Encoding"), None);
+    assert_eq!(header(&res, "Vary"), None);
+    assert_eq!(body(&res), text.as_bytes());
+}
+
+fn get_file_accepting_gzip(server: &TestServer, name: &str) -> Vec<u8> {
+    server.send(
+        format!(
+            "GET /files/{} HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: gzip\r\n\r\n",
+            name
+        )
+        .as_bytes(),
+    )
+}
+
+#[test]
+fn text_file_is_gzipped() {
+    let dir = TempDir::new();
+    let text = "plain text file contents\n".repeat(100);
+    fs::write(dir.path().join("notes.txt"), &text).unwrap();
+    let server = TestServer::with_config(dir_config(&dir));
+
+    let res = get_file_accepting_gzip(&server, "notes.txt");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(header(&res, "Content-Encoding").as_deref(), Some("gzip"));
+    let compressed = body(&res);
+    assert_eq!(
+        header(&res, "Content-Length"),
+        Some(compressed.len().to_string())
+    );
+    assert_eq!(gzip::decode(&compressed).unwrap(), text.as_bytes());
+}
+
+#[test]
+fn gzipped_file_is_a_representation_of_its_own() {
+    let dir = TempDir::new();
+    fs::write(
+        dir.path().join("notes.txt"),
+        "plain text file contents\n".repeat(100),
+    )
+    .unwrap();
+    let server = TestServer::with_config(dir_config(&dir));
+
+    let plain = server.send(b"GET /files/notes.txt HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    let res = get_file_accepting_gzip(&server, "notes.txt");
+
+    let plain_etag = header(&plain, "ETag").unwrap();
+    let etag = header(&res, "ETag").unwrap();
+    assert_ne!(etag, plain_etag);
+    assert_eq!(header(&plain, "Accept-Ranges").as_deref(), Some("bytes"));
+    assert_eq!(header(&res, "Accept-Ranges").as_deref(), Some("none"));
+
+    // Revalidating the compressed copy answers with its own tag.
+    let res = server.send(
+        format!(
+            "GET /files/notes.txt HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: gzip\r\nIf-None-Match: {}\r\n\r\n",
+            etag
+        )
+        .as_bytes(),
+    );
+    assert_eq!(status_line(&res), "HTTP/1.1 304 Not Modified");
+    assert_eq!(header(&res, "ETag"), Some(etag));
+}
+
+#[test]
+fn already_compressed_media_type_is_not_gzipped() {
+    let dir = TempDir::new();
+    let data = vec![0u8; 4096];
+    fs::write(dir.path().join("image.png"), &data).unwrap();
+    let server = TestServer::with_config(Config {
+        mime_types: vec![("png".to_string(), "image/png".to_string())],
+        ..dir_config(&dir)
+    });
+
+    let res = get_file_accepting_gzip(&server, "image.png");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(header(&res, "Content-Encoding"), None);
+    assert_eq!(body(&res), data);
+}
+
+#[test]
+fn identity_refused_compresses_even_short_bodies() {
+    let server = TestServer::start();
+    let res = echo_accepting(&server, "abc", "gzip, identity;q=0");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(header(&res, "Content-Encoding").as_deref(), Some("gzip"));
+    assert_eq!(gzip::decode(&body(&res)).unwrap(), b"abc");
+}
+
+#[test]
+fn no_acceptable_coding_is_not_acceptable() {
+    let server = TestServer::start();
+
+    for accept_encoding in [
+        "identity;q=0",
+        "compress, *;q=0",
+        "br;q=0, gzip;q=0, identity;q=0",
+    ] {
+        let res = echo_accepting(&server, "abc", accept_encoding);
+        assert_eq!(
+            status_line(&res),
+            "HTTP/1.1 406 Not Acceptable",
+            "{}",
+            accept_encoding
+        );
+        assert_eq!(header(&res, "Vary").as_deref(), Some("Accept-Encoding"));
+    }
+}
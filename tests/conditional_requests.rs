@@ -0,0 +1,213 @@
+mod common;
+
+use std::fs;
+
+use common::{body, dir_config, header, status_line, TempDir, TestServer};
+use pretty_assertions::assert_eq;
+
+fn server_with_file(contents: &str) -> (TempDir, TestServer) {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("file.txt"), contents).unwrap();
+    let server = TestServer::with_config(dir_config(&dir));
+    (dir, server)
+}
+
+fn get(server: &TestServer, extra_headers: &str) -> Vec<u8> {
+    server.send(
+        format!(
+            "GET /files/file.txt HTTP/1.1\r\nHost: localhost\r\n{}\r\n",
+            extra_headers
+        )
+        .as_bytes(),
+    )
+}
+
+#[test]
+fn matching_if_none_match_is_not_modified() {
+    let (_dir, server) = server_with_file("hello");
+    let etag = header(&get(&server, ""), "ETag").unwrap();
+
+    for if_none_match in [
+        etag.clone(),
+        format!("\"other\", W/{}", etag),
+        "*".to_string(),
+    ] {
+        let res = get(&server, &format!("If-None-Match: {}\r\n", if_none_match));
+
+        assert_eq!(
+            status_line(&res),
+            "HTTP/1.1 304 Not Modified",
+            "{}",
+            if_none_match
+        );
+        assert_eq!(header(&res, "ETag").as_deref(), Some(etag.as_str()));
+        assert_eq!(header(&res, "Content-Length"), None);
+        assert_eq!(body(&res), b"");
+    }
+}
+
+#[test]
+fn stale_if_none_match_serves_the_file() {
+    let (dir, server) = server_with_file("hello");
+    let etag = header(&get(&server, ""), "ETag").unwrap();
+
+    fs::write(dir.path().join("file.txt"), "hello, changed").unwrap();
+    let res = get(&server, &format!("If-None-Match: {}\r\n", etag));
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_ne!(header(&res, "ETag"), Some(etag));
+    assert_eq!(body(&res), b"hello, changed");
+}
+
+#[test]
+fn not_modified_keeps_the_connection_usable() {
+    let (_dir, server) = server_with_file("hello");
+    let etag = header(&get(&server, ""), "ETag").unwrap();
+
+    let res = server.send(
+        format!(
+            "GET /files/file.txt HTTP/1.1\r\nHost: localhost\r\nIf-None-Match: {}\r\n\r\n\
+             GET /echo/next HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            etag
+        )
+        .as_bytes(),
+    );
+
+    let text = String::from_utf8_lossy(&res);
+    assert!(
+        text.starts_with("HTTP/1.1 304 Not Modified\r\n"),
+        "{}",
+        text
+    );
+    assert!(text.ends_with("\r\n\r\nnext"), "{}", text);
+}
+
+#[test]
+fn unchanged_since_if_modified_since_is_not_modified() {
+    let (_dir, server) = server_with_file("hello");
+    let last_modified = header(&get(&server, ""), "Last-Modified").unwrap();
+
+    let res = get(
+        &server,
+        &format!("If-Modified-Since: {}\r\n", last_modified),
+    );
+
+    assert_eq!(status_line(&res), "HTTP/1.1 304 Not Modified");
+    assert_eq!(
+        header(&res, "Last-Modified").as_deref(),
+        Some(last_modified.as_str())
+    );
+    assert_eq!(body(&res), b"");
+}
+
+#[test]
+fn modified_since_if_modified_since_serves_the_file() {
+    let (_dir, server) = server_with_file("hello");
+
+    for since in [
+        "Thu, 01 Jan 1970 00:00:00 GMT",
+        // Invalid, and in the future: both ignored.
+        "not a date",
+        "Fri, 31 Dec 9999 23:59:59 GMT",
+    ] {
+        let res = get(&server, &format!("If-Modified-Since: {}\r\n", since));
+
+        assert_eq!(status_line(&res), "HTTP/1.1 200 OK", "{}", since);
+        assert_eq!(body(&res), b"hello");
+    }
+}
+
+#[test]
+fn if_none_match_takes_precedence_over_if_modified_since() {
+    let (_dir, server) = server_with_file("hello");
+    let last_modified = header(&get(&server, ""), "Last-Modified").unwrap();
+
+    let res = get(
+        &server,
+        &format!(
+            "If-None-Match: \"other\"\r\nIf-Modified-Since: {}\r\n",
+            last_modified
+        ),
+    );
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+}
+
+fn post(server: &TestServer, extra_headers: &str, body: &str) -> Vec<u8> {
+    server.send(
+        format!(
+            "POST /files/file.txt HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n{}\r\n{}",
+            body.len(),
+            extra_headers,
+            body
+        )
+        .as_bytes(),
+    )
+}
+
+#[test]
+fn write_with_matching_if_match_replaces_the_file() {
+    let (dir, server) = server_with_file("version 1");
+    let etag = header(&get(&server, ""), "ETag").unwrap();
+
+    let res = post(&server, &format!("If-Match: {}\r\n", etag), "version 2");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 201 Created");
+    assert_eq!(
+        fs::read_to_string(dir.path().join("file.txt")).unwrap(),
+        "version 2"
+    );
+}
+
+#[test]
+fn write_with_stale_if_match_is_a_precondition_failure() {
+    let (dir, server) = server_with_file("version 1");
+    let etag = header(&get(&server, ""), "ETag").unwrap();
+    // Someone else updates the file in the meantime.
+    let res = post(&server, "", "version 2, someone else's");
+    assert_eq!(status_line(&res), "HTTP/1.1 201 Created");
+
+    let res = post(&server, &format!("If-Match: {}\r\n", etag), "version 2");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 412 Precondition Failed");
+    assert_eq!(
+        fs::read_to_string(dir.path().join("file.txt")).unwrap(),
+        "version 2, someone else's"
+    );
+}
+
+#[test]
+fn write_if_match_any_needs_an_existing_file() {
+    let (dir, server) = server_with_file("version 1");
+
+    let res = server.send(
+        b"POST /files/new.txt HTTP/1.1\r\nHost: localhost\r\nIf-Match: *\r\nContent-Length: 3\r\n\r\nnew",
+    );
+
+    assert_eq!(status_line(&res), "HTTP/1.1 412 Precondition Failed");
+    assert!(!dir.path().join("new.txt").exists());
+}
+
+#[test]
+fn write_with_if_unmodified_since() {
+    let (dir, server) = server_with_file("version 1");
+    let last_modified = header(&get(&server, ""), "Last-Modified").unwrap();
+
+    let res = post(
+        &server,
+        "If-Unmodified-Since: Thu, 01 Jan 1970 00:00:00 GMT\r\n",
+        "stale",
+    );
+    assert_eq!(status_line(&res), "HTTP/1.1 412 Precondition Failed");
+
+    let res = post(
+        &server,
+        &format!("If-Unmodified-Since: {}\r\n", last_modified),
+        "version 2",
+    );
+    assert_eq!(status_line(&res), "HTTP/1.1 201 Created");
+    assert_eq!(
+        fs::read_to_string(dir.path().join("file.txt")).unwrap(),
+        "version 2"
+    );
+}
@@ -0,0 +1,285 @@
+mod common;
+
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use common::{body, dir_config, header, read_until_closed, status_line, TempDir, TestServer};
+use http_server_starter_rust::Config;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn connection_close_closes_the_socket_after_the_response() {
+    let server = TestServer::start();
+    let mut stream = server.connect();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    // The write side is left open, so the response can only end with EOF if
+    // the server closes the connection.
+    stream
+        .write_all(b"GET /echo/bye HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .unwrap();
+
+    let mut res = vec![];
+    stream
+        .read_to_end(&mut res)
+        .expect("server closes the connection");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(header(&res, "Connection").as_deref(), Some("close"));
+}
+
+#[test]
+fn http_1_0_closes_by_default() {
+    let server = TestServer::start();
+    let mut stream = server.connect();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    stream.write_all(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+
+    let mut res = vec![];
+    stream
+        .read_to_end(&mut res)
+        .expect("server closes the connection");
+
+    assert_eq!(status_line(&res), "HTTP/1.0 200 OK");
+    assert_eq!(header(&res, "Connection").as_deref(), Some("close"));
+}
+
+#[test]
+fn http_1_0_keep_alive() {
+    let server = TestServer::start();
+    let mut stream = server.connect();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    stream
+        .write_all(b"GET /echo/first HTTP/1.0\r\nConnection: keep-alive\r\n\r\n")
+        .unwrap();
+    let mut res = vec![];
+    while !res.ends_with(b"first") {
+        let mut buf = [0u8; 1024];
+        let n = stream.read(&mut buf).unwrap();
+        assert!(n > 0, "connection closed after the first response");
+        res.extend_from_slice(&buf[..n]);
+    }
+
+    assert_eq!(status_line(&res), "HTTP/1.0 200 OK");
+    assert_eq!(header(&res, "Connection").as_deref(), Some("keep-alive"));
+    assert_eq!(header(&res, "Content-Length").as_deref(), Some("5"));
+
+    stream
+        .write_all(b"GET /echo/second HTTP/1.0\r\n\r\n")
+        .unwrap();
+    let res = read_until_closed(&mut stream);
+
+    assert_eq!(status_line(&res), "HTTP/1.0 200 OK");
+    assert_eq!(header(&res, "Connection").as_deref(), Some("close"));
+    assert_eq!(body(&res), b"second");
+}
+
+// Reads a single response, delimited by its Content-Length, leaving the
+// connection open.
+fn read_response(stream: &mut TcpStream) -> Vec<u8> {
+    let mut res = vec![];
+    let mut byte = [0u8; 1];
+    while !res.ends_with(b"\r\n\r\n") {
+        stream.read_exact(&mut byte).expect("response head");
+        res.push(byte[0]);
+    }
+
+    let len: usize = header(&res, "Content-Length")
+        .expect("Content-Length")
+        .parse()
+        .unwrap();
+    let mut body = vec![0u8; len];
+    stream.read_exact(&mut body).expect("response body");
+    res.extend_from_slice(&body);
+    res
+}
+
+#[test]
+fn apache_bench_keep_alive_reuses_the_connection() {
+    let server = TestServer::start();
+    let mut stream = server.connect();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    // As sent by `ab -k`, one request after the other on the same socket.
+    for _ in 0..3 {
+        stream
+            .write_all(
+                b"GET /echo/ab HTTP/1.0\r\nConnection: Keep-Alive\r\nHost: localhost\r\n\
+                  User-Agent: ApacheBench/2.3\r\nAccept: */*\r\n\r\n",
+            )
+            .unwrap();
+
+        let res = read_response(&mut stream);
+        assert_eq!(status_line(&res), "HTTP/1.0 200 OK");
+        assert_eq!(header(&res, "Connection").as_deref(), Some("keep-alive"));
+        assert_eq!(body(&res), b"ab");
+    }
+}
+
+#[test]
+fn requests_after_connection_close_are_ignored() {
+    let server = TestServer::start();
+    let res = server.send_str(
+        "GET /echo/first HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n\
+         GET /echo/second HTTP/1.1\r\nHost: localhost\r\n\r\n",
+    );
+
+    assert!(res.ends_with("first"), "{}", res);
+}
+
+#[test]
+fn client_closing_mid_response_does_not_affect_the_server() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("big.bin"), vec![7u8; 8 * 1024 * 1024]).unwrap();
+    let server = TestServer::with_config(dir_config(&dir));
+
+    let mut stream = server.connect();
+    stream
+        .write_all(b"GET /files/big.bin HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let mut partial = [0u8; 64];
+    stream.read_exact(&mut partial).unwrap();
+    drop(stream);
+
+    let res = server.send(b"GET /echo/still-up HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+}
+
+#[test]
+fn expect_continue_gets_an_interim_response_first() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(dir_config(&dir));
+    let mut stream = server.connect();
+
+    stream
+        .write_all(
+            b"POST /files/a.txt HTTP/1.1\r\nHost: localhost\r\nExpect: 100-continue\r\n\
+              Content-Length: 3\r\nConnection: close\r\n\r\n",
+        )
+        .unwrap();
+
+    // The body is only sent once the server says so.
+    let interim = b"HTTP/1.1 100 Continue\r\n\r\n";
+    let mut buf = vec![0u8; interim.len()];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, interim);
+
+    stream.write_all(b"abc").unwrap();
+    let res = read_until_closed(&mut stream);
+
+    assert_eq!(status_line(&res), "HTTP/1.1 201 Created");
+    assert_eq!(fs::read(dir.path().join("a.txt")).unwrap(), b"abc");
+}
+
+#[test]
+fn expect_continue_with_oversized_body_is_refused() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(Config {
+        max_body_size: Some(10),
+        ..dir_config(&dir)
+    });
+    let mut stream = server.connect();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    stream
+        .write_all(
+            b"POST /files/a.txt HTTP/1.1\r\nHost: localhost\r\nExpect: 100-continue\r\n\
+              Content-Length: 11\r\n\r\n",
+        )
+        .unwrap();
+
+    // Refused right away, without a 100 Continue and without the body.
+    let res = read_until_closed(&mut stream);
+    assert_eq!(status_line(&res), "HTTP/1.1 413 Payload Too Large");
+    assert_eq!(header(&res, "Connection").as_deref(), Some("close"));
+    assert!(!dir.path().join("a.txt").exists());
+}
+
+#[test]
+fn unsupported_expectation_fails() {
+    let server = TestServer::start();
+
+    let res = server.send(
+        b"POST /echo/a HTTP/1.1\r\nHost: localhost\r\nExpect: 200-ok\r\nContent-Length: 3\r\n\r\nabc",
+    );
+
+    assert_eq!(status_line(&res), "HTTP/1.1 417 Expectation Failed");
+    assert_eq!(header(&res, "Connection").as_deref(), Some("close"));
+}
+
+#[test]
+fn expect_continue_is_ignored_for_http_1_0() {
+    let server = TestServer::start();
+
+    let res = server
+        .send(b"GET /echo/a HTTP/1.0\r\nExpect: 100-continue\r\nContent-Length: 3\r\n\r\nabc");
+
+    let text = String::from_utf8(res).unwrap();
+    assert!(text.starts_with("HTTP/1.0 200 OK\r\n"), "{}", text);
+    assert!(!text.contains("100 Continue"), "{}", text);
+}
+
+#[test]
+fn connection_closes_after_max_requests() {
+    let server = TestServer::with_config(Config {
+        keepalive_max_requests: Some(3),
+        ..Config::default()
+    });
+    let mut stream = server.connect();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    stream
+        .write_all(&b"GET /echo/a HTTP/1.1\r\nHost: localhost\r\n\r\n".repeat(4))
+        .unwrap();
+
+    let res = String::from_utf8(read_until_closed(&mut stream)).unwrap();
+    let responses: Vec<&str> = res.split("HTTP/1.1 ").skip(1).collect();
+
+    assert_eq!(responses.len(), 3, "{}", res);
+    assert!(!responses[1].contains("Connection: close"));
+    assert!(responses[2].contains("Connection: close\r\n"));
+}
+
+#[test]
+fn malformed_chunked_upload_is_answered_without_waiting_for_more() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(dir_config(&dir));
+    let mut stream = server.connect();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    // The write side is left open and nothing follows the malformed chunk
+    // size: the server must answer (and close) on its own, without waiting
+    // for more of the body.
+    stream
+        .write_all(
+            b"PUT /files/a.txt HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+              zz\r\n",
+        )
+        .unwrap();
+
+    let mut res = vec![];
+    stream
+        .read_to_end(&mut res)
+        .expect("server answers and closes the connection");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 400 Bad Request");
+    assert_eq!(header(&res, "Connection").as_deref(), Some("close"));
+}
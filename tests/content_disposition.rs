@@ -0,0 +1,43 @@
+mod common;
+
+use std::fs;
+
+use common::{body, dir_config, header, status_line, TempDir, TestServer};
+use pretty_assertions::assert_eq;
+
+fn get(server: &TestServer, path: &str) -> Vec<u8> {
+    server.send(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes())
+}
+
+#[test]
+fn download_is_an_attachment() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("report.csv"), "a,b\n1,2\n").unwrap();
+    let server = TestServer::with_config(dir_config(&dir));
+
+    let res = get(&server, "/files/report.csv?download=1");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(
+        header(&res, "Content-Disposition").as_deref(),
+        Some("attachment; filename=\"report.csv\"")
+    );
+    assert_eq!(body(&res), b"a,b\n1,2\n");
+}
+
+#[test]
+fn files_are_inline_by_default() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("report.csv"), "a,b\n1,2\n").unwrap();
+    let server = TestServer::with_config(dir_config(&dir));
+
+    for path in ["/files/report.csv", "/files/report.csv?download=0"] {
+        let res = get(&server, path);
+        assert_eq!(
+            header(&res, "Content-Disposition").as_deref(),
+            Some("inline; filename=\"report.csv\""),
+            "{}",
+            path
+        );
+    }
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use std::fs;
+
+use common::{dir_config, status_line, TempDir, TestServer};
+use pretty_assertions::assert_eq;
+
+fn upload(server: &TestServer, content_md5: &str, body: &str) -> Vec<u8> {
+    server.send(
+        format!(
+            "POST /files/upload.txt HTTP/1.1\r\nHost: localhost\r\nContent-MD5: {}\r\nContent-Length: {}\r\n\r\n{}",
+            content_md5,
+            body.len(),
+            body
+        )
+        .as_bytes(),
+    )
+}
+
+// base64 of the MD5 digest of "hello world".
+const HELLO_WORLD_MD5: &str = "XrY7u+Ae7tCTyyK7j1rNww==";
+
+#[test]
+fn upload_with_matching_digest_is_written() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = upload(&server, HELLO_WORLD_MD5, "hello world");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 201 Created");
+    assert_eq!(
+        fs::read_to_string(dir.path().join("upload.txt")).unwrap(),
+        "hello world"
+    );
+}
+
+#[test]
+fn upload_with_wrong_digest_is_rejected() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("upload.txt"), "previous").unwrap();
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = upload(&server, HELLO_WORLD_MD5, "hello w0rld");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 400 Bad Request");
+    // The previous contents are untouched and no temporary file is left.
+    assert_eq!(
+        fs::read_to_string(dir.path().join("upload.txt")).unwrap(),
+        "previous"
+    );
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
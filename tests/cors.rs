@@ -0,0 +1,116 @@
+mod common;
+
+use common::{header, status_line, TestServer};
+use http_server_starter_rust::{Config, Cors};
+use pretty_assertions::assert_eq;
+
+fn server(cors: Cors) -> TestServer {
+    TestServer::with_config(Config {
+        cors,
+        ..Config::default()
+    })
+}
+
+#[test]
+fn allow_all_adds_wildcard_origin_to_every_response() {
+    let server = server(Cors::AllowAll);
+
+    for path in ["/", "/echo/abc", "/unknown"] {
+        let res = server.send_str(&format!(
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nOrigin: http://example.test\r\n\r\n",
+            path
+        ));
+
+        assert_eq!(
+            header(res.as_bytes(), "Access-Control-Allow-Origin").as_deref(),
+            Some("*"),
+            "{}",
+            path
+        );
+    }
+}
+
+#[test]
+fn allow_all_accepts_preflight_from_any_origin() {
+    let server = server(Cors::AllowAll);
+
+    for origin in ["http://a.test", "https://b.test:8443"] {
+        let res = server.send_str(&format!(
+            "OPTIONS /files/x HTTP/1.1\r\nHost: localhost\r\nOrigin: {}\r\n\
+             Access-Control-Request-Method: POST\r\n\
+             Access-Control-Request-Headers: content-type\r\n\r\n",
+            origin
+        ));
+        let res = res.as_bytes();
+
+        assert_eq!(status_line(res), "HTTP/1.1 204 No Content");
+        assert_eq!(
+            header(res, "Access-Control-Allow-Origin").as_deref(),
+            Some("*")
+        );
+        assert_eq!(
+            header(res, "Access-Control-Allow-Methods").as_deref(),
+            Some("GET, HEAD, POST, PUT, DELETE, OPTIONS")
+        );
+        assert_eq!(
+            header(res, "Access-Control-Allow-Headers").as_deref(),
+            Some("content-type")
+        );
+    }
+}
+
+#[test]
+fn preflight_allows_the_methods_of_the_resource() {
+    let preflight = |server: &TestServer, path: &str| {
+        server.send_str(&format!(
+            "OPTIONS {} HTTP/1.1\r\nHost: localhost\r\nOrigin: http://a.test\r\n\
+             Access-Control-Request-Method: GET\r\n\r\n",
+            path
+        ))
+    };
+
+    let server = server(Cors::AllowAll);
+    let res = preflight(&server, "/echo/abc");
+    assert_eq!(
+        header(res.as_bytes(), "Access-Control-Allow-Methods").as_deref(),
+        Some("GET, HEAD, OPTIONS")
+    );
+
+    let res = preflight(&server, "/unknown");
+    assert_eq!(status_line(res.as_bytes()), "HTTP/1.1 404 Not Found");
+    assert_eq!(header(res.as_bytes(), "Access-Control-Allow-Methods"), None);
+
+    let server = TestServer::with_config(Config {
+        cors: Cors::AllowAll,
+        read_only: true,
+        ..Config::default()
+    });
+    let res = preflight(&server, "/files/x");
+    assert_eq!(
+        header(res.as_bytes(), "Access-Control-Allow-Methods").as_deref(),
+        Some("GET, HEAD, OPTIONS")
+    );
+}
+
+#[test]
+fn specific_origins_only_allow_listed_origin() {
+    let server = server(Cors::Origins(vec!["http://a.test".to_string()]));
+
+    let res = server.send_str("GET / HTTP/1.1\r\nHost: localhost\r\nOrigin: http://a.test\r\n\r\n");
+    assert_eq!(
+        header(res.as_bytes(), "Access-Control-Allow-Origin").as_deref(),
+        Some("http://a.test")
+    );
+    assert_eq!(header(res.as_bytes(), "Vary").as_deref(), Some("Origin"));
+
+    let res = server.send_str("GET / HTTP/1.1\r\nHost: localhost\r\nOrigin: http://b.test\r\n\r\n");
+    assert_eq!(header(res.as_bytes(), "Access-Control-Allow-Origin"), None);
+}
+
+#[test]
+fn disabled_by_default() {
+    let server = TestServer::start();
+    let res = server.send_str("GET / HTTP/1.1\r\nHost: localhost\r\nOrigin: http://a.test\r\n\r\n");
+
+    assert_eq!(header(res.as_bytes(), "Access-Control-Allow-Origin"), None);
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::{dir_config, header, status_line, TempDir, TestServer};
+use http_server_starter_rust::Config;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn configured_headers_are_added_to_every_response() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(Config {
+        headers: vec![
+            ("X-Content-Type-Options".to_string(), "nosniff".to_string()),
+            ("X-Frame-Options".to_string(), "DENY".to_string()),
+        ],
+        ..dir_config(&dir)
+    });
+
+    for (request, status) in [
+        (
+            "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n",
+            "HTTP/1.1 200 OK",
+        ),
+        (
+            "GET /echo/abc HTTP/1.1\r\nHost: localhost\r\n\r\n",
+            "HTTP/1.1 200 OK",
+        ),
+        (
+            "GET /files/missing HTTP/1.1\r\nHost: localhost\r\n\r\n",
+            "HTTP/1.1 404 Not Found",
+        ),
+        // Rejected before routing.
+        ("GET / HTTP/1.1\r\n\r\n", "HTTP/1.1 400 Bad Request"),
+    ] {
+        let res = server.send_str(request);
+
+        assert_eq!(status_line(res.as_bytes()), status, "{:?}", request);
+        assert_eq!(
+            header(res.as_bytes(), "X-Content-Type-Options").as_deref(),
+            Some("nosniff"),
+            "{:?}",
+            request
+        );
+        assert_eq!(
+            header(res.as_bytes(), "X-Frame-Options").as_deref(),
+            Some("DENY"),
+            "{:?}",
+            request
+        );
+    }
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use std::fs;
+
+use common::{dir_config, status_line, TempDir, TestServer};
+use http_server_starter_rust::Config;
+use pretty_assertions::assert_eq;
+
+fn delete(server: &TestServer, path: &str) -> Vec<u8> {
+    server.send(format!("DELETE {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes())
+}
+
+#[test]
+fn deletes_a_file() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("file.txt"), "contents").unwrap();
+    let server = TestServer::with_config(dir_config(&dir));
+
+    let res = delete(&server, "/files/file.txt");
+    assert_eq!(status_line(&res), "HTTP/1.1 204 No Content");
+    assert!(!dir.path().join("file.txt").exists());
+
+    let res = delete(&server, "/files/file.txt");
+    assert_eq!(status_line(&res), "HTTP/1.1 404 Not Found");
+}
+
+#[test]
+fn deletes_a_file_in_a_subdirectory() {
+    let dir = TempDir::new();
+    fs::create_dir(dir.path().join("docs")).unwrap();
+    fs::write(dir.path().join("docs").join("a.txt"), "contents").unwrap();
+    fs::write(dir.path().join("a.txt"), "top level").unwrap();
+    let server = TestServer::with_config(dir_config(&dir));
+
+    let res = delete(&server, "/files/docs/a.txt");
+    assert_eq!(status_line(&res), "HTTP/1.1 204 No Content");
+    assert!(!dir.path().join("docs").join("a.txt").exists());
+    assert!(dir.path().join("docs").is_dir());
+    assert!(dir.path().join("a.txt").exists());
+}
+
+#[test]
+fn does_not_delete_outside_the_directory() {
+    let dir = TempDir::new();
+    fs::create_dir(dir.path().join("root")).unwrap();
+    fs::create_dir(dir.path().join("root/sub")).unwrap();
+    fs::write(dir.path().join("outside.txt"), "keep").unwrap();
+    let server = TestServer::with_config(Config {
+        directory: Some(dir.path().join("root").to_string_lossy().to_string()),
+        ..Config::default()
+    });
+
+    for path in ["/files/..", "/files/.", "/files/", "/files/../outside.txt"] {
+        let res = delete(&server, path);
+        assert_ne!(status_line(&res), "HTTP/1.1 204 No Content", "{}", path);
+    }
+    // Directories aren't removed either.
+    let res = delete(&server, "/files/sub");
+    assert_eq!(status_line(&res), "HTTP/1.1 403 Forbidden");
+
+    assert!(dir.path().join("outside.txt").exists());
+    assert!(dir.path().join("root/sub").exists());
+}
+
+#[test]
+fn read_only_forbids_deleting() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("file.txt"), "contents").unwrap();
+    let server = TestServer::with_config(Config {
+        read_only: true,
+        ..dir_config(&dir)
+    });
+
+    let res = delete(&server, "/files/file.txt");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 403 Forbidden");
+    assert!(dir.path().join("file.txt").exists());
+}
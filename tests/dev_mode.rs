@@ -0,0 +1,87 @@
+mod common;
+
+use common::{body, status_line, TestServer};
+use http_server_starter_rust::{json, Config};
+use pretty_assertions::assert_eq;
+
+fn dev_server() -> TestServer {
+    TestServer::with_config(Config {
+        dev: true,
+        ..Config::default()
+    })
+}
+
+fn not_found_body(server: &TestServer, path: &str) -> String {
+    let res = server.send(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes());
+    assert_eq!(status_line(&res), "HTTP/1.1 404 Not Found");
+    String::from_utf8(body(&res)).unwrap()
+}
+
+#[test]
+fn not_found_suggests_similar_routes() {
+    let server = dev_server();
+
+    assert_eq!(
+        not_found_body(&server, "/eco/foo"),
+        "No route matches /eco/foo.\nDid you mean: /echo/\n"
+    );
+    assert!(not_found_body(&server, "/user").contains("Did you mean: /user-agent"));
+    assert!(not_found_body(&server, "/fils/a.txt").contains("Did you mean: /files/"));
+}
+
+#[test]
+fn not_found_without_similar_routes() {
+    let server = dev_server();
+
+    assert_eq!(
+        not_found_body(&server, "/completely-different"),
+        "No route matches /completely-different.\n"
+    );
+}
+
+#[test]
+fn not_found_is_terse_outside_dev_mode() {
+    let server = TestServer::start();
+
+    assert_eq!(not_found_body(&server, "/eco/foo"), "");
+}
+
+#[test]
+fn debug_headers_echoes_parsed_headers() {
+    let server = dev_server();
+
+    let res = server.send(
+        b"GET /debug/headers HTTP/1.1\r\n\
+          Host: localhost\r\n\
+          X-Custom: value\r\n\
+          Accept: text/html\r\n\
+          Accept: application/json\r\n\
+          x-custom: lowercase\r\n\
+          \r\n",
+    );
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+
+    let headers = json::parse(&String::from_utf8(body(&res)).unwrap()).unwrap();
+    let values = |name: &str| -> Vec<String> {
+        headers
+            .get(name)
+            .and_then(|v| v.as_array())
+            .unwrap_or_else(|| panic!("missing {}", name))
+            .iter()
+            .map(|v| v.as_str().unwrap().to_string())
+            .collect()
+    };
+
+    assert_eq!(values("Host"), ["localhost"]);
+    assert_eq!(values("X-Custom"), ["value"]);
+    assert_eq!(values("Accept"), ["text/html", "application/json"]);
+    assert_eq!(values("x-custom"), ["lowercase"]);
+}
+
+#[test]
+fn debug_headers_is_not_found_outside_dev_mode() {
+    let server = TestServer::start();
+
+    let res = server.send(b"GET /debug/headers HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(status_line(&res), "HTTP/1.1 404 Not Found");
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use std::fs;
+
+use common::{body, dir_config, header, status_line, TempDir, TestServer};
+use http_server_starter_rust::json::{self, Value};
+use pretty_assertions::assert_eq;
+
+fn names(listing: &Value) -> Vec<String> {
+    listing
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            entry
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap()
+                .to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn lists_directory_as_json() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("b.txt"), "hello").unwrap();
+    fs::create_dir(dir.path().join("a-dir")).unwrap();
+
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = server.send(b"GET /files/ HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(
+        header(&res, "Content-Type").as_deref(),
+        Some("application/json")
+    );
+
+    let listing = json::parse(&String::from_utf8(body(&res)).unwrap()).unwrap();
+    assert_eq!(names(&listing), vec!["a-dir", "b.txt"]);
+
+    let file = &listing.as_array().unwrap()[1];
+    assert_eq!(file.get("type").and_then(Value::as_str), Some("file"));
+    assert_eq!(file.get("size").and_then(Value::as_f64), Some(5.0));
+}
+
+#[test]
+fn escapes_special_characters_in_names() {
+    let dir = TempDir::new();
+    let name = "quote\"and\\backslash.txt";
+    fs::write(dir.path().join(name), "").unwrap();
+
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = server.send(b"GET /files/ HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    let body = String::from_utf8(body(&res)).unwrap();
+    let listing = json::parse(&body).unwrap_or_else(|e| panic!("{}: {}", e, body));
+    assert_eq!(names(&listing), vec![name]);
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use std::fs;
+
+use common::{dir_config, TempDir, TestServer};
+use http_server_starter_rust::{Config, TrailingSlash};
+use pretty_assertions::assert_eq;
+
+fn hinted_server(dir: &TempDir) -> TestServer {
+    fs::write(dir.path().join("index.html"), "<html></html>").unwrap();
+
+    TestServer::with_config(Config {
+        early_hints: vec![
+            (
+                "/files/index.html".to_string(),
+                "</files/style.css>; rel=preload; as=style".to_string(),
+            ),
+            (
+                "/files/index.html".to_string(),
+                "</files/app.js>; rel=preload; as=script".to_string(),
+            ),
+        ],
+        ..dir_config(dir)
+    })
+}
+
+#[test]
+fn early_hints_precede_the_final_response() {
+    let dir = TempDir::new();
+    let server = hinted_server(&dir);
+    let res = server.send_str("GET /files/index.html HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    let (interim, last) = res.split_once("\r\n\r\n").unwrap();
+    assert_eq!(
+        interim,
+        "HTTP/1.1 103 Early Hints\r\n\
+         Link: </files/style.css>; rel=preload; as=style\r\n\
+         Link: </files/app.js>; rel=preload; as=script"
+    );
+    assert!(last.starts_with("HTTP/1.1 200 OK\r\n"), "{}", last);
+    assert!(last.ends_with("<html></html>"), "{}", last);
+}
+
+#[test]
+fn routes_without_hints_get_only_the_final_response() {
+    let dir = TempDir::new();
+    let server = hinted_server(&dir);
+    let res = server.send_str("GET /echo/abc HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert!(res.starts_with("HTTP/1.1 200 OK\r\n"), "{}", res);
+    assert!(!res.contains("103"), "{}", res);
+}
+
+#[test]
+fn hints_match_the_path_after_normalization() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("index.html"), "<html></html>").unwrap();
+    let server = TestServer::with_config(Config {
+        base_path: Some("/app".to_string()),
+        trailing_slash: TrailingSlash::Ignore,
+        early_hints: vec![(
+            "/files/index.html".to_string(),
+            "</app/files/style.css>; rel=preload; as=style".to_string(),
+        )],
+        ..dir_config(&dir)
+    });
+
+    let res = server.send_str("GET /app/files/index.html HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert!(res.starts_with("HTTP/1.1 103 Early Hints\r\n"), "{}", res);
+    assert!(res.contains("\r\n\r\nHTTP/1.1 200 OK\r\n"), "{}", res);
+
+    let res = server.send_str("GET /app/files/index.html/ HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert!(res.starts_with("HTTP/1.1 103 Early Hints\r\n"), "{}", res);
+
+    // Outside of the base path, the hinted path isn't routed.
+    let res = server.send_str("GET /files/index.html HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert!(res.starts_with("HTTP/1.1 404 Not Found\r\n"), "{}", res);
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::{header, status_line, TestServer};
+use http_server_starter_rust::Config;
+use pretty_assertions::assert_eq;
+
+fn echo_server() -> TestServer {
+    TestServer::with_config(Config {
+        echo_request: true,
+        ..Config::default()
+    })
+}
+
+#[test]
+fn echoes_the_request_method_and_path() {
+    let server = echo_server();
+    let res = server.send(b"GET /echo/abc?x=1 HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(header(&res, "X-Echo-Method").as_deref(), Some("GET"));
+    assert_eq!(
+        header(&res, "X-Echo-Path").as_deref(),
+        Some("/echo/abc?x=1")
+    );
+}
+
+#[test]
+fn echoes_requests_which_fail() {
+    let server = echo_server();
+    let res = server.send(b"DELETE /missing HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 404 Not Found");
+    assert_eq!(header(&res, "X-Echo-Method").as_deref(), Some("DELETE"));
+    assert_eq!(header(&res, "X-Echo-Path").as_deref(), Some("/missing"));
+}
+
+#[test]
+fn not_echoed_by_default() {
+    let server = TestServer::start();
+    let res = server.send(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(header(&res, "X-Echo-Method"), None);
+    assert_eq!(header(&res, "X-Echo-Path"), None);
+}
@@ -0,0 +1,212 @@
+mod common;
+
+use std::fs;
+
+use common::{body, dir_config, header, status_line, TempDir, TestServer};
+use http_server_starter_rust::json;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn get_root() {
+    let server = TestServer::start();
+    let res = server.send(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(body(&res), b"");
+}
+
+#[test]
+fn get_echo() {
+    let server = TestServer::start();
+    let res = server.send(b"GET /echo/abc HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(
+        header(&res, "Content-Type").as_deref(),
+        Some("text/plain; charset=utf-8")
+    );
+    assert_eq!(header(&res, "Content-Length").as_deref(), Some("3"));
+    assert_eq!(body(&res), b"abc");
+}
+
+#[test]
+fn get_user_agent() {
+    let server = TestServer::start();
+    let res = server
+        .send(b"GET /user-agent HTTP/1.1\r\nHost: localhost\r\nUser-Agent: foobar/1.2.3\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(
+        header(&res, "Content-Type").as_deref(),
+        Some("text/plain; charset=utf-8")
+    );
+    assert_eq!(body(&res), b"foobar/1.2.3");
+}
+
+#[test]
+fn get_version() {
+    let server = TestServer::start();
+    let res = server.send(b"GET /version HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(
+        header(&res, "Content-Type").as_deref(),
+        Some("application/json")
+    );
+
+    let info = json::parse(&String::from_utf8(body(&res)).unwrap()).unwrap();
+    assert_eq!(
+        info.get("version").and_then(|v| v.as_str()),
+        Some(env!("CARGO_PKG_VERSION"))
+    );
+}
+
+#[test]
+fn get_unknown_route() {
+    let server = TestServer::start();
+    let res = server.send(b"GET /unknown HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 404 Not Found");
+}
+
+#[test]
+fn get_file() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("hello.txt"), "Hello, World!").unwrap();
+
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = server.send(b"GET /files/hello.txt HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(
+        header(&res, "Content-Type").as_deref(),
+        Some("application/octet-stream")
+    );
+    assert_eq!(body(&res), b"Hello, World!");
+}
+
+#[test]
+fn get_file_in_subdirectory() {
+    let dir = TempDir::new();
+    fs::create_dir(dir.path().join("docs")).unwrap();
+    fs::write(dir.path().join("docs").join("a.txt"), "nested").unwrap();
+
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = server.send(b"GET /files/docs/a.txt HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(body(&res), b"nested");
+
+    let res = server.send(b"GET /files/docs/missing.txt HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(status_line(&res), "HTTP/1.1 404 Not Found");
+}
+
+#[test]
+fn get_missing_file() {
+    let dir = TempDir::new();
+
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = server.send(b"GET /files/missing.txt HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 404 Not Found");
+}
+
+#[test]
+fn post_file() {
+    let dir = TempDir::new();
+
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = server
+        .send(b"POST /files/new.txt HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 201 Created");
+    assert_eq!(header(&res, "Location").as_deref(), Some("/files/new.txt"));
+    assert_eq!(fs::read(dir.path().join("new.txt")).unwrap(), b"hello");
+}
+
+#[test]
+fn post_file_in_subdirectory() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(dir_config(&dir));
+    let post = |target: &str| {
+        server.send_str(&format!(
+            "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello",
+            target
+        ))
+    };
+
+    // The directory has to exist, and nothing is written on the way.
+    let res = post("/files/new/b.txt");
+    assert_eq!(status_line(res.as_bytes()), "HTTP/1.1 404 Not Found");
+    assert!(!dir.path().join("new").exists());
+
+    fs::create_dir(dir.path().join("new")).unwrap();
+    let res = post("/files/new/b.txt");
+    assert_eq!(status_line(res.as_bytes()), "HTTP/1.1 201 Created");
+    assert_eq!(
+        header(res.as_bytes(), "Location").as_deref(),
+        Some("/files/new/b.txt")
+    );
+    assert_eq!(
+        fs::read(dir.path().join("new").join("b.txt")).unwrap(),
+        b"hello"
+    );
+}
+
+#[test]
+fn connection_closed_without_request_gets_no_response() {
+    let server = TestServer::start();
+
+    // Connect and immediately close, like a health-check probe.
+    let res = server.send(b"");
+    assert_eq!(res, b"");
+
+    // The server is still serving other clients.
+    let res = server.send(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+}
+
+#[test]
+fn post_large_binary_file() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(dir_config(&dir));
+
+    // Bigger than any of the server's buffers, and not valid UTF-8.
+    let data: Vec<u8> = (0..8 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let mut req = format!(
+        "POST /files/large.bin HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n",
+        data.len()
+    )
+    .into_bytes();
+    req.extend_from_slice(&data);
+
+    let res = server.send(&req);
+
+    assert_eq!(status_line(&res), "HTTP/1.1 201 Created");
+    assert!(fs::read(dir.path().join("large.bin")).unwrap() == data);
+}
+
+#[test]
+fn binary_body_on_a_buffered_route() {
+    let server = TestServer::start();
+
+    let mut req =
+        b"GET /echo/abc HTTP/1.1\r\nHost: localhost\r\nContent-Length: 4\r\n\r\n".to_vec();
+    req.extend_from_slice(b"\xff\x00\xfe\x01");
+    let res = server.send(&req);
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(body(&res), b"abc");
+}
+
+#[test]
+fn get_echo_multibyte_utf8() {
+    let server = TestServer::start();
+    let res = server.send("GET /echo/héllo-wörld-✓ HTTP/1.1\r\nHost: localhost\r\n\r\n".as_bytes());
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(
+        header(&res, "Content-Type").as_deref(),
+        Some("text/plain; charset=utf-8")
+    );
+    assert_eq!(body(&res), "héllo-wörld-✓".as_bytes());
+}
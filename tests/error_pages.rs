@@ -0,0 +1,57 @@
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use common::{body, header, status_line, TempDir, TestServer};
+use http_server_starter_rust::{Config, Server};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn custom_404_page_is_served_on_missing_route() {
+    let dir = TempDir::new();
+    let page = dir.path().join("404.html");
+    fs::write(&page, "<h1>Nothing to see here</h1>").unwrap();
+
+    let server = TestServer::with_config(Config {
+        error_pages: vec![(404, page)],
+        ..Config::default()
+    });
+    let res = server.send(b"GET /missing HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 404 Not Found");
+    assert_eq!(
+        header(&res, "Content-Type").as_deref(),
+        Some("text/html; charset=utf-8")
+    );
+    assert_eq!(body(&res), b"<h1>Nothing to see here</h1>");
+}
+
+#[test]
+fn other_statuses_keep_the_default_body() {
+    let dir = TempDir::new();
+    let page = dir.path().join("500.html");
+    fs::write(&page, "<h1>Oops</h1>").unwrap();
+
+    let server = TestServer::with_config(Config {
+        error_pages: vec![(500, page)],
+        ..Config::default()
+    });
+    let res = server.send(b"GET /missing HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 404 Not Found");
+    assert_eq!(body(&res), b"");
+}
+
+#[test]
+fn missing_error_page_fails_at_startup() {
+    let config = Config {
+        error_pages: vec![(404, PathBuf::from("/nonexistent/404.html"))],
+        ..Config::default()
+    };
+
+    let err = Server::bind("127.0.0.1:0", config)
+        .err()
+        .expect("bind error");
+    assert!(err.to_string().contains("404 error page"), "{}", err);
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use std::fs;
+
+use common::{body, dir_config, status_line, TempDir, TestServer};
+use http_server_starter_rust::Config;
+use pretty_assertions::assert_eq;
+
+fn get(server: &TestServer, path: &str) -> Vec<u8> {
+    server.send(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes())
+}
+
+fn files() -> TempDir {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("index.html"), "<h1>hi</h1>").unwrap();
+    fs::write(dir.path().join("style.css"), "h1 {}").unwrap();
+    fs::write(dir.path().join(".env"), "SECRET=1").unwrap();
+    fs::write(dir.path().join("server.key"), "-----BEGIN").unwrap();
+    dir
+}
+
+#[test]
+fn denied_extensions_are_forbidden() {
+    let dir = files();
+    let server = TestServer::with_config(Config {
+        denied_extensions: vec!["env".to_string(), "key".to_string()],
+        ..dir_config(&dir)
+    });
+
+    for path in ["/files/.env", "/files/server.key"] {
+        let res = get(&server, path);
+        assert_eq!(status_line(&res), "HTTP/1.1 403 Forbidden", "{}", path);
+        assert!(body(&res).is_empty());
+    }
+
+    let res = get(&server, "/files/style.css");
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+}
+
+#[test]
+fn only_allowed_extensions_are_served() {
+    let dir = files();
+    let server = TestServer::with_config(Config {
+        allowed_extensions: Some(vec!["html".to_string()]),
+        ..dir_config(&dir)
+    });
+
+    let res = get(&server, "/files/index.html");
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(body(&res), b"<h1>hi</h1>");
+
+    let res = get(&server, "/files/style.css");
+    assert_eq!(status_line(&res), "HTTP/1.1 403 Forbidden");
+
+    // Index files are subject to the same rules.
+    let res = get(&server, "/files/");
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(body(&res), b"<h1>hi</h1>");
+}
+
+#[test]
+fn files_with_disallowed_extensions_are_not_written() {
+    let dir = files();
+    let server = TestServer::with_config(Config {
+        denied_extensions: vec!["env".to_string()],
+        allowed_extensions: Some(vec!["env".to_string(), "txt".to_string()]),
+        ..dir_config(&dir)
+    });
+
+    for method in ["POST", "PUT"] {
+        for name in [".env", "new.env", "style.css"] {
+            let res = server.send(
+                format!(
+                    "{} /files/{} HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello",
+                    method, name
+                )
+                .as_bytes(),
+            );
+            assert_eq!(
+                status_line(&res),
+                "HTTP/1.1 403 Forbidden",
+                "{} {}",
+                method,
+                name
+            );
+        }
+    }
+    assert_eq!(
+        fs::read_to_string(dir.path().join(".env")).unwrap(),
+        "SECRET=1"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("style.css")).unwrap(),
+        "h1 {}"
+    );
+    assert!(!dir.path().join("new.env").exists());
+
+    let res = server
+        .send(b"POST /files/new.txt HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello");
+    assert_eq!(status_line(&res), "HTTP/1.1 201 Created");
+}
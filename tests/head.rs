@@ -0,0 +1,52 @@
+mod common;
+
+use std::fs;
+
+use common::{body, dir_config, header, status_line, TempDir, TestServer};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn head_has_the_get_headers_without_the_body() {
+    let server = TestServer::start();
+
+    let get = server.send(b"GET /echo/hello HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    let head = server.send(b"HEAD /echo/hello HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&head), "HTTP/1.1 200 OK");
+    assert_eq!(header(&head, "Content-Length").as_deref(), Some("5"));
+    assert_eq!(header(&head, "Content-Type"), header(&get, "Content-Type"));
+    assert_eq!(body(&head), b"");
+}
+
+#[test]
+fn head_of_a_file() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("file.txt"), "file contents").unwrap();
+    let server = TestServer::with_config(dir_config(&dir));
+
+    let get = server.send(b"GET /files/file.txt HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    let head = server.send(b"HEAD /files/file.txt HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&head), "HTTP/1.1 200 OK");
+    for name in ["Content-Length", "ETag", "Last-Modified", "Cache-Control"] {
+        assert_eq!(header(&head, name), header(&get, name), "{}", name);
+    }
+    assert_eq!(body(&head), b"");
+
+    let res = server.send(b"HEAD /files/missing.txt HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(status_line(&res), "HTTP/1.1 404 Not Found");
+}
+
+#[test]
+fn head_keeps_the_connection_in_sync() {
+    let server = TestServer::start();
+
+    let res = server.send(
+        b"HEAD /echo/first HTTP/1.1\r\nHost: localhost\r\n\r\n\
+          GET /echo/second HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    );
+
+    let text = String::from_utf8(res).unwrap();
+    assert_eq!(text.matches("HTTP/1.1 200 OK").count(), 2, "{}", text);
+    assert!(text.ends_with("\r\n\r\nsecond"), "{}", text);
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use common::{status_line, TestServer};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn http_1_1_without_host_is_a_bad_request() {
+    let server = TestServer::start();
+    let res = server.send(b"GET / HTTP/1.1\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 400 Bad Request");
+}
+
+#[test]
+fn http_1_1_with_host_is_ok() {
+    let server = TestServer::start();
+    let res = server.send(b"GET / HTTP/1.1\r\nHost: localhost:4221\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+}
+
+#[test]
+fn http_1_1_with_two_hosts_is_a_bad_request() {
+    let server = TestServer::start();
+    let res = server.send(b"GET / HTTP/1.1\r\nHost: a.test\r\nHost: b.test\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 400 Bad Request");
+}
+
+#[test]
+fn http_1_0_without_host_is_ok() {
+    let server = TestServer::start();
+    let res = server.send(b"GET / HTTP/1.0\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.0 200 OK");
+}
+
+#[test]
+fn malformed_host_is_a_bad_request() {
+    let server = TestServer::start();
+
+    for host in ["local host", "localhost:port", "localhost:99999", "[::1"] {
+        let res = server.send_str(&format!("GET / HTTP/1.1\r\nHost: {}\r\n\r\n", host));
+        assert_eq!(
+            status_line(res.as_bytes()),
+            "HTTP/1.1 400 Bad Request",
+            "{}",
+            host
+        );
+    }
+
+    let res = server.send(b"GET / HTTP/1.0\r\nHost: local host\r\n\r\n");
+    assert_eq!(status_line(&res), "HTTP/1.1 400 Bad Request");
+}
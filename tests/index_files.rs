@@ -0,0 +1,76 @@
+mod common;
+
+use std::fs;
+
+use common::{body, dir_config, header, status_line, TempDir, TestServer};
+use http_server_starter_rust::Config;
+use pretty_assertions::assert_eq;
+
+fn index_config(dir: &TempDir, index_files: &[&str]) -> Config {
+    Config {
+        index_files: index_files.iter().map(|name| name.to_string()).collect(),
+        ..dir_config(dir)
+    }
+}
+
+#[test]
+fn serves_first_existing_index_file() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("index.htm"), "htm index").unwrap();
+    fs::write(dir.path().join("default.html"), "default index").unwrap();
+
+    let server = TestServer::with_config(index_config(
+        &dir,
+        &["index.html", "index.htm", "default.html"],
+    ));
+    let res = server.send(b"GET /files/ HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(body(&res), b"htm index");
+}
+
+#[test]
+fn lists_directory_when_no_index_file_is_configured() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("index.htm"), "htm index").unwrap();
+
+    let server = TestServer::with_config(index_config(&dir, &["index.html"]));
+    let res = server.send(b"GET /files/ HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(
+        header(&res, "Content-Type").as_deref(),
+        Some("application/json")
+    );
+}
+
+#[test]
+fn serves_index_file_of_subdirectory() {
+    let dir = TempDir::new();
+    fs::create_dir(dir.path().join("docs")).unwrap();
+    fs::write(dir.path().join("docs").join("index.html"), "docs index").unwrap();
+
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = server.send(b"GET /files/docs HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(body(&res), b"docs index");
+}
+
+#[test]
+fn serves_index_file_of_nested_subdirectory() {
+    let dir = TempDir::new();
+    fs::create_dir_all(dir.path().join("docs").join("api")).unwrap();
+    fs::write(dir.path().join("docs").join("index.html"), "docs index").unwrap();
+    fs::write(
+        dir.path().join("docs").join("api").join("index.html"),
+        "api index",
+    )
+    .unwrap();
+
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = server.send(b"GET /files/docs/api/ HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(body(&res), b"api index");
+}
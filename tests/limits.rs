@@ -0,0 +1,213 @@
+mod common;
+
+use common::{dir_config, status_line, TempDir, TestServer};
+use http_server_starter_rust::{gzip, Config};
+use pretty_assertions::assert_eq;
+
+fn request_with_headers(count: usize) -> String {
+    let mut req = "GET / HTTP/1.1\r\nHost: localhost\r\n".to_string();
+    for i in 0..count {
+        req.push_str(&format!("X-Header-{}: {}\r\n", i, i));
+    }
+    req.push_str("\r\n");
+    req
+}
+
+#[test]
+fn too_many_headers_is_rejected() {
+    let server = TestServer::start();
+    let res = server.send_str(&request_with_headers(200));
+
+    assert_eq!(
+        status_line(res.as_bytes()),
+        "HTTP/1.1 431 Request Header Fields Too Large"
+    );
+}
+
+#[test]
+fn headers_within_the_limit_are_accepted() {
+    let server = TestServer::start();
+    // The Host header counts too.
+    let res = server.send_str(&request_with_headers(99));
+
+    assert_eq!(status_line(res.as_bytes()), "HTTP/1.1 200 OK");
+}
+
+#[test]
+fn max_headers_is_configurable() {
+    let server = TestServer::with_config(Config {
+        max_headers: 5,
+        ..Config::default()
+    });
+    let res = server.send_str(&request_with_headers(5));
+
+    assert_eq!(
+        status_line(res.as_bytes()),
+        "HTTP/1.1 431 Request Header Fields Too Large"
+    );
+}
+
+#[test]
+fn too_long_header_line_is_rejected() {
+    let server = TestServer::with_config(Config {
+        max_header_line_length: 64,
+        ..Config::default()
+    });
+
+    let res = server.send_str(&format!(
+        "GET / HTTP/1.1\r\nHost: localhost\r\nX-Long: {}\r\n\r\n",
+        "a".repeat(56)
+    ));
+    assert_eq!(status_line(res.as_bytes()), "HTTP/1.1 200 OK");
+
+    let res = server.send_str(&format!(
+        "GET / HTTP/1.1\r\nHost: localhost\r\nX-Long: {}\r\n\r\n",
+        "a".repeat(57)
+    ));
+    assert_eq!(
+        status_line(res.as_bytes()),
+        "HTTP/1.1 431 Request Header Fields Too Large"
+    );
+}
+
+#[test]
+fn too_large_headers_are_rejected() {
+    let server = TestServer::with_config(Config {
+        max_header_size: 1024,
+        ..Config::default()
+    });
+    // Up to 17 bytes per header line, under the header count limit.
+    let res = server.send_str(&request_with_headers(80));
+
+    assert_eq!(
+        status_line(res.as_bytes()),
+        "HTTP/1.1 431 Request Header Fields Too Large"
+    );
+
+    let res = server.send_str(&request_with_headers(20));
+    assert_eq!(status_line(res.as_bytes()), "HTTP/1.1 200 OK");
+}
+
+#[test]
+fn too_long_uri_is_rejected() {
+    let server = TestServer::start();
+    let res = server.send_str(&format!(
+        "GET /echo/{} HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        "a".repeat(10 * 1024)
+    ));
+
+    assert_eq!(status_line(res.as_bytes()), "HTTP/1.1 414 URI Too Long");
+}
+
+#[test]
+fn max_uri_length_is_configurable() {
+    let server = TestServer::with_config(Config {
+        max_uri_length: 16,
+        ..Config::default()
+    });
+
+    let res = server.send_str("GET /echo/0123456789 HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(status_line(res.as_bytes()), "HTTP/1.1 200 OK");
+
+    let res = server.send_str("GET /echo/0123456789a HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(status_line(res.as_bytes()), "HTTP/1.1 414 URI Too Long");
+}
+
+fn limited_server(dir: &TempDir) -> TestServer {
+    TestServer::with_config(Config {
+        max_body_size: Some(10),
+        ..dir_config(dir)
+    })
+}
+
+fn chunked(body: &[u8]) -> Vec<u8> {
+    let mut out = format!("{:x}\r\n", body.len()).into_bytes();
+    out.extend_from_slice(body);
+    out.extend_from_slice(b"\r\n0\r\n\r\n");
+    out
+}
+
+#[test]
+fn declared_body_over_the_limit_is_refused() {
+    let dir = TempDir::new();
+    let server = limited_server(&dir);
+
+    let res = server.send_str(&format!(
+        "POST /files/a.txt HTTP/1.1\r\nHost: localhost\r\nContent-Length: 11\r\n\r\n{}",
+        "a".repeat(11)
+    ));
+    assert_eq!(
+        status_line(res.as_bytes()),
+        "HTTP/1.1 413 Payload Too Large"
+    );
+    assert!(res.contains("Connection: close\r\n"), "{}", res);
+    assert!(!dir.path().join("a.txt").exists());
+
+    let res = server.send_str(&format!(
+        "POST /files/a.txt HTTP/1.1\r\nHost: localhost\r\nContent-Length: 10\r\n\r\n{}",
+        "a".repeat(10)
+    ));
+    assert_eq!(status_line(res.as_bytes()), "HTTP/1.1 201 Created");
+}
+
+#[test]
+fn chunked_body_over_the_limit_is_refused() {
+    let dir = TempDir::new();
+    let server = limited_server(&dir);
+
+    for target in ["/files/a.txt", "/echo/a"] {
+        let mut raw = format!(
+            "POST {} HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n",
+            target
+        )
+        .into_bytes();
+        raw.extend_from_slice(&chunked(&[b'a'; 11]));
+        let res = server.send(&raw);
+
+        assert_eq!(
+            status_line(&res),
+            "HTTP/1.1 413 Payload Too Large",
+            "{}",
+            target
+        );
+    }
+    assert!(!dir.path().join("a.txt").exists());
+}
+
+#[test]
+fn decompressed_body_over_the_limit_is_refused() {
+    let dir = TempDir::new();
+    let server = limited_server(&dir);
+
+    // Compresses to fewer bytes than the limit.
+    let compressed = gzip::encode(&[b'a'; 1000], 6);
+    let mut raw = b"POST /files/a.txt HTTP/1.1\r\nHost: localhost\r\n\
+                    Transfer-Encoding: gzip, chunked\r\n\r\n"
+        .to_vec();
+    raw.extend_from_slice(&chunked(&compressed));
+    let res = server.send(&raw);
+
+    assert_eq!(status_line(&res), "HTTP/1.1 413 Payload Too Large");
+}
+
+#[test]
+fn gzip_bomb_is_refused_while_decompressing() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(Config {
+        max_body_size: Some(64 * 1024),
+        ..dir_config(&dir)
+    });
+
+    // 8 MiB of zeros compress to a few KiB, well under the limit.
+    let compressed = gzip::encode(&vec![0u8; 8 * 1024 * 1024], 6);
+    assert!(compressed.len() < 64 * 1024);
+
+    let mut raw = b"POST /files/bomb.bin HTTP/1.1\r\nHost: localhost\r\n\
+                    Transfer-Encoding: gzip, chunked\r\n\r\n"
+        .to_vec();
+    raw.extend_from_slice(&chunked(&compressed));
+    let res = server.send(&raw);
+
+    assert_eq!(status_line(&res), "HTTP/1.1 413 Payload Too Large");
+    assert!(!dir.path().join("bomb.bin").exists());
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use common::{status_line, TestServer};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn malformed_requests_are_a_bad_request() {
+    let server = TestServer::start();
+
+    for raw in [
+        &b"GET /\r\n\r\n"[..],
+        b"GET / HTTP/1.1 extra\r\nHost: localhost\r\n\r\n",
+        b" / HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        b"G(T / HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        b"GET / FOO\r\n\r\n",
+        b"GET / HTTP/1\r\n\r\n",
+        b"\xff\xfe\x00\x01\r\n\r\n",
+        b"GET / HTTP/1.1\nHost: localhost\n\n",
+        b"GET / HTTP/1.1\r\nHost: localhost\r\nno colon\r\n\r\n",
+        b"GET / HTTP/1.1\r\nHost: localhost\r\nX-Space : value\r\n\r\n",
+        b"GET / HTTP/1.1\r\nHost: localhost\r\nX-Folded: a\r\n b\r\n\r\n",
+        b"GET / HTTP/1.1\r\nHost: localhost\r\nX-Binary: \xff\r\n\r\n",
+    ] {
+        let res = server.send(raw);
+
+        assert_eq!(status_line(&res), "HTTP/1.1 400 Bad Request", "{:?}", raw);
+        assert!(
+            String::from_utf8_lossy(&res).contains("Connection: close\r\n"),
+            "{:?}",
+            raw
+        );
+    }
+
+    // The server is still up.
+    let res = server.send(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+}
+
+#[test]
+fn unsupported_major_version_is_refused() {
+    let server = TestServer::start();
+    let res = server.send(b"GET / HTTP/2.0\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 505 HTTP Version Not Supported");
+}
+
+#[test]
+fn higher_minor_version_is_answered_as_http_1_1() {
+    let server = TestServer::start();
+    let res = server.send(b"GET / HTTP/1.2\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+}
+
+#[test]
+fn header_without_space_after_colon_is_parsed() {
+    let server = TestServer::start();
+    let res = server
+        .send(b"GET /user-agent HTTP/1.1\r\nHost:localhost\r\nUser-Agent:\tcurl/8.0 \r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert!(
+        res.ends_with(b"\r\n\r\ncurl/8.0"),
+        "{:?}",
+        String::from_utf8_lossy(&res)
+    );
+}
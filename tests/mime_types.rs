@@ -0,0 +1,73 @@
+mod common;
+
+use std::fs;
+
+use common::{dir_config, header, status_line, TempDir, TestServer};
+use http_server_starter_rust::Config;
+use pretty_assertions::assert_eq;
+
+fn content_type(server: &TestServer, path: &str) -> Option<String> {
+    let res = server.send(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes());
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    header(&res, "Content-Type")
+}
+
+#[test]
+fn serves_files_with_configured_mime_types() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("module.wasm"), b"\0asm").unwrap();
+    fs::write(dir.path().join("data.bin"), b"data").unwrap();
+
+    let server = TestServer::with_config(Config {
+        mime_types: vec![("wasm".to_string(), "application/wasm".to_string())],
+        ..dir_config(&dir)
+    });
+
+    assert_eq!(
+        content_type(&server, "/files/module.wasm").as_deref(),
+        Some("application/wasm")
+    );
+    assert_eq!(
+        content_type(&server, "/files/data.bin").as_deref(),
+        Some("application/octet-stream")
+    );
+}
+
+#[test]
+fn sniffs_files_without_an_extension() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("logo"), b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+    fs::write(dir.path().join("LICENSE"), "MIT License\n").unwrap();
+    fs::write(dir.path().join("blob"), b"\x7fELF\x02\x01").unwrap();
+
+    let server = TestServer::with_config(Config {
+        sniff_content_type: true,
+        ..dir_config(&dir)
+    });
+
+    assert_eq!(
+        content_type(&server, "/files/logo").as_deref(),
+        Some("image/png")
+    );
+    assert_eq!(
+        content_type(&server, "/files/LICENSE").as_deref(),
+        Some("text/plain; charset=utf-8")
+    );
+    assert_eq!(
+        content_type(&server, "/files/blob").as_deref(),
+        Some("application/octet-stream")
+    );
+}
+
+#[test]
+fn sniffing_is_opt_in() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("LICENSE"), "MIT License\n").unwrap();
+
+    let server = TestServer::with_config(dir_config(&dir));
+
+    assert_eq!(
+        content_type(&server, "/files/LICENSE").as_deref(),
+        Some("application/octet-stream")
+    );
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use common::{body, header, status_line, TestServer};
+use http_server_starter_rust::Config;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn options_has_allow_header_and_empty_body() {
+    let server = TestServer::start();
+    let res = server.send(b"OPTIONS /files/foo HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(
+        header(&res, "Allow").as_deref(),
+        Some("GET, HEAD, POST, PUT, DELETE, OPTIONS")
+    );
+    assert_eq!(header(&res, "Content-Length").as_deref(), Some("0"));
+    assert_eq!(header(&res, "Content-Type"), None);
+    assert_eq!(body(&res), b"");
+}
+
+#[test]
+fn options_for_read_only_route() {
+    let server = TestServer::start();
+    let res = server.send(b"OPTIONS /echo/abc HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(header(&res, "Allow").as_deref(), Some("GET, HEAD, OPTIONS"));
+}
+
+#[test]
+fn options_asterisk_form() {
+    let server = TestServer::start();
+    let res = server.send(b"OPTIONS * HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(
+        header(&res, "Allow").as_deref(),
+        Some("GET, HEAD, POST, PUT, DELETE, OPTIONS")
+    );
+}
+
+#[test]
+fn options_asterisk_form_leaves_out_unavailable_methods() {
+    let server = TestServer::with_config(Config {
+        read_only: true,
+        ..Config::default()
+    });
+    let res = server.send(b"OPTIONS * HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(header(&res, "Allow").as_deref(), Some("GET, HEAD, OPTIONS"));
+
+    // The admin route only exists with a token, and isn't a write.
+    let server = TestServer::with_config(Config {
+        read_only: true,
+        admin_token: Some("secret".to_string()),
+        ..Config::default()
+    });
+    let res = server.send(b"OPTIONS * HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(
+        header(&res, "Allow").as_deref(),
+        Some("GET, HEAD, POST, OPTIONS")
+    );
+}
+
+#[test]
+fn options_unknown_route() {
+    let server = TestServer::start();
+    let res = server.send(b"OPTIONS /unknown HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 404 Not Found");
+}
+
+#[test]
+fn options_follows_the_config() {
+    let server = TestServer::with_config(Config {
+        read_only: true,
+        stats: true,
+        ..Config::default()
+    });
+
+    for (path, allow) in [
+        ("/files/foo", "GET, HEAD, OPTIONS"),
+        ("/stats", "GET, HEAD, OPTIONS"),
+    ] {
+        let res =
+            server.send(format!("OPTIONS {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes());
+        assert_eq!(header(&res, "Allow").as_deref(), Some(allow), "{}", path);
+    }
+}
+
+#[test]
+fn unsupported_method_is_not_allowed() {
+    let server = TestServer::start();
+
+    for (req, allow) in [
+        ("DELETE /echo/hi", "GET, HEAD, OPTIONS"),
+        ("POST /user-agent", "GET, HEAD, OPTIONS"),
+        (
+            "PATCH /files/a.txt",
+            "GET, HEAD, POST, PUT, DELETE, OPTIONS",
+        ),
+    ] {
+        let res = server.send(format!("{} HTTP/1.1\r\nHost: localhost\r\n\r\n", req).as_bytes());
+
+        assert_eq!(
+            status_line(&res),
+            "HTTP/1.1 405 Method Not Allowed",
+            "{}",
+            req
+        );
+        assert_eq!(header(&res, "Allow").as_deref(), Some(allow), "{}", req);
+    }
+
+    let res = server.send(b"DELETE /unknown HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(status_line(&res), "HTTP/1.1 404 Not Found");
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use common::{body, status_line, TestServer};
+use http_server_starter_rust::{Config, DirEntry, FileSystem, Metadata};
+use pretty_assertions::assert_eq;
+
+// Panics on every file access, standing in for a bug in a handler.
+#[derive(Debug)]
+struct PanickingFileSystem;
+
+impl FileSystem for PanickingFileSystem {
+    fn read(&self, _: &Path) -> io::Result<Vec<u8>> {
+        panic!("read is broken")
+    }
+
+    fn write(&self, _: &Path) -> io::Result<Box<dyn io::Write>> {
+        panic!("write is broken")
+    }
+
+    fn metadata(&self, _: &Path) -> io::Result<Metadata> {
+        panic!("metadata is broken")
+    }
+
+    fn read_dir(&self, _: &Path) -> io::Result<Vec<DirEntry>> {
+        panic!("read_dir is broken")
+    }
+
+    fn rename(&self, _: &Path, _: &Path) -> io::Result<()> {
+        panic!("rename is broken")
+    }
+
+    fn remove_file(&self, _: &Path) -> io::Result<()> {
+        panic!("remove_file is broken")
+    }
+}
+
+#[test]
+fn panicking_handlers_do_not_reduce_capacity() {
+    let server = TestServer::with_config(Config {
+        directory: Some(".".to_string()),
+        filesystem: Arc::new(PanickingFileSystem),
+        ..Config::default()
+    });
+
+    for _ in 0..10 {
+        // The connection is closed without a response.
+        let res = server.send(b"GET /files/a.txt HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(res.is_empty(), "{:?}", String::from_utf8_lossy(&res));
+    }
+
+    let res = server.send(b"GET /echo/still-up HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(body(&res), b"still-up");
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use std::fs;
+
+use common::{body, status_line, TempDir, TestServer};
+use http_server_starter_rust::Config;
+use pretty_assertions::assert_eq;
+
+fn get(server: &TestServer, target: &str) -> Vec<u8> {
+    server.send(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target).as_bytes())
+}
+
+#[test]
+fn dot_segments_never_leave_the_directory() {
+    let dir = TempDir::new();
+    fs::create_dir(dir.path().join("public")).unwrap();
+    fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+    fs::write(dir.path().join("public").join("a.txt"), "public").unwrap();
+    let server = TestServer::with_config(Config {
+        directory: Some(dir.path().join("public").to_string_lossy().to_string()),
+        ..Config::default()
+    });
+
+    for target in [
+        "/files/../secret.txt",
+        "/files/../../secret.txt",
+        "/files/%2E%2E/secret.txt",
+        "/files/..%2Fsecret.txt",
+    ] {
+        let res = get(&server, target);
+        assert_eq!(status_line(&res), "HTTP/1.1 404 Not Found", "{}", target);
+        assert_ne!(body(&res), b"secret", "{}", target);
+    }
+
+    for target in ["/files/./a.txt", "/files/x/../a.txt", "//files//a.txt"] {
+        let res = get(&server, target);
+        assert_eq!(status_line(&res), "HTTP/1.1 200 OK", "{}", target);
+        assert_eq!(body(&res), b"public", "{}", target);
+    }
+}
+
+#[test]
+fn equivalent_paths_route_the_same() {
+    let server = TestServer::start();
+
+    for target in [
+        "/echo/a/b",
+        "//echo//a/b",
+        "/echo/./a/x/../b",
+        "/x/../echo/a/b",
+    ] {
+        let res = get(&server, target);
+        assert_eq!(status_line(&res), "HTTP/1.1 200 OK", "{}", target);
+        assert_eq!(body(&res), b"a/b", "{}", target);
+    }
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use std::fs;
+
+use common::{body, dir_config, header, status_line, TempDir, TestServer};
+use http_server_starter_rust::{Config, TrailingSlash};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn echo_is_percent_decoded() {
+    let server = TestServer::start();
+    let res = server.send(b"GET /echo/hello%20world HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(body(&res), b"hello world");
+}
+
+#[test]
+fn file_names_are_percent_decoded() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("my file.txt"), "spaced").unwrap();
+    let server = TestServer::with_config(dir_config(&dir));
+
+    let res = server.send(b"GET /files/my%20file.txt HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(body(&res), b"spaced");
+
+    let res = server.send(
+        b"POST /files/new%20file.txt HTTP/1.1\r\nHost: localhost\r\nContent-Length: 3\r\n\r\nabc",
+    );
+    assert_eq!(status_line(&res), "HTTP/1.1 201 Created");
+    assert_eq!(
+        header(&res, "Location").as_deref(),
+        Some("/files/new%20file.txt")
+    );
+    assert_eq!(fs::read(dir.path().join("new file.txt")).unwrap(), b"abc");
+}
+
+#[test]
+fn invalid_percent_encoding_is_a_bad_request() {
+    let server = TestServer::start();
+
+    for target in ["/echo/%zz", "/echo/%e9", "/echo/a%00b", "/echo/a%+fb"] {
+        let res = server.send_str(&format!(
+            "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n",
+            target
+        ));
+        assert_eq!(
+            status_line(res.as_bytes()),
+            "HTTP/1.1 400 Bad Request",
+            "{}",
+            target
+        );
+    }
+}
+
+#[test]
+fn trailing_slash_redirect_keeps_the_encoding() {
+    let server = TestServer::with_config(Config {
+        trailing_slash: TrailingSlash::Redirect,
+        ..Config::default()
+    });
+    let res = server.send(b"GET /echo/a%20b/?x=%20 HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 301 Moved Permanently");
+    assert_eq!(
+        header(&res, "Location").as_deref(),
+        Some("/echo/a%20b?x=%20")
+    );
+}
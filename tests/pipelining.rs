@@ -0,0 +1,104 @@
+mod common;
+
+use common::{dir_config, TempDir, TestServer};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn pipelined_get_requests_are_answered_in_order() {
+    let server = TestServer::start();
+    let res = server.send_str(
+        "GET /echo/first HTTP/1.1\r\nHost: localhost\r\n\r\n\
+         GET /echo/second HTTP/1.1\r\nHost: localhost\r\n\r\n",
+    );
+
+    assert_eq!(
+        res,
+        "HTTP/1.1 200 OK\r\nAccept-Ranges: none\r\nCache-Control: no-store\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: 5\r\n\r\nfirst\
+         HTTP/1.1 200 OK\r\nAccept-Ranges: none\r\nCache-Control: no-store\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: 6\r\n\r\nsecond"
+    );
+}
+
+#[test]
+fn body_does_not_swallow_pipelined_request() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = server.send_str(
+        "POST /files/a.txt HTTP/1.1\r\nHost: localhost\r\nContent-Length: 3\r\n\r\nabc\
+         GET /files/a.txt HTTP/1.1\r\nHost: localhost\r\n\r\n",
+    );
+
+    // The file validators depend on its modification time, leave them out.
+    let res: String = res
+        .split_inclusive("\r\n")
+        .filter(|line| !line.starts_with("ETag:") && !line.starts_with("Last-Modified:"))
+        .collect();
+    assert_eq!(
+        res,
+        "HTTP/1.1 201 Created\r\nLocation: /files/a.txt\r\nCache-Control: no-store\r\nContent-Length: 0\r\n\r\n\
+         HTTP/1.1 200 OK\r\nAccept-Ranges: bytes\r\nContent-Disposition: inline; filename=\"a.txt\"\r\nCache-Control: public, max-age=0\r\nContent-Type: application/octet-stream\r\nContent-Length: 3\r\n\r\nabc"
+    );
+}
+
+#[test]
+fn get_body_does_not_swallow_pipelined_request() {
+    let server = TestServer::start();
+
+    for first in [
+        "GET /echo/first HTTP/1.1\r\nHost: localhost\r\nContent-Length: 27\r\n\r\n\
+         GET /echo/smuggled HTTP/1.1",
+        "GET /echo/first HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n\
+         1b\r\nGET /echo/smuggled HTTP/1.1\r\n0\r\n\r\n",
+    ] {
+        let res = server.send_str(&format!(
+            "{}GET /echo/second HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            first
+        ));
+
+        let bodies: Vec<&str> = res
+            .split("HTTP/1.1 ")
+            .filter(|part| !part.is_empty())
+            .map(|part| part.rsplit("\r\n\r\n").next().unwrap())
+            .collect();
+        assert_eq!(bodies, ["first", "second"], "{}", res);
+    }
+}
+
+#[test]
+fn errors_and_bodiless_responses_keep_the_pipeline_in_order() {
+    let server = TestServer::start();
+    let res = server.send_str(
+        "GET /missing HTTP/1.1\r\nHost: localhost\r\n\r\n\
+         HEAD /echo/head HTTP/1.1\r\nHost: localhost\r\n\r\n\
+         DELETE /echo/x HTTP/1.1\r\nHost: localhost\r\n\r\n\
+         GET /echo/last HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    );
+
+    let status_lines: Vec<&str> = res
+        .split("\r\n")
+        .filter(|line| line.starts_with("HTTP/1.1 "))
+        .collect();
+    assert_eq!(
+        status_lines,
+        [
+            "HTTP/1.1 404 Not Found",
+            "HTTP/1.1 200 OK",
+            "HTTP/1.1 405 Method Not Allowed",
+            "HTTP/1.1 200 OK"
+        ]
+    );
+    assert!(res.ends_with("\r\n\r\nlast"), "{}", res);
+    assert!(!res.contains("head"), "{}", res);
+}
+
+#[test]
+fn extra_crlf_after_a_body_is_not_a_request() {
+    let server = TestServer::start();
+    let res = server.send_str(
+        "GET /echo/first HTTP/1.1\r\nHost: localhost\r\nContent-Length: 3\r\n\r\nabc\r\n\
+         GET /echo/second HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    );
+
+    assert_eq!(res.matches("HTTP/1.1 ").count(), 2, "{}", res);
+    assert!(res.contains("\r\n\r\nfirstHTTP/1.1 200 OK\r\n"), "{}", res);
+    assert!(res.ends_with("\r\n\r\nsecond"), "{}", res);
+}
@@ -0,0 +1,142 @@
+mod common;
+
+use std::fs;
+
+use common::{body, dir_config, header, status_line, TempDir, TestServer};
+use pretty_assertions::assert_eq;
+
+// Not a real gzip stream, the server serves the sidecar bytes verbatim.
+const FAKE_GZIP: &[u8] = b"\x1f\x8b\x08\x00compressed";
+
+#[test]
+fn serves_gz_sidecar_when_gzip_is_accepted() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("file.txt"), "plain contents").unwrap();
+    fs::write(dir.path().join("file.txt.gz"), FAKE_GZIP).unwrap();
+
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = server.send(
+        b"GET /files/file.txt HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: gzip, deflate\r\n\r\n",
+    );
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(header(&res, "Content-Encoding").as_deref(), Some("gzip"));
+    assert_eq!(header(&res, "Vary").as_deref(), Some("Accept-Encoding"));
+    assert_eq!(
+        header(&res, "Content-Type").as_deref(),
+        Some("application/octet-stream")
+    );
+    assert_eq!(body(&res), FAKE_GZIP);
+}
+
+#[test]
+fn serves_plain_file_when_gzip_is_not_accepted() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("file.txt"), "plain contents").unwrap();
+    fs::write(dir.path().join("file.txt.gz"), FAKE_GZIP).unwrap();
+
+    let server = TestServer::with_config(dir_config(&dir));
+
+    for accept in [
+        "",
+        "Accept-Encoding: br\r\n",
+        "Accept-Encoding: gzip;q=0\r\n",
+    ] {
+        let res = server.send_str(&format!(
+            "GET /files/file.txt HTTP/1.1\r\nHost: localhost\r\n{}\r\n",
+            accept
+        ));
+        let res = res.as_bytes();
+
+        assert_eq!(header(res, "Content-Encoding"), None, "{:?}", accept);
+        assert_eq!(body(res), b"plain contents", "{:?}", accept);
+    }
+}
+
+#[test]
+fn serves_plain_file_when_sidecar_is_absent() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("file.txt"), "plain contents").unwrap();
+
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = server
+        .send(b"GET /files/file.txt HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: gzip\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(header(&res, "Content-Encoding"), None);
+    assert_eq!(body(&res), b"plain contents");
+}
+
+#[test]
+fn sidecar_is_not_served_without_the_file() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("file.txt.gz"), FAKE_GZIP).unwrap();
+
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = server
+        .send(b"GET /files/file.txt HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: gzip\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 404 Not Found");
+}
+
+#[test]
+fn sidecar_has_its_own_validators() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("file.txt"), "plain contents").unwrap();
+    fs::write(dir.path().join("file.txt.gz"), FAKE_GZIP).unwrap();
+
+    let server = TestServer::with_config(dir_config(&dir));
+    let get = |headers: &str| {
+        server.send(
+            format!(
+                "GET /files/file.txt HTTP/1.1\r\nHost: localhost\r\n{}\r\n",
+                headers
+            )
+            .as_bytes(),
+        )
+    };
+
+    let plain = get("");
+    let gzipped = get("Accept-Encoding: gzip\r\n");
+    let plain_etag = header(&plain, "ETag").unwrap();
+    let etag = header(&gzipped, "ETag").unwrap();
+    assert_ne!(etag, plain_etag);
+    assert_eq!(header(&gzipped, "Accept-Ranges").as_deref(), Some("bytes"));
+    assert!(header(&gzipped, "Last-Modified").is_some());
+    // The plain file varies on Accept-Encoding too, as the sidecar exists.
+    assert_eq!(header(&plain, "Vary").as_deref(), Some("Accept-Encoding"));
+
+    let res = get(&format!(
+        "Accept-Encoding: gzip\r\nIf-None-Match: {}\r\n",
+        etag
+    ));
+    assert_eq!(status_line(&res), "HTTP/1.1 304 Not Modified");
+    assert_eq!(header(&res, "Content-Encoding").as_deref(), Some("gzip"));
+
+    // The plain file's tag doesn't validate the sidecar.
+    let res = get(&format!(
+        "Accept-Encoding: gzip\r\nIf-None-Match: {}\r\n",
+        plain_etag
+    ));
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+}
+
+#[test]
+fn ranges_apply_to_the_sidecar_bytes() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("file.txt"), "plain contents").unwrap();
+    fs::write(dir.path().join("file.txt.gz"), FAKE_GZIP).unwrap();
+
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = server.send(
+        b"GET /files/file.txt HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: gzip\r\nRange: bytes=0-3\r\n\r\n",
+    );
+
+    assert_eq!(status_line(&res), "HTTP/1.1 206 Partial Content");
+    assert_eq!(header(&res, "Content-Encoding").as_deref(), Some("gzip"));
+    assert_eq!(
+        header(&res, "Content-Range").as_deref(),
+        Some(format!("bytes 0-3/{}", FAKE_GZIP.len()).as_str())
+    );
+    assert_eq!(body(&res), &FAKE_GZIP[..4]);
+}
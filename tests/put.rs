@@ -0,0 +1,111 @@
+mod common;
+
+use std::fs;
+
+use common::{body, dir_config, header, status_line, TempDir, TestServer};
+use http_server_starter_rust::Config;
+use pretty_assertions::assert_eq;
+
+fn put(server: &TestServer, path: &str, contents: &str) -> Vec<u8> {
+    server.send(
+        format!(
+            "PUT {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            path,
+            contents.len(),
+            contents
+        )
+        .as_bytes(),
+    )
+}
+
+#[test]
+fn put_creates_then_replaces() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(dir_config(&dir));
+
+    let res = put(&server, "/files/doc.txt", "first");
+    assert_eq!(status_line(&res), "HTTP/1.1 201 Created");
+    assert_eq!(header(&res, "Location").as_deref(), Some("/files/doc.txt"));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("doc.txt")).unwrap(),
+        "first"
+    );
+
+    let res = put(&server, "/files/doc.txt", "second");
+    assert_eq!(status_line(&res), "HTTP/1.1 204 No Content");
+    assert_eq!(header(&res, "Location"), None);
+    assert_eq!(body(&res), b"");
+    assert_eq!(
+        fs::read_to_string(dir.path().join("doc.txt")).unwrap(),
+        "second"
+    );
+}
+
+#[test]
+fn put_is_idempotent() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(dir_config(&dir));
+
+    for _ in 0..3 {
+        put(&server, "/files/doc.txt", "same");
+    }
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("doc.txt")).unwrap(),
+        "same"
+    );
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn put_keeps_the_connection_in_sync() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(dir_config(&dir));
+
+    let res = server.send(
+        b"PUT /files/doc.txt HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello\
+          GET /files/doc.txt HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    );
+
+    let text = String::from_utf8(res).unwrap();
+    assert!(text.starts_with("HTTP/1.1 201 Created\r\n"), "{}", text);
+    assert!(text.ends_with("\r\n\r\nhello"), "{}", text);
+}
+
+#[test]
+fn failed_put_keeps_the_previous_file() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("a.txt"), "previous").unwrap();
+    let server = TestServer::with_config(Config {
+        max_body_size: Some(10),
+        ..dir_config(&dir)
+    });
+
+    for (chunks, status) in [
+        ("zz\r\nhello\r\n0\r\n\r\n", "HTTP/1.1 400 Bad Request"),
+        (
+            "b\r\nhello world\r\n0\r\n\r\n",
+            "HTTP/1.1 413 Payload Too Large",
+        ),
+        // Cut off before the last chunk.
+        ("5\r\nhello\r\n", "HTTP/1.1 400 Bad Request"),
+    ] {
+        let res = server.send(
+            format!(
+                "PUT /files/a.txt HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n{}",
+                chunks
+            )
+            .as_bytes(),
+        );
+
+        assert_eq!(status_line(&res), status, "{:?}", chunks);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "previous",
+            "{:?}",
+            chunks
+        );
+    }
+    // No temporary file is left behind either.
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
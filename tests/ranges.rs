@@ -0,0 +1,176 @@
+mod common;
+
+use std::fs;
+
+use common::{body, dir_config, header, status_line, TempDir, TestServer};
+use pretty_assertions::assert_eq;
+
+fn server_with_file(contents: &str) -> (TempDir, TestServer) {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("file.txt"), contents).unwrap();
+    let server = TestServer::with_config(dir_config(&dir));
+    (dir, server)
+}
+
+fn get(server: &TestServer, extra_headers: &str) -> Vec<u8> {
+    server.send(
+        format!(
+            "GET /files/file.txt HTTP/1.1\r\nHost: localhost\r\n{}\r\n",
+            extra_headers
+        )
+        .as_bytes(),
+    )
+}
+
+#[test]
+fn serves_a_byte_range() {
+    let (_dir, server) = server_with_file("0123456789");
+    let res = get(&server, "Range: bytes=2-5\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 206 Partial Content");
+    assert_eq!(
+        header(&res, "Content-Range").as_deref(),
+        Some("bytes 2-5/10")
+    );
+    assert_eq!(body(&res), b"2345");
+}
+
+#[test]
+fn serves_a_suffix_range() {
+    let (_dir, server) = server_with_file("0123456789");
+    let res = get(&server, "Range: bytes=-3\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 206 Partial Content");
+    assert_eq!(
+        header(&res, "Content-Range").as_deref(),
+        Some("bytes 7-9/10")
+    );
+    assert_eq!(body(&res), b"789");
+}
+
+#[test]
+fn serves_an_open_ended_range() {
+    let (_dir, server) = server_with_file("0123456789");
+    let res = get(&server, "Range: bytes=7-\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 206 Partial Content");
+    assert_eq!(
+        header(&res, "Content-Range").as_deref(),
+        Some("bytes 7-9/10")
+    );
+    assert_eq!(header(&res, "Content-Length").as_deref(), Some("3"));
+    assert_eq!(body(&res), b"789");
+}
+
+#[test]
+fn serves_multiple_ranges_as_multipart() {
+    let (_dir, server) = server_with_file("0123456789");
+    let res = get(&server, "Range: bytes=0-1,6-8\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 206 Partial Content");
+    assert_eq!(header(&res, "Content-Range"), None);
+
+    let content_type = header(&res, "Content-Type").unwrap();
+    let boundary = content_type
+        .strip_prefix("multipart/byteranges; boundary=")
+        .unwrap_or_else(|| panic!("unexpected content type {}", content_type));
+
+    let body = String::from_utf8(body(&res)).unwrap();
+    assert_eq!(
+        body,
+        format!(
+            "--{b}\r\nContent-Type: application/octet-stream\r\nContent-Range: bytes 0-1/10\r\n\r\n01\r\n\
+             --{b}\r\nContent-Type: application/octet-stream\r\nContent-Range: bytes 6-8/10\r\n\r\n678\r\n\
+             --{b}--\r\n",
+            b = boundary
+        )
+    );
+}
+
+#[test]
+fn overlapping_ranges_are_merged() {
+    let (_dir, server) = server_with_file("0123456789");
+    let res = get(&server, "Range: bytes=1-4,3-6\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 206 Partial Content");
+    assert_eq!(
+        header(&res, "Content-Range").as_deref(),
+        Some("bytes 1-6/10")
+    );
+    assert_eq!(body(&res), b"123456");
+}
+
+#[test]
+fn unsatisfiable_range() {
+    let (_dir, server) = server_with_file("0123456789");
+    let res = get(&server, "Range: bytes=10-\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 416 Range Not Satisfiable");
+    assert_eq!(header(&res, "Content-Range").as_deref(), Some("bytes */10"));
+}
+
+#[test]
+fn if_range_with_matching_etag_serves_the_range() {
+    let (_dir, server) = server_with_file("0123456789");
+    let etag = header(&get(&server, ""), "ETag").unwrap();
+
+    let res = get(
+        &server,
+        &format!("Range: bytes=5-\r\nIf-Range: {}\r\n", etag),
+    );
+
+    assert_eq!(status_line(&res), "HTTP/1.1 206 Partial Content");
+    assert_eq!(body(&res), b"56789");
+}
+
+#[test]
+fn if_range_with_stale_etag_serves_the_full_file() {
+    let (_dir, server) = server_with_file("0123456789");
+
+    for validator in ["\"stale\"", "W/\"stale\""] {
+        let res = get(
+            &server,
+            &format!("Range: bytes=5-\r\nIf-Range: {}\r\n", validator),
+        );
+
+        assert_eq!(status_line(&res), "HTTP/1.1 200 OK", "{}", validator);
+        assert_eq!(body(&res), b"0123456789");
+    }
+}
+
+#[test]
+fn if_range_with_matching_date_serves_the_range() {
+    let (_dir, server) = server_with_file("0123456789");
+    let last_modified = header(&get(&server, ""), "Last-Modified").unwrap();
+
+    let res = get(
+        &server,
+        &format!("Range: bytes=0-1\r\nIf-Range: {}\r\n", last_modified),
+    );
+
+    assert_eq!(status_line(&res), "HTTP/1.1 206 Partial Content");
+    assert_eq!(body(&res), b"01");
+}
+
+#[test]
+fn if_range_with_stale_date_serves_the_full_file() {
+    let (_dir, server) = server_with_file("0123456789");
+    let res = get(
+        &server,
+        "Range: bytes=0-1\r\nIf-Range: Sun, 06 Nov 1994 08:49:37 GMT\r\n",
+    );
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(body(&res), b"0123456789");
+}
+
+#[test]
+fn file_responses_advertise_byte_ranges() {
+    let (_dir, server) = server_with_file("0123456789");
+    let res = get(&server, "");
+
+    assert_eq!(header(&res, "Accept-Ranges").as_deref(), Some("bytes"));
+
+    let res = server.send(b"GET /echo/abc HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(header(&res, "Accept-Ranges").as_deref(), Some("none"));
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use common::{header, status_line, TestServer};
+use http_server_starter_rust::Config;
+use pretty_assertions::assert_eq;
+
+fn rate_limited(rate: f64) -> TestServer {
+    TestServer::with_config(Config {
+        rate_limit: Some(rate),
+        ..Config::default()
+    })
+}
+
+#[test]
+fn flooding_client_gets_too_many_requests() {
+    let server = rate_limited(2.0);
+    let res = server.send_str(&"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n".repeat(5));
+
+    // The first two requests fit in the bucket, the third one is rejected
+    // and the connection closed, so the rest are never answered.
+    let statuses: Vec<&str> = res
+        .split("\r\n")
+        .filter(|line| line.starts_with("HTTP/1.1 "))
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![
+            "HTTP/1.1 200 OK",
+            "HTTP/1.1 200 OK",
+            "HTTP/1.1 429 Too Many Requests"
+        ]
+    );
+    let rejected = &res[res.find("HTTP/1.1 429").unwrap()..];
+    assert_eq!(
+        header(rejected.as_bytes(), "Retry-After"),
+        Some("1".to_string())
+    );
+    assert_eq!(
+        header(rejected.as_bytes(), "Connection"),
+        Some("close".to_string())
+    );
+}
+
+#[test]
+fn limit_applies_across_connections() {
+    let server = rate_limited(1.0);
+
+    let res = server.send_str("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(status_line(res.as_bytes()), "HTTP/1.1 200 OK");
+
+    let res = server.send_str("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert_eq!(
+        status_line(res.as_bytes()),
+        "HTTP/1.1 429 Too Many Requests"
+    );
+}
+
+fn forwarded_for(server: &TestServer, ip: &str) -> String {
+    let res = server.send_str(&format!(
+        "GET / HTTP/1.1\r\nHost: localhost\r\nX-Forwarded-For: {}, 10.0.0.1\r\n\r\n",
+        ip
+    ));
+    status_line(res.as_bytes())
+}
+
+#[test]
+fn trusted_proxy_limits_each_forwarded_client() {
+    let server = TestServer::with_config(Config {
+        rate_limit: Some(1.0),
+        trust_proxy: true,
+        ..Config::default()
+    });
+
+    assert_eq!(forwarded_for(&server, "203.0.113.1"), "HTTP/1.1 200 OK");
+    assert_eq!(forwarded_for(&server, "203.0.113.2"), "HTTP/1.1 200 OK");
+    assert_eq!(
+        forwarded_for(&server, "203.0.113.1"),
+        "HTTP/1.1 429 Too Many Requests"
+    );
+}
+
+#[test]
+fn forwarded_for_is_ignored_without_trusted_proxy() {
+    let server = rate_limited(1.0);
+
+    assert_eq!(forwarded_for(&server, "203.0.113.1"), "HTTP/1.1 200 OK");
+    assert_eq!(
+        forwarded_for(&server, "203.0.113.2"),
+        "HTTP/1.1 429 Too Many Requests"
+    );
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use std::fs;
+
+use common::{body, dir_config, status_line, TempDir, TestServer};
+use http_server_starter_rust::Config;
+use pretty_assertions::assert_eq;
+
+const UPLOAD: &[u8] =
+    b"POST /files/new.txt HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello";
+
+#[test]
+fn post_is_forbidden_in_read_only_mode() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(Config {
+        read_only: true,
+        ..dir_config(&dir)
+    });
+    let res = server.send(UPLOAD);
+
+    assert_eq!(status_line(&res), "HTTP/1.1 403 Forbidden");
+    assert!(!dir.path().join("new.txt").exists());
+}
+
+#[test]
+fn post_is_allowed_when_not_read_only() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = server.send(UPLOAD);
+
+    assert_eq!(status_line(&res), "HTTP/1.1 201 Created");
+    assert_eq!(fs::read(dir.path().join("new.txt")).unwrap(), b"hello");
+}
+
+#[test]
+fn get_still_works_in_read_only_mode() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("file.txt"), "contents").unwrap();
+    let server = TestServer::with_config(Config {
+        read_only: true,
+        ..dir_config(&dir)
+    });
+    let res = server.send(b"GET /files/file.txt HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(body(&res), b"contents");
+}
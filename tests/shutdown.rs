@@ -0,0 +1,43 @@
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+
+use http_server_starter_rust::{Config, Server};
+
+#[test]
+fn shutdown_waits_for_in_flight_requests() {
+    let server = Server::bind("127.0.0.1:0", Config::default()).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle().unwrap();
+    let running = thread::spawn(move || server.run());
+
+    // A request whose body is still on its way.
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .write_all(b"POST /echo/slow HTTP/1.1\r\nHost: localhost\r\nContent-Length: 4\r\n\r\nab")
+        .unwrap();
+    thread::sleep(Duration::from_millis(100));
+
+    handle.shutdown();
+    thread::sleep(Duration::from_millis(200));
+    assert!(
+        !running.is_finished(),
+        "server exited with a request in flight"
+    );
+
+    stream.write_all(b"cd").unwrap();
+    let mut res = String::new();
+    stream.read_to_string(&mut res).unwrap();
+
+    // The request is answered, and the connection closed since the server is
+    // going away.
+    assert!(
+        res.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"),
+        "{}",
+        res
+    );
+    assert!(res.contains("Connection: close\r\n"), "{}", res);
+
+    running.join().unwrap();
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use std::fs;
+
+use common::{body, header, status_line, TempDir, TestServer};
+use http_server_starter_rust::Config;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn reports_file_count_and_total_size() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("a.txt"), "hello").unwrap();
+    fs::create_dir(dir.path().join("nested")).unwrap();
+    fs::write(dir.path().join("nested").join("b.bin"), [0u8; 10]).unwrap();
+
+    let server = TestServer::with_config(Config {
+        directory: Some(dir.path_string()),
+        stats: true,
+        ..Config::default()
+    });
+    let res = server.send(b"GET /stats HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(
+        header(&res, "Content-Type").as_deref(),
+        Some("application/json")
+    );
+
+    let body = String::from_utf8(body(&res)).unwrap();
+    assert!(body.contains(r#""file_count":2"#), "{}", body);
+    assert!(body.contains(r#""total_bytes":15"#), "{}", body);
+    assert!(body.contains(r#""directory":"#), "{}", body);
+}
+
+#[test]
+fn not_found_without_directory() {
+    let server = TestServer::with_config(Config {
+        stats: true,
+        ..Config::default()
+    });
+    let res = server.send(b"GET /stats HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 404 Not Found");
+}
+
+#[test]
+fn not_found_when_disabled() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(Config {
+        directory: Some(dir.path_string()),
+        ..Config::default()
+    });
+    let res = server.send(b"GET /stats HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 404 Not Found");
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_are_not_followed() {
+    let dir = TempDir::new();
+    fs::write(dir.path().join("a.txt"), "hello").unwrap();
+    // A loop back to the directory itself, and a dangling link.
+    std::os::unix::fs::symlink(dir.path(), dir.path().join("loop")).unwrap();
+    std::os::unix::fs::symlink(dir.path().join("missing"), dir.path().join("dangling")).unwrap();
+
+    let server = TestServer::with_config(Config {
+        directory: Some(dir.path_string()),
+        stats: true,
+        ..Config::default()
+    });
+    let res = server.send(b"GET /stats HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    let body = String::from_utf8(body(&res)).unwrap();
+    assert!(body.contains(r#""file_count":1"#), "{}", body);
+    assert!(body.contains(r#""total_bytes":5"#), "{}", body);
+}
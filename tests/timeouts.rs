@@ -0,0 +1,50 @@
+mod common;
+
+use std::io::Write;
+use std::thread;
+use std::time::Duration;
+
+use common::{read_until_closed, status_line, TestServer};
+use http_server_starter_rust::Config;
+use pretty_assertions::assert_eq;
+
+fn server_with_timeout(timeout: Duration) -> TestServer {
+    TestServer::with_config(Config {
+        request_timeout: Some(timeout),
+        ..Config::default()
+    })
+}
+
+#[test]
+fn slow_request_head_times_out() {
+    let server = server_with_timeout(Duration::from_millis(300));
+    let mut stream = server.connect();
+
+    // Every line arrives well within any per-read timeout, but the whole
+    // head takes longer than the budget.
+    stream.write_all(b"GET / HTTP/1.1\r\n").unwrap();
+    for i in 0..20 {
+        thread::sleep(Duration::from_millis(50));
+        if write!(stream, "X-Slow-{}: x\r\n", i).is_err() {
+            break;
+        }
+    }
+
+    let res = read_until_closed(&mut stream);
+    assert_eq!(status_line(&res), "HTTP/1.1 408 Request Timeout");
+}
+
+#[test]
+fn idle_connection_is_not_a_slow_request() {
+    let server = server_with_timeout(Duration::from_millis(200));
+    let mut stream = server.connect();
+
+    // Waiting before the first byte doesn't count towards the budget.
+    thread::sleep(Duration::from_millis(400));
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .unwrap();
+
+    let res = read_until_closed(&mut stream);
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+}
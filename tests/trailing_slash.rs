@@ -0,0 +1,65 @@
+mod common;
+
+use common::{body, header, status_line, TestServer};
+use http_server_starter_rust::{Config, TrailingSlash};
+use pretty_assertions::assert_eq;
+
+fn server(policy: TrailingSlash) -> TestServer {
+    TestServer::with_config(Config {
+        trailing_slash: policy,
+        ..Config::default()
+    })
+}
+
+#[test]
+fn ignore_matches_user_agent_with_trailing_slash() {
+    let server = server(TrailingSlash::Ignore);
+    let res = server
+        .send(b"GET /user-agent/ HTTP/1.1\r\nHost: localhost\r\nUser-Agent: curl/8.0\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(body(&res), b"curl/8.0");
+}
+
+#[test]
+fn ignore_drops_trailing_slash_from_echo() {
+    let server = server(TrailingSlash::Ignore);
+    let res = server.send(b"GET /echo/foo/ HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+    assert_eq!(body(&res), b"foo");
+}
+
+#[test]
+fn redirect_points_to_path_without_slash() {
+    let server = server(TrailingSlash::Redirect);
+    let res = server.send(b"GET /user-agent/ HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 301 Moved Permanently");
+    assert_eq!(header(&res, "Location").as_deref(), Some("/user-agent"));
+}
+
+#[test]
+fn redirect_keeps_query_string() {
+    let server = server(TrailingSlash::Redirect);
+    let res = server.send(b"GET /echo/foo/?a=1 HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 301 Moved Permanently");
+    assert_eq!(header(&res, "Location").as_deref(), Some("/echo/foo?a=1"));
+}
+
+#[test]
+fn strict_does_not_match_trailing_slash() {
+    let server = server(TrailingSlash::Strict);
+    let res = server.send(b"GET /user-agent/ HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 404 Not Found");
+}
+
+#[test]
+fn root_is_never_redirected() {
+    let server = server(TrailingSlash::Redirect);
+    let res = server.send(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+}
@@ -0,0 +1,192 @@
+mod common;
+
+use std::fs;
+
+use common::{body, dir_config, status_line, TempDir, TestServer};
+use http_server_starter_rust::{gzip, json, Config};
+use pretty_assertions::assert_eq;
+
+// Splits the data into chunks of the given size, chunked-encoded.
+fn chunked(data: &[u8], size: usize) -> Vec<u8> {
+    let mut out = vec![];
+    for chunk in data.chunks(size) {
+        out.extend_from_slice(format!("{:x}\r\n", chunk.len()).as_bytes());
+        out.extend_from_slice(chunk);
+        out.extend_from_slice(b"\r\n");
+    }
+    out.extend_from_slice(b"0\r\n\r\n");
+    out
+}
+
+fn post(server: &TestServer, transfer_encoding: &str, body: &[u8]) -> Vec<u8> {
+    let mut raw = format!(
+        "POST /files/upload.txt HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: {}\r\n\r\n",
+        transfer_encoding
+    )
+    .into_bytes();
+    raw.extend_from_slice(body);
+    server.send(&raw)
+}
+
+#[test]
+fn chunked_upload_is_decoded() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = post(&server, "chunked", &chunked(b"hello, chunked world", 6));
+
+    assert_eq!(status_line(&res), "HTTP/1.1 201 Created");
+    assert_eq!(
+        fs::read(dir.path().join("upload.txt")).unwrap(),
+        b"hello, chunked world"
+    );
+}
+
+#[test]
+fn chunk_extensions_and_trailers_are_skipped() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = post(
+        &server,
+        "chunked",
+        b"5;name=value\r\nhello\r\n7;a;b=\"c\"\r\n, world\r\n0;last\r\nX-Checksum: 1\r\n\r\n",
+    );
+
+    assert_eq!(status_line(&res), "HTTP/1.1 201 Created");
+    assert_eq!(
+        fs::read(dir.path().join("upload.txt")).unwrap(),
+        b"hello, world"
+    );
+}
+
+#[test]
+fn declared_trailers_are_merged_into_the_headers() {
+    let server = TestServer::with_config(Config {
+        dev: true,
+        ..Config::default()
+    });
+    let res = server.send(
+        b"GET /debug/headers HTTP/1.1\r\n\
+          Host: localhost\r\n\
+          Transfer-Encoding: chunked\r\n\
+          Trailer: X-Checksum, Content-Type\r\n\
+          \r\n\
+          5\r\nhello\r\n0\r\n\
+          X-Checksum: abc\r\n\
+          Content-Type: text/html\r\n\
+          X-Undeclared: 1\r\n\
+          \r\n",
+    );
+    assert_eq!(status_line(&res), "HTTP/1.1 200 OK");
+
+    let headers = json::parse(&String::from_utf8(body(&res)).unwrap()).unwrap();
+    assert_eq!(
+        headers
+            .get("X-Checksum")
+            .and_then(|v| v.as_array())
+            .map(|v| v.len()),
+        Some(1)
+    );
+    assert!(headers.get("Content-Type").is_none());
+    assert!(headers.get("X-Undeclared").is_none());
+}
+
+#[test]
+fn gzip_then_chunked_upload_is_decoded() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(dir_config(&dir));
+    let text = "layered transfer codings ".repeat(100);
+    let res = post(
+        &server,
+        "gzip, chunked",
+        &chunked(&gzip::encode(text.as_bytes(), 6), 100),
+    );
+
+    assert_eq!(status_line(&res), "HTTP/1.1 201 Created");
+    assert_eq!(
+        fs::read_to_string(dir.path().join("upload.txt")).unwrap(),
+        text
+    );
+}
+
+#[test]
+fn pipelined_request_after_chunked_body() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(dir_config(&dir));
+
+    let mut raw =
+        b"POST /files/a.txt HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n"
+            .to_vec();
+    raw.extend_from_slice(&chunked(b"abc", 2));
+    raw.extend_from_slice(b"GET /files/a.txt HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    let res = String::from_utf8(server.send(&raw)).unwrap();
+
+    assert!(res.starts_with("HTTP/1.1 201 Created\r\n"), "{}", res);
+    assert!(res.ends_with("\r\n\r\nabc"), "{}", res);
+}
+
+#[test]
+fn unknown_transfer_coding_is_not_implemented() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(dir_config(&dir));
+    let res = post(&server, "br, chunked", &chunked(b"abc", 3));
+
+    assert_eq!(status_line(&res), "HTTP/1.1 501 Not Implemented");
+    assert!(!dir.path().join("upload.txt").exists());
+}
+
+#[test]
+fn chunked_must_be_the_final_coding() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(dir_config(&dir));
+
+    for transfer_encoding in ["gzip", "chunked, gzip", "chunked, chunked"] {
+        let res = post(&server, transfer_encoding, b"abc");
+        assert_eq!(
+            status_line(&res),
+            "HTTP/1.1 400 Bad Request",
+            "{}",
+            transfer_encoding
+        );
+    }
+}
+
+#[test]
+fn malformed_chunks_are_a_bad_request() {
+    let dir = TempDir::new();
+    let server = TestServer::with_config(dir_config(&dir));
+    for body in [&b"zz\r\nabc\r\n0\r\n\r\n"[..], b"+3\r\nabc\r\n0\r\n\r\n"] {
+        let res = post(&server, "chunked", body);
+
+        assert_eq!(status_line(&res), "HTTP/1.1 400 Bad Request", "{:?}", body);
+        assert!(!dir.path().join("upload.txt").exists());
+    }
+}
+
+#[test]
+fn content_length_with_transfer_encoding_is_rejected() {
+    let server = TestServer::start();
+    // Read by Content-Length, the body would end after "0\r\n\r\n" and the
+    // smuggled request would be answered.
+    let res = server.send_str(
+        "POST /echo/a HTTP/1.1\r\nHost: localhost\r\n\
+         Content-Length: 5\r\nTransfer-Encoding: chunked\r\n\r\n\
+         0\r\n\r\n\
+         GET /echo/smuggled HTTP/1.1\r\nHost: localhost\r\n\r\n",
+    );
+
+    assert!(res.starts_with("HTTP/1.1 400 Bad Request\r\n"), "{}", res);
+    assert!(res.contains("Connection: close\r\n"), "{}", res);
+    assert!(!res.contains("smuggled"), "{}", res);
+}
+
+#[test]
+fn conflicting_content_lengths_are_rejected() {
+    let server = TestServer::start();
+    let res = server.send_str(
+        "POST /echo/a HTTP/1.1\r\nHost: localhost\r\n\
+         Content-Length: 3\r\nContent-Length: 30\r\n\r\nabc",
+    );
+
+    assert!(res.starts_with("HTTP/1.1 400 Bad Request\r\n"), "{}", res);
+    assert!(res.contains("Connection: close\r\n"), "{}", res);
+}
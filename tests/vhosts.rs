@@ -0,0 +1,49 @@
+mod common;
+
+use std::fs;
+
+use common::{body, status_line, TempDir, TestServer};
+use http_server_starter_rust::Config;
+use pretty_assertions::assert_eq;
+
+fn get(server: &TestServer, host: &str) -> Vec<u8> {
+    server.send(format!("GET /files/index.txt HTTP/1.1\r\nHost: {}\r\n\r\n", host).as_bytes())
+}
+
+#[test]
+fn files_resolve_per_host() {
+    let default = TempDir::new();
+    let site_a = TempDir::new();
+    let site_b = TempDir::new();
+    fs::write(default.path().join("index.txt"), "default").unwrap();
+    fs::write(site_a.path().join("index.txt"), "site a").unwrap();
+    fs::write(site_b.path().join("index.txt"), "site b").unwrap();
+
+    let server = TestServer::with_config(Config {
+        directory: Some(default.path_string()),
+        vhosts: vec![
+            ("a.test".to_string(), site_a.path_string()),
+            ("b.test".to_string(), site_b.path_string()),
+        ],
+        ..Config::default()
+    });
+
+    assert_eq!(body(&get(&server, "a.test")), b"site a");
+    assert_eq!(body(&get(&server, "B.TEST:4221")), b"site b");
+    assert_eq!(body(&get(&server, "other.test")), b"default");
+}
+
+#[test]
+fn uploads_go_to_the_vhost_directory() {
+    let site = TempDir::new();
+
+    let server = TestServer::with_config(Config {
+        vhosts: vec![("a.test".to_string(), site.path_string())],
+        ..Config::default()
+    });
+    let res =
+        server.send(b"POST /files/new.txt HTTP/1.1\r\nHost: a.test\r\nContent-Length: 2\r\n\r\nhi");
+
+    assert_eq!(status_line(&res), "HTTP/1.1 201 Created");
+    assert_eq!(fs::read(site.path().join("new.txt")).unwrap(), b"hi");
+}